@@ -0,0 +1,43 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It times the consistency check at each security level.
+//!
+//! Run with `cargo bench --bench consistency [-- n]` (n defaults to 1024).
+//! The multi-exponentiations skip the windows in which every challenge is
+//! zero, so they take time proportional to the length of the challenges.
+use pointproofs_paramgen::consistency::{ConsistencyChecker, SecurityLevel};
+use pointproofs_paramgen::{generate, init_alpha};
+use std::time::Instant;
+
+// Runs of each check; the median is reported
+const RUNS: usize = 5;
+
+fn main() {
+    let n = std::env::args()
+        .skip(1)
+        .find_map(|a| a.parse::<usize>().ok())
+        .unwrap_or(1024);
+    println!("generating params with n = {}", n);
+    let params = generate(init_alpha(), n);
+    for &level in [
+        SecurityLevel::Bits64,
+        SecurityLevel::Bits128,
+        SecurityLevel::Full,
+    ]
+    .iter()
+    {
+        let checker = ConsistencyChecker::new().with_security_level(level);
+        let mut times: Vec<u128> = (0..RUNS)
+            .map(|_| {
+                let start = Instant::now();
+                assert!(checker.check(&params));
+                start.elapsed().as_millis()
+            })
+            .collect();
+        times.sort_unstable();
+        println!(
+            "--security {:<5} {:>6} ms",
+            level.to_string(),
+            times[RUNS / 2]
+        );
+    }
+}
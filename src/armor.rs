@@ -0,0 +1,258 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines an ASCII armor for the small binary objects that participants
+//! post to the ceremony's mailing list: receipts, proofs and digests.
+//!
+//! The armor follows OpenPGP (RFC 4880, section 6.2):
+//! ```text
+//! -----BEGIN POINTPROOFS PROOF-----
+//! Mode: schnorr
+//!
+//! <base64, 64 characters per line>
+//! =<base64 of the CRC-24 of the data>
+//! -----END POINTPROOFS PROOF-----
+//! ```
+//! Decoding skips any text around the block, such as the rest of an email,
+//! and tolerates what mail transport does to text: CRLF line endings,
+//! trailing whitespace and a `> ` quoting prefix.
+use crate::digest::{from_hex, Digest};
+use crate::error::{Error, Result};
+use crate::format::ProofMode;
+use crate::ContributionProof;
+use std::fmt;
+use std::io::Read;
+
+// Characters of base64 per line
+const LINE_LENGTH: usize = 64;
+
+/// What an armored block holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArmorKind {
+    /// The text of a receipt.
+    Receipt,
+    /// The compressed serialization of a contribution proof; the `Mode`
+    /// header says which kind.
+    Proof,
+    /// A SHA-512 digest.
+    Digest,
+}
+
+impl ArmorKind {
+    fn label(self) -> &'static str {
+        match self {
+            ArmorKind::Receipt => "POINTPROOFS RECEIPT",
+            ArmorKind::Proof => "POINTPROOFS PROOF",
+            ArmorKind::Digest => "POINTPROOFS DIGEST",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        [ArmorKind::Receipt, ArmorKind::Proof, ArmorKind::Digest]
+            .iter()
+            .copied()
+            .find(|k| k.label() == label)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Armored {
+    pub kind: ArmorKind,
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+impl Armored {
+    pub fn new(kind: ArmorKind, data: Vec<u8>) -> Self {
+        Armored {
+            kind,
+            headers: vec![],
+            data,
+        }
+    }
+
+    /// Adds a header; names and values must fit on one line.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The value of the first header with this name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Decodes the first armored block in `text`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines().map(clean_line);
+        let kind = loop {
+            match lines.next() {
+                None => return Err(bad("no BEGIN line")),
+                Some(line) => {
+                    if let Some(label) = line
+                        .strip_prefix("-----BEGIN ")
+                        .and_then(|l| l.strip_suffix("-----"))
+                    {
+                        break ArmorKind::from_label(label)
+                            .ok_or_else(|| bad(&format!("unknown kind {:?}", label)))?;
+                    }
+                }
+            }
+        };
+
+        let mut headers = vec![];
+        loop {
+            match lines.next() {
+                None => return Err(bad("no END line")),
+                Some("") => break,
+                Some(line) => match line.find(": ") {
+                    Some(i) => headers.push((line[..i].to_string(), line[i + 2..].to_string())),
+                    None => return Err(bad("malformed header line")),
+                },
+            }
+        }
+
+        let end = format!("-----END {}-----", kind.label());
+        let mut body = String::new();
+        let mut checksum = None;
+        loop {
+            match lines.next() {
+                None => return Err(bad("no END line")),
+                Some(line) if line == end => break,
+                Some(line) if line.starts_with('=') => checksum = Some(line[1..].to_string()),
+                Some(line) => body.push_str(line),
+            }
+        }
+        let data = base64::decode(&body).map_err(|_| bad("the body is not base64"))?;
+        match checksum {
+            Some(c) if c == base64::encode(crc24(&data)) => Ok(Armored {
+                kind,
+                headers,
+                data,
+            }),
+            Some(_) => Err(bad("checksum mismatch; the text was damaged in transit")),
+            None => Err(bad("no checksum")),
+        }
+    }
+
+    /// Decodes the armored block in `text`, which must be of this kind.
+    pub fn parse_kind(text: &str, kind: ArmorKind) -> Result<Self> {
+        let armored = Armored::parse(text)?;
+        if armored.kind != kind {
+            return Err(bad(&format!(
+                "expected {} but found {}",
+                kind.label(),
+                armored.kind.label()
+            )));
+        }
+        Ok(armored)
+    }
+}
+
+impl fmt::Display for Armored {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "-----BEGIN {}-----", self.kind.label())?;
+        for (name, value) in self.headers.iter() {
+            writeln!(f, "{}: {}", name, value)?;
+        }
+        writeln!(f)?;
+        let body = base64::encode(&self.data);
+        for i in (0..body.len()).step_by(LINE_LENGTH) {
+            writeln!(f, "{}", &body[i..body.len().min(i + LINE_LENGTH)])?;
+        }
+        writeln!(f, "={}", base64::encode(crc24(&self.data)))?;
+        writeln!(f, "-----END {}-----", self.kind.label())
+    }
+}
+
+/// Armors a contribution proof, recording its mode in the `Mode` header.
+pub fn armor_proof(proof: &ContributionProof) -> Result<Armored> {
+    let mut data = vec![];
+    proof.serialize(&mut data, true)?;
+    Ok(Armored::new(ArmorKind::Proof, data).with_header("Mode", &proof.mode().to_string()))
+}
+
+/// Decodes an armored proof, which must be of the given mode.
+pub fn dearmor_proof(armored: &Armored, mode: ProofMode) -> Result<ContributionProof> {
+    if armored.kind != ArmorKind::Proof || armored.header("Mode") != Some(&mode.to_string()) {
+        return Err(bad(&format!("expected a proof in {} mode", mode)));
+    }
+    ContributionProof::deserialize(&mut &armored.data[..], mode, true)
+}
+
+pub fn armor_digest(digest: &Digest) -> Armored {
+    Armored::new(ArmorKind::Digest, digest.to_vec())
+}
+
+/// Parses a digest given either in hex or armored.
+pub fn parse_digest(text: &str) -> Result<Digest> {
+    let bytes = if text.contains("-----BEGIN ") {
+        Armored::parse_kind(text, ArmorKind::Digest)?.data
+    } else {
+        from_hex(text.trim()).ok_or_else(|| bad("the digest is neither hex nor armored"))?
+    };
+    if bytes.len() != 64 {
+        return Err(bad("a digest must be 64 bytes"));
+    }
+    let mut digest = [0u8; 64];
+    digest.copy_from_slice(&bytes);
+    Ok(digest)
+}
+
+// Longest armored proof read from a contribution file
+const MAX_ARMORED_PROOF: usize = 1 << 16;
+
+/// Reads an armored proof up to and including its END line, leaving
+/// anything after it, such as a signature, in the reader.
+pub(crate) fn read_armored_proof<R: Read>(r: &mut R, mode: ProofMode) -> Result<ContributionProof> {
+    let end = format!("-----END {}-----", ArmorKind::Proof.label());
+    let mut text = vec![];
+    let mut byte = [0u8; 1];
+    loop {
+        r.read_exact(&mut byte)?;
+        text.push(byte[0]);
+        if byte[0] == b'\n' {
+            let line_start = text[..text.len() - 1]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+            let line = String::from_utf8_lossy(&text[line_start..]);
+            if clean_line(&line) == end {
+                break;
+            }
+        }
+        if text.len() > MAX_ARMORED_PROOF {
+            return Err(bad("the armored proof is too long"));
+        }
+    }
+    let text = String::from_utf8(text).map_err(|_| bad("the armored proof is not text"))?;
+    dearmor_proof(&Armored::parse(&text)?, mode)
+}
+
+// Undoes what mail transport may have done to a line
+fn clean_line(line: &str) -> &str {
+    let line = line.trim_end();
+    line.strip_prefix("> ")
+        .or_else(|| line.strip_prefix('>'))
+        .unwrap_or(line)
+}
+
+// The CRC-24 of RFC 4880, section 6.1
+fn crc24(data: &[u8]) -> [u8; 3] {
+    let mut crc: u32 = 0x00b7_04ce;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= 0x0186_4cfb;
+            }
+        }
+    }
+    [(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]
+}
+
+fn bad(what: &str) -> Error {
+    Error::Format(format!("Bad armor: {}", what))
+}
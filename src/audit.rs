@@ -0,0 +1,100 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines an audit that looks for trivially known values of alpha.
+//!
+//! `consistent` only rules out alpha in {0, 1}. The audit here additionally
+//! looks for alpha = k or alpha = k * init_alpha for small k (positive or
+//! negative), using a baby-step/giant-step search on g2^alpha, and for alpha
+//! equal to the hash of a few well-known strings. Passing the audit does not
+//! prove that alpha is unknown, but failing it proves that alpha is known.
+use crate::hash_to_field_pointproofs::hash_to_field_pointproofs;
+use crate::small_exponent::SmallExponentTable;
+use crate::{derive_rerandomization_scalar, init_alpha, INIT_ALPHA_SEED};
+use pairing_plus::bls12_381::{Fr, G2Affine};
+use pairing_plus::{CurveAffine, CurveProjective};
+use std::fmt;
+
+/// A reason to believe that alpha is known.
+#[derive(Debug, PartialEq)]
+pub enum WeakAlpha {
+    /// alpha = k for a small k.
+    SmallExponent(i128),
+    /// alpha = k * init_alpha for a small k, e.g. because nobody contributed.
+    SmallMultipleOfInit(i128),
+    /// alpha is derived from a well-known string.
+    WellKnown(&'static str),
+}
+
+impl fmt::Display for WeakAlpha {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeakAlpha::SmallExponent(k) => write!(f, "alpha = {}", k),
+            WeakAlpha::SmallMultipleOfInit(k) => {
+                write!(f, "alpha = {} * (alpha of the initial parameters)", k)
+            }
+            WeakAlpha::WellKnown(name) => write!(f, "alpha = {}", name),
+        }
+    }
+}
+
+/// How much work the baby-step/giant-step search does.
+/// It covers |k| <= baby_steps * (giant_steps + 1), using memory for
+/// `baby_steps` points and time for about `baby_steps + 2 * giant_steps` points.
+pub struct AuditOptions {
+    pub baby_steps: u64,
+    pub giant_steps: u64,
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        AuditOptions {
+            baby_steps: 1 << 16,
+            giant_steps: 1 << 16,
+        }
+    }
+}
+
+/// Values of alpha that anyone could come up with.
+pub fn well_known_alphas() -> Vec<(&'static str, Fr)> {
+    vec![
+        (
+            "HashToField(first 100 digits of pi), the initial alpha",
+            init_alpha(),
+        ),
+        ("HashToField(\"\")", hash_to_field_pointproofs(b"")),
+        (
+            "HashToField(\"3.\" || first 100 digits of pi without the 3)",
+            hash_to_field_pointproofs(format!("3.{}", &INIT_ALPHA_SEED[1..])),
+        ),
+        ("HashToField(\"pi\")", hash_to_field_pointproofs(b"pi")),
+        (
+            "the exponent mixed in for empty entropy",
+            derive_rerandomization_scalar(b"", b""),
+        ),
+    ]
+}
+
+/// Checks g2^alpha against small exponents, small multiples of the initial
+/// alpha and well-known values. Returns every match found; an empty result
+/// means none of these checks found alpha.
+pub fn audit_alpha(g2alpha: &G2Affine, options: &AuditOptions) -> Vec<WeakAlpha> {
+    let mut found = vec![];
+
+    for (name, alpha) in well_known_alphas() {
+        if G2Affine::one().mul(alpha).into_affine() == *g2alpha {
+            found.push(WeakAlpha::WellKnown(name));
+        }
+    }
+
+    let table = SmallExponentTable::<G2Affine>::new(options.baby_steps);
+    if let Some(k) = table.find_exponent(g2alpha, options.giant_steps) {
+        found.push(WeakAlpha::SmallExponent(k));
+    }
+
+    let g2_init = G2Affine::one().mul(init_alpha()).into_affine();
+    let table = SmallExponentTable::with_base(g2_init, options.baby_steps);
+    if let Some(k) = table.find_exponent(g2alpha, options.giant_steps) {
+        found.push(WeakAlpha::SmallMultipleOfInit(k));
+    }
+
+    found
+}
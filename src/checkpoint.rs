@@ -0,0 +1,493 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines checkpoints from which an interrupted evolve or verify can resume.
+//!
+//! An evolve checkpoint holds the exponent being mixed in, so it is never
+//! written in plaintext: the whole checkpoint is encrypted with
+//! ChaCha20-Poly1305 under a key derived from a passphrase with Argon2id.
+//! Whoever obtains both the checkpoint and the passphrase learns the secret
+//! of the contribution, so the checkpoint must be deleted once the
+//! contribution is written.
+//!
+//! A verify checkpoint holds nothing secret, only how many rounds of the
+//! consistency check have passed and the seed and sums of the round in
+//! progress, and is stored in plaintext. It is only as trustworthy as the
+//! disk it is stored on.
+//!
+//! Both kinds start with `CHECKPOINT_MAGIC`, a version byte and a kind byte.
+use crate::consistency::{CheckProgress, ConsistencyChecker};
+use crate::digest::{params_digest, to_hex, Digest};
+use crate::encoding::{read_point, read_point_unchecked, read_scalar, write_point, write_scalar};
+use crate::error::{Error, Result};
+use crate::format::ProofMode;
+use crate::progress::{Hooks, Phase};
+use crate::schnorr::make_pok;
+use crate::spot_check::{spot_check, SPOT_CHECKS};
+use crate::update_proof::make_update_proof;
+use crate::{derive_rerandomization_scalar, ContributionProof, PointproofsParams};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use ff::Field;
+use pairing_plus::bls12_381::{Fr, G1Affine, G2Affine};
+use pairing_plus::{CurveAffine, CurveProjective};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::io::{Read, Write};
+use zeroize::Zeroize;
+
+/// Marks the start of a checkpoint file.
+pub const CHECKPOINT_MAGIC: [u8; 4] = *b"PPCK";
+
+/// Current version of the checkpoint layout.
+/// Version 2 records the progress of verify within a round.
+pub const CHECKPOINT_VERSION: u8 = 2;
+
+const KIND_EVOLVE: u8 = 1;
+const KIND_VERIFY: u8 = 2;
+const HEADER_LEN: usize = 6;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The output of a rerandomization, computed one power of alpha at a time.
+#[derive(Debug, Default, PartialEq)]
+pub struct PartialParams {
+    // outputs for the powers 1..=N, N+2..=2N, in that order
+    g1: Vec<G1Affine>,
+    g2: Vec<G2Affine>,
+}
+
+impl PartialParams {
+    pub fn new() -> Self {
+        PartialParams::default()
+    }
+
+    /// Number of powers computed so far, out of 2N-1.
+    pub fn done(&self) -> usize {
+        self.g1.len()
+    }
+
+    /// Raises up to `count` more points of `params` to the matching power of alpha.
+    /// If the operation is cancelled, the powers computed until then are kept.
+    pub fn advance(
+        &mut self,
+        params: &PointproofsParams,
+        alpha: Fr,
+        count: usize,
+        hooks: &Hooks,
+    ) -> Result<()> {
+        let n = params.n;
+        let total = 2 * n - 1;
+        let start = self.done();
+        let end = std::cmp::min(start + count, total);
+        // output j holds power j+1 up to N, and power j+2 after skipping N+1
+        let exponent = if start < n { start + 1 } else { start + 2 };
+        let mut scalar = alpha.pow([exponent as u64]);
+        for j in start..end {
+            hooks.step(Phase::Rerandomize, j, total)?;
+            let (g1, g2) = if j < n {
+                (params.g1_alpha_1_to_n[j], params.g2_alpha_1_to_n[j])
+            } else {
+                (
+                    params.g1_alpha_nplus2_to_2n[j - n],
+                    params.g2_alpha_nplus2_to_2n[j - n],
+                )
+            };
+            self.g2.push(g2.mul(scalar).into_affine());
+            self.g1.push(g1.mul(scalar).into_affine());
+            scalar.mul_assign(&alpha);
+            if j + 1 == n {
+                // skip alpha^{N+1}
+                scalar.mul_assign(&alpha);
+            }
+        }
+        scalar.zeroize();
+        if end == total {
+            hooks.step(Phase::Rerandomize, total, total)?;
+        }
+        Ok(())
+    }
+
+    /// Assembles the parameters once all 2N-1 powers are computed.
+    pub fn finish(self, n: usize) -> Result<PointproofsParams> {
+        if n == 0 || self.done() != 2 * n - 1 {
+            return Err(Error::InvalidInput(
+                "The rerandomization is not complete".to_string(),
+            ));
+        }
+        let mut g1_alpha_1_to_n = self.g1;
+        let g1_alpha_nplus2_to_2n = g1_alpha_1_to_n.split_off(n);
+        let mut g2_alpha_1_to_n = self.g2;
+        let g2_alpha_nplus2_to_2n = g2_alpha_1_to_n.split_off(n);
+        let gt_alpha_nplus1 = g2_alpha_1_to_n[n - 1].pairing_with(&g1_alpha_1_to_n[0]);
+        Ok(PointproofsParams {
+            n,
+            g1_alpha_1_to_n,
+            g1_alpha_nplus2_to_2n,
+            g2_alpha_1_to_n,
+            g2_alpha_nplus2_to_2n,
+            gt_alpha_nplus1,
+        })
+    }
+}
+
+/// A key derived from a passphrase. It is kept in memory so that a
+/// checkpoint can be rewritten many times without rerunning Argon2.
+pub struct CheckpointKey {
+    salt: [u8; SALT_LEN],
+    key: [u8; 32],
+}
+
+impl CheckpointKey {
+    /// Derives a key from `passphrase` under a fresh random salt.
+    pub fn new(passphrase: &[u8]) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        CheckpointKey::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(passphrase: &[u8], salt: [u8; SALT_LEN]) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(Error::InvalidInput(
+                "The checkpoint passphrase must not be empty".to_string(),
+            ));
+        }
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase, &salt, &mut key)
+            .map_err(|e| Error::InvalidInput(format!("Cannot derive checkpoint key: {}", e)))?;
+        Ok(CheckpointKey { salt, key })
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(&self.key))
+    }
+}
+
+impl Drop for CheckpointKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// Everything needed to finish an interrupted evolve, including its secret exponent.
+pub struct EvolveCheckpoint {
+    /// `params_digest` of the parameters being rerandomized.
+    pub input_digest: Digest,
+    pub id: Vec<u8>,
+    pub mode: ProofMode,
+    alpha: Fr,
+    pub partial: PartialParams,
+}
+
+impl EvolveCheckpoint {
+    /// Starts a contribution to `params`, deriving the exponent from
+    /// `entropy` as `rerandomize_with_hooks` does.
+    pub fn new<B: AsRef<[u8]>>(
+        params: &PointproofsParams,
+        entropy: B,
+        id: &[u8],
+        mode: ProofMode,
+    ) -> Self {
+        EvolveCheckpoint {
+            input_digest: params_digest(params),
+            id: id.to_vec(),
+            mode,
+            alpha: derive_rerandomization_scalar(entropy, id),
+            partial: PartialParams::new(),
+        }
+    }
+
+    /// Returns true if this checkpoint was made while rerandomizing `params`.
+    pub fn matches(&self, params: &PointproofsParams) -> bool {
+        self.input_digest[..] == params_digest(params)[..]
+    }
+
+    /// Computes up to `count` more powers; see `PartialParams::advance`.
+    pub fn advance(
+        &mut self,
+        params: &PointproofsParams,
+        count: usize,
+        hooks: &Hooks,
+    ) -> Result<()> {
+        self.partial.advance(params, self.alpha, count, hooks)
+    }
+
+    pub fn is_complete(&self, params: &PointproofsParams) -> bool {
+        self.partial.done() == 2 * params.n - 1
+    }
+
+    /// Assembles the rerandomized parameters and the proof for them.
+    pub fn finish(
+        mut self,
+        params: &PointproofsParams,
+    ) -> Result<(PointproofsParams, ContributionProof)> {
+        let proof = match self.mode {
+            ProofMode::Schnorr => ContributionProof::Schnorr(make_pok(self.alpha, &self.id)),
+            ProofMode::Pairing => {
+                ContributionProof::Pairing(make_update_proof(params.g1_alpha_1_to_n[0], self.alpha))
+            }
+        };
+        let partial = std::mem::take(&mut self.partial);
+        let new_params = partial.finish(params.n)?;
+        spot_check(params, &new_params, self.alpha, SPOT_CHECKS, &mut OsRng)?;
+        Ok((new_params, proof))
+    }
+
+    /// Writes the checkpoint, encrypted under `key`.
+    pub fn write<W: Write>(&self, w: &mut W, key: &CheckpointKey) -> Result<()> {
+        let mut body: Vec<u8> = vec![];
+        body.extend_from_slice(&self.input_digest);
+        body.push(self.mode.to_byte());
+        body.extend_from_slice(&(self.id.len() as u32).to_le_bytes());
+        body.extend_from_slice(&self.id);
+        write_scalar(&mut body, &self.alpha)?;
+        body.extend_from_slice(&(self.partial.done() as u32).to_le_bytes());
+        // uncompressed, since the points are authenticated and need no decoding checks
+        for pt in &self.partial.g1 {
+            write_point(&mut body, pt, false)?;
+        }
+        for pt in &self.partial.g2 {
+            write_point(&mut body, pt, false)?;
+        }
+
+        // the header and salt are authenticated along with the body
+        let mut prefix = header(KIND_EVOLVE).to_vec();
+        prefix.extend_from_slice(&key.salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: &body,
+            aad: &prefix,
+        };
+        let ciphertext = key.cipher().encrypt(Nonce::from_slice(&nonce), payload);
+        body.zeroize();
+        let ciphertext = ciphertext
+            .map_err(|_| Error::InvalidInput("The checkpoint is too large".to_string()))?;
+        w.write_all(&prefix)?;
+        w.write_all(&nonce)?;
+        w.write_all(&ciphertext)?;
+        Ok(())
+    }
+
+    /// Reads a checkpoint written by `write`, returning it along with the key
+    /// derived from `passphrase`, which can be used to rewrite it.
+    pub fn read<R: Read>(r: &mut R, passphrase: &[u8]) -> Result<(Self, CheckpointKey)> {
+        let mut bytes: Vec<u8> = vec![];
+        r.read_to_end(&mut bytes)?;
+        check_header(&bytes, KIND_EVOLVE)?;
+        if bytes.len() < HEADER_LEN + SALT_LEN + NONCE_LEN {
+            return Err(Error::Format("Truncated checkpoint".to_string()));
+        }
+        let (prefix, rest) = bytes.split_at(HEADER_LEN + SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&prefix[HEADER_LEN..]);
+        let key = CheckpointKey::with_salt(passphrase, salt)?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: prefix,
+        };
+        let mut body = key
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| {
+                Error::Format(
+                    "Cannot decrypt the checkpoint: wrong passphrase, or the file was modified"
+                        .to_string(),
+                )
+            })?;
+        let checkpoint = EvolveCheckpoint::parse_body(&body);
+        body.zeroize();
+        Ok((checkpoint?, key))
+    }
+
+    fn parse_body(mut r: &[u8]) -> Result<Self> {
+        let mut input_digest = [0u8; 64];
+        r.read_exact(&mut input_digest)?;
+        let mut buf = [0u8; 1];
+        r.read_exact(&mut buf)?;
+        let mode = ProofMode::from_byte(buf[0])?;
+        let id_len = read_u32(&mut r)?;
+        if id_len > r.len() {
+            return Err(Error::Format("Truncated checkpoint".to_string()));
+        }
+        let mut id = vec![0u8; id_len];
+        r.read_exact(&mut id)?;
+        let alpha = read_scalar(&mut r)?;
+        let done = read_u32(&mut r)?;
+        let mut partial = PartialParams::new();
+        for _ in 0..done {
+            partial.g1.push(read_point_unchecked(&mut r, false)?);
+        }
+        for _ in 0..done {
+            partial.g2.push(read_point_unchecked(&mut r, false)?);
+        }
+        Ok(EvolveCheckpoint {
+            input_digest,
+            id,
+            mode,
+            alpha,
+            partial,
+        })
+    }
+}
+
+impl Drop for EvolveCheckpoint {
+    fn drop(&mut self) {
+        self.alpha.zeroize();
+    }
+}
+
+/// How far an interrupted verify got.
+#[derive(Debug, PartialEq)]
+pub struct VerifyCheckpoint {
+    /// `params_digest` of the assumed-good parameters.
+    pub old_digest: Digest,
+    /// `params_digest` of the parameters being verified.
+    pub new_digest: Digest,
+    /// The checker's settings, which a resumed check must share.
+    pub settings: String,
+    /// How far the consistency check has got.
+    pub progress: CheckProgress,
+}
+
+impl VerifyCheckpoint {
+    /// A checkpoint at the start of checking `new` against `old` with `checker`.
+    pub fn new(
+        old: &PointproofsParams,
+        new: &PointproofsParams,
+        checker: &ConsistencyChecker,
+    ) -> Self {
+        VerifyCheckpoint {
+            old_digest: params_digest(old),
+            new_digest: params_digest(new),
+            settings: checker_settings(checker),
+            progress: CheckProgress::default(),
+        }
+    }
+
+    /// Returns true if both checkpoints are for the same check of the same parameters.
+    pub fn same_check(&self, other: &VerifyCheckpoint) -> bool {
+        self.old_digest[..] == other.old_digest[..]
+            && self.new_digest[..] == other.new_digest[..]
+            && self.settings == other.settings
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        w.write_all(&header(KIND_VERIFY))?;
+        w.write_all(&self.old_digest)?;
+        w.write_all(&self.new_digest)?;
+        w.write_all(&(self.settings.len() as u32).to_le_bytes())?;
+        w.write_all(self.settings.as_bytes())?;
+        let progress = &self.progress;
+        w.write_all(&(progress.rounds_passed as u32).to_le_bytes())?;
+        match &progress.round_seed {
+            None => w.write_all(&[0])?,
+            Some(seed) => {
+                w.write_all(&[1])?;
+                w.write_all(seed)?;
+            }
+        }
+        w.write_all(&[progress.g1_sums.len() as u8, progress.g2_sums.len() as u8])?;
+        for p in progress.g1_sums.iter() {
+            write_point(w, p, false)?;
+        }
+        for p in progress.g2_sums.iter() {
+            write_point(w, p, false)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> Result<Self> {
+        let mut bytes: Vec<u8> = vec![];
+        r.read_to_end(&mut bytes)?;
+        check_header(&bytes, KIND_VERIFY)?;
+        let mut r = &bytes[HEADER_LEN..];
+        let mut old_digest = [0u8; 64];
+        r.read_exact(&mut old_digest)?;
+        let mut new_digest = [0u8; 64];
+        r.read_exact(&mut new_digest)?;
+        let settings_len = read_u32(&mut r)?;
+        if settings_len > r.len() {
+            return Err(Error::Format("Truncated checkpoint".to_string()));
+        }
+        let mut settings = vec![0u8; settings_len];
+        r.read_exact(&mut settings)?;
+        let settings = String::from_utf8(settings)
+            .map_err(|_| Error::Format("Malformed checkpoint settings".to_string()))?;
+        let rounds_passed = read_u32(&mut r)?;
+        let mut buf = [0u8; 1];
+        r.read_exact(&mut buf)?;
+        let round_seed = match buf[0] {
+            0 => None,
+            1 => {
+                let mut seed = [0u8; 32];
+                r.read_exact(&mut seed)?;
+                Some(seed)
+            }
+            _ => return Err(Error::Format("Malformed checkpoint".to_string())),
+        };
+        let mut counts = [0u8; 2];
+        r.read_exact(&mut counts)?;
+        // a round has three sums in G1 and two in G2
+        if counts[0] > 3 || counts[1] > 2 {
+            return Err(Error::Format("Malformed checkpoint".to_string()));
+        }
+        let mut progress = CheckProgress {
+            rounds_passed,
+            round_seed,
+            ..CheckProgress::default()
+        };
+        for _ in 0..counts[0] {
+            progress.g1_sums.push(read_point(&mut r, false)?);
+        }
+        for _ in 0..counts[1] {
+            progress.g2_sums.push(read_point(&mut r, false)?);
+        }
+        Ok(VerifyCheckpoint {
+            old_digest,
+            new_digest,
+            settings,
+            progress,
+        })
+    }
+}
+
+// Everything about a checker that changes which rounds it runs
+fn checker_settings(checker: &ConsistencyChecker) -> String {
+    if checker.is_paranoid() {
+        return "paranoid".to_string();
+    }
+    format!(
+        "security={} rounds={} seed={}",
+        checker.security_level(),
+        checker.rounds(),
+        checker.seed().map_or("none".to_string(), to_hex)
+    )
+}
+
+fn header(kind: u8) -> [u8; HEADER_LEN] {
+    let m = CHECKPOINT_MAGIC;
+    [m[0], m[1], m[2], m[3], CHECKPOINT_VERSION, kind]
+}
+
+fn check_header(bytes: &[u8], kind: u8) -> Result<()> {
+    if bytes.len() < HEADER_LEN || bytes[..4] != CHECKPOINT_MAGIC {
+        return Err(Error::Format("Not a checkpoint file".to_string()));
+    }
+    if bytes[4] != CHECKPOINT_VERSION {
+        return Err(Error::Format("Unsupported checkpoint version".to_string()));
+    }
+    if bytes[5] != kind {
+        return Err(Error::Format(
+            "The checkpoint is for a different command".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn read_u32(r: &mut &[u8]) -> Result<usize> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf) as usize)
+}
@@ -0,0 +1,575 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the probabilistic consistency check described in consistencycheck.pdf.
+//!
+//! The check reduces the parameters to a handful of points using random
+//! scalars r_1, ..., r_N and then verifies five pairing equations between
+//! them. Rather than evaluating each side of each equation with its own
+//! pairing, the equations are raised to independent random weights and
+//! multiplied together, so the whole check costs a single multi-Miller loop
+//! and a single final exponentiation. Pairings that share a G2 argument are
+//! merged by adding their G1 arguments first. The line coefficients of the
+//! fixed generator g2 are prepared once per checker, so a checker can be
+//! reused across every message of a transcript, and those of g2^alpha and
+//! g2^{alpha^N} once per set of parameters, however many rounds are run.
+//!
+//! The r_i and the weights do not need to be uniform in Fr. If they are
+//! drawn uniformly from [0, 2^k), each of the two random steps lets
+//! inconsistent parameters through with probability at most 2^-k, so one
+//! round has soundness error at most 2^-(k-1). The multi-exponentiations,
+//! which dominate the cost, skip the windows in which every challenge is
+//! zero, so short challenges make them proportionally cheaper (see
+//! benches/consistency.rs). `SecurityLevel` picks k, and independent rounds
+//! multiply the soundness.
+//!
+//! By default the challenges come from the operating system. A checker can
+//! instead be given a seed, in which case the challenges are derived from the
+//! seed and the `params_digest` of the parameters being checked, Fiat-Shamir
+//! style. The check is then reproducible bit for bit: an auditor can publish
+//! (seed, digest, result) and anyone can replay it. The seed should be chosen
+//! after the parameters are fixed, since whoever knows it in advance could
+//! search for inconsistent parameters that pass.
+//!
+//! Either way, each round first draws a 32-byte round seed and derives all
+//! of its challenges from it, so that a round interrupted between two
+//! multi-exponentiations can be resumed from its seed and the sums computed
+//! so far (see `CheckProgress`).
+use crate::digest::{params_digest, Digest};
+use crate::error::{Error, Result};
+use crate::hash_to_field_pointproofs::hash_to_field_pointproofs;
+use crate::paranoid::paranoid_consistent_with_hooks;
+use crate::progress::{Hooks, Phase};
+use crate::PointproofsParams;
+use ff::{Field, PrimeField};
+use pairing_plus::bls12_381::{
+    Bls12, Fq12, Fr, FrRepr, G1Affine, G1Prepared, G2Affine, G2Prepared, G1,
+};
+use pairing_plus::{CurveAffine, CurveProjective, Engine};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest as _, Sha512};
+use std::fmt;
+use zeroize::Zeroize;
+
+/// How unlikely it must be for one round of the check to accept inconsistent parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecurityLevel {
+    /// Soundness error at most 2^-64 per round.
+    Bits64,
+    /// Soundness error at most 2^-128 per round.
+    Bits128,
+    /// Challenges uniform in Fr, for a soundness error below 2^-253 per round.
+    Full,
+}
+
+impl SecurityLevel {
+    /// Per-round soundness, in bits.
+    pub fn bits(self) -> u32 {
+        match self {
+            SecurityLevel::Bits64 => 64,
+            SecurityLevel::Bits128 => 128,
+            SecurityLevel::Full => 253,
+        }
+    }
+
+    // Length of each challenge in bits, or None for challenges uniform in Fr
+    fn challenge_bits(self) -> Option<u32> {
+        match self {
+            SecurityLevel::Bits64 => Some(65),
+            SecurityLevel::Bits128 => Some(129),
+            SecurityLevel::Full => None,
+        }
+    }
+}
+
+impl fmt::Display for SecurityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecurityLevel::Bits64 => write!(f, "64"),
+            SecurityLevel::Bits128 => write!(f, "128"),
+            SecurityLevel::Full => write!(f, "full"),
+        }
+    }
+}
+
+impl std::str::FromStr for SecurityLevel {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "64" => Ok(SecurityLevel::Bits64),
+            "128" => Ok(SecurityLevel::Bits128),
+            "full" => Ok(SecurityLevel::Full),
+            _ => Err(Error::InvalidInput(
+                "Security level must be one of: 64, 128, full".to_string(),
+            )),
+        }
+    }
+}
+
+// Progress steps in one round of the probabilistic check:
+// five multi-exponentiations and the multi-pairing
+const STEPS_PER_ROUND: usize = 6;
+
+/// How far a probabilistic check has got, so that an interrupted check can be resumed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CheckProgress {
+    /// Number of rounds that have passed.
+    pub rounds_passed: usize,
+    /// The seed of the next round, once it has started.
+    pub round_seed: Option<[u8; 32]>,
+    /// The sums of the next round computed so far, in the order S, T, U_1.
+    pub g1_sums: Vec<G1Affine>,
+    /// The sums R_2, U_2 of the next round, which are computed after those in G1.
+    pub g2_sums: Vec<G2Affine>,
+}
+
+impl CheckProgress {
+    /// Number of multi-exponentiations of the next round that are done.
+    pub fn sums_done(&self) -> usize {
+        self.g1_sums.len() + self.g2_sums.len()
+    }
+}
+
+pub struct ConsistencyChecker {
+    // prepared generator of G2, the G2 argument shared by most of the pairings
+    g2: G2Prepared,
+    level: SecurityLevel,
+    rounds: usize,
+    // if set, challenges are derived from this seed instead of drawn from the OS
+    seed: Option<Vec<u8>>,
+    // if set, `paranoid_consistent` is run instead of the probabilistic check
+    paranoid: bool,
+}
+
+impl Default for ConsistencyChecker {
+    fn default() -> Self {
+        ConsistencyChecker::new()
+    }
+}
+
+impl ConsistencyChecker {
+    /// A checker running one round with challenges uniform in Fr.
+    pub fn new() -> Self {
+        ConsistencyChecker {
+            g2: G2Affine::one().prepare(),
+            level: SecurityLevel::Full,
+            rounds: 1,
+            seed: None,
+            paranoid: false,
+        }
+    }
+
+    pub fn with_security_level(mut self, level: SecurityLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the number of independent rounds; at least one round is always run.
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds.max(1);
+        self
+    }
+
+    /// Derives the challenges deterministically from `seed` and the parameters being checked.
+    pub fn with_seed(mut self, seed: &[u8]) -> Self {
+        self.seed = Some(seed.to_vec());
+        self
+    }
+
+    /// Runs the exhaustive `paranoid_consistent` instead, ignoring the
+    /// security level, rounds and seed.
+    pub fn paranoid(mut self) -> Self {
+        self.paranoid = true;
+        self
+    }
+
+    pub fn is_paranoid(&self) -> bool {
+        self.paranoid
+    }
+
+    pub fn security_level(&self) -> SecurityLevel {
+        self.level
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    pub fn seed(&self) -> Option<&[u8]> {
+        self.seed.as_deref()
+    }
+
+    /// The check accepts inconsistent parameters with probability at most 2^-soundness_bits().
+    pub fn soundness_bits(&self) -> u32 {
+        self.level.bits() * self.rounds as u32
+    }
+
+    /// Checks that a set of parameters are in the correct form
+    /// (g2^alpha, g2^alpha^2, etc.) for some alpha.
+    pub fn check(&self, params: &PointproofsParams) -> bool {
+        // without a cancel token, the check cannot fail
+        self.check_with_hooks(params, &Hooks::default())
+            .unwrap_or(false)
+    }
+
+    /// Like `check`, but reports progress and can be cancelled through `hooks`.
+    pub fn check_with_hooks(&self, params: &PointproofsParams, hooks: &Hooks) -> Result<bool> {
+        self.check_resumable(params, CheckProgress::default(), hooks, &mut |_| Ok(()))
+    }
+
+    /// How many rounds a check resumed from `progress` takes as passed: all
+    /// that it records, except that a pass always rests on at least one
+    /// round run by the resumed check, so the last round is run again.
+    pub fn rounds_resumed(&self, progress: &CheckProgress) -> usize {
+        if self.paranoid {
+            return 0;
+        }
+        progress.rounds_passed.min(self.rounds.saturating_sub(1))
+    }
+
+    /// Like `check_with_hooks`, but starts from `progress`, trusting that
+    /// the rounds and sums it records are correct (see `rounds_resumed`), and calls
+    /// `on_progress` after each multi-exponentiation and each round that
+    /// passes, so that an interrupted check can be resumed. With a seed, the
+    /// seeds of the skipped rounds are drawn and discarded, so a resumed
+    /// check is the same as an uninterrupted one. A paranoid check has no
+    /// rounds and always starts over.
+    pub fn check_resumable(
+        &self,
+        params: &PointproofsParams,
+        progress: CheckProgress,
+        hooks: &Hooks,
+        on_progress: &mut dyn FnMut(&CheckProgress) -> Result<()>,
+    ) -> Result<bool> {
+        if self.paranoid {
+            return paranoid_consistent_with_hooks(params, hooks);
+        }
+        match &self.seed {
+            None => self.check_rounds(params, &mut OsRng, progress, hooks, on_progress),
+            Some(seed) => {
+                let mut rng = ChallengeRng::new(&params_digest(params), seed);
+                self.check_rounds(params, &mut rng, progress, hooks, on_progress)
+            }
+        }
+    }
+
+    /// Like `check`, but draws every round seed from `rng`, ignoring any seed.
+    /// This always runs the probabilistic check.
+    pub fn check_with_rng<R: RngCore>(&self, params: &PointproofsParams, rng: &mut R) -> bool {
+        self.check_rounds(
+            params,
+            rng,
+            CheckProgress::default(),
+            &Hooks::default(),
+            &mut |_| Ok(()),
+        )
+        .unwrap_or(false)
+    }
+
+    fn check_rounds<R: RngCore>(
+        &self,
+        params: &PointproofsParams,
+        rng: &mut R,
+        mut progress: CheckProgress,
+        hooks: &Hooks,
+        on_progress: &mut dyn FnMut(&CheckProgress) -> Result<()>,
+    ) -> Result<bool> {
+        if params.n == 0 || !points_ok(params) {
+            return Ok(false);
+        }
+        if progress.g1_sums.len() > 3
+            || progress.g2_sums.len() > 2
+            || (progress.round_seed.is_none() && progress.sums_done() > 0)
+            || (!progress.g2_sums.is_empty() && progress.g1_sums.len() < 3)
+            || progress.rounds_passed > self.rounds
+            || (progress.rounds_passed == self.rounds && progress.sums_done() > 0)
+        {
+            return Err(Error::InvalidInput(
+                "Inconsistent progress of a consistency check".to_string(),
+            ));
+        }
+        if progress.rounds_passed > self.rounds_resumed(&progress) {
+            progress = CheckProgress {
+                rounds_passed: self.rounds_resumed(&progress),
+                ..CheckProgress::default()
+            };
+        }
+        // the G2 arguments that are the same in every round
+        let n = params.n;
+        let fixed_g2 = [
+            params.g2_alpha_1_to_n[0].prepare(),
+            params.g2_alpha_1_to_n[n - 1].prepare(),
+        ];
+        let total = STEPS_PER_ROUND * self.rounds;
+        for round in 0..self.rounds {
+            let mut seed = [0u8; 32];
+            rng.fill_bytes(&mut seed);
+            if round < progress.rounds_passed {
+                continue;
+            }
+            // a resumed round keeps the seed it started with
+            let seed = *progress.round_seed.get_or_insert(seed);
+            let step = |k: usize| hooks.step(Phase::Check, STEPS_PER_ROUND * round + k, total);
+            if !self.check_round(params, &fixed_g2, &seed, &mut progress, step, on_progress)? {
+                return Ok(false);
+            }
+            progress = CheckProgress {
+                rounds_passed: round + 1,
+                ..CheckProgress::default()
+            };
+            on_progress(&progress)?;
+        }
+        hooks.step(Phase::Check, total, total)?;
+        Ok(true)
+    }
+
+    // A random challenge of the length the security level asks for
+    fn challenge<R: RngCore>(&self, rng: &mut R) -> Fr {
+        match self.level.challenge_bits() {
+            None => {
+                let mut r: [u8; 64] = [0; 64];
+                rng.fill_bytes(&mut r[..]);
+                // For convenience, just using already-implemented hash-to-field
+                let res = hash_to_field_pointproofs(&r[..]);
+                r.zeroize();
+                res
+            }
+            Some(bits) => {
+                let mut repr = FrRepr([0; 4]);
+                for (i, limb) in repr.0.iter_mut().enumerate() {
+                    let remaining = bits.saturating_sub(64 * i as u32);
+                    *limb = match remaining {
+                        0 => 0,
+                        1..=63 => rng.next_u64() & ((1u64 << remaining) - 1),
+                        _ => rng.next_u64(),
+                    };
+                }
+                // short challenges are always below the modulus
+                Fr::from_repr(repr).unwrap()
+            }
+        }
+    }
+
+    // Runs one round with the challenges derived from `seed`, calling step(k)
+    // before its k-th step, and recording each sum in `progress` as it is
+    // computed. fixed_g2 holds g2^alpha and g2^{alpha^N}, prepared.
+    fn check_round<S: Fn(usize) -> Result<()>>(
+        &self,
+        params: &PointproofsParams,
+        fixed_g2: &[G2Prepared; 2],
+        seed: &[u8; 32],
+        progress: &mut CheckProgress,
+        step: S,
+        on_progress: &mut dyn FnMut(&CheckProgress) -> Result<()>,
+    ) -> Result<bool> {
+        let n = params.n;
+        let mut rng = ChallengeRng::for_round(seed);
+
+        // Generate N random scalars r_1, ..., r_N, and the five weights c_j used below
+        let rs_owned: Vec<FrRepr> = (0..n)
+            .map(|_| self.challenge(&mut rng).into_repr())
+            .collect();
+        let rs: Vec<&[u64; 4]> = rs_owned.iter().map(|r| &r.0).collect();
+        let c: Vec<Fr> = (0..5).map(|_| self.challenge(&mut rng)).collect();
+
+        // Compute:
+        // S = prod_{i=1}^{N-1} ("g_1^{alpha^i}")^{r_i}
+        // T = prod{i=1}^{N-1} ("g_1^{alpha^{i+1}}")^{r_i}
+        // U_1 = prod{i=1}^{N-1} ("g_1^{alpha^{i+N+1}")^{r_i}
+        // R_2 = prod_{i=1}^{N} ("g_2^{alpha^i}")^{r_i}
+        // U_2 = prod{i=1}^{N-1} ("g_2^{alpha^{i+N+1}")^{r_i}
+        // and R_1 = prod_{i=1}^{N} ("g_1^{alpha^i}")^{r_i} = S * ("g_1^{alpha^N}")^{r_N}
+        let g1_bases = [
+            &params.g1_alpha_1_to_n[0..n - 1],
+            &params.g1_alpha_1_to_n[1..n],
+            &params.g1_alpha_nplus2_to_2n[0..n - 1],
+        ];
+        let g2_bases = [
+            &params.g2_alpha_1_to_n[0..n],
+            &params.g2_alpha_nplus2_to_2n[0..n - 1],
+        ];
+        while progress.g1_sums.len() < g1_bases.len() {
+            let bases = g1_bases[progress.g1_sums.len()];
+            step(progress.sums_done())?;
+            let sum = G1Affine::sum_of_products(bases, &rs[0..bases.len()]);
+            progress.g1_sums.push(sum.into_affine());
+            on_progress(progress)?;
+        }
+        while progress.g2_sums.len() < g2_bases.len() {
+            let bases = g2_bases[progress.g2_sums.len()];
+            step(progress.sums_done())?;
+            let sum = G2Affine::sum_of_products(bases, &rs[0..bases.len()]);
+            progress.g2_sums.push(sum.into_affine());
+            on_progress(progress)?;
+        }
+        let pt_s = progress.g1_sums[0].into_projective();
+        let pt_t = progress.g1_sums[1].into_projective();
+        let pt_u1 = progress.g1_sums[2].into_projective();
+        let pt_r2 = progress.g2_sums[0];
+        let pt_u2 = progress.g2_sums[1];
+        let pt_r1 = {
+            let mut tmp =
+                params.g1_alpha_1_to_n[n - 1].mul(Fr::from_repr(rs_owned[n - 1]).unwrap());
+            tmp.add_assign(&pt_s);
+            tmp
+        };
+
+        // The equations to check are
+        // 1: e(R_1, g_2) = e(g_1, R_2)
+        //    which essentially checks e("g_1^{alpha^i}", g_2) = e(g_1, "g_2^{alpha^i}") for all 1<=i<=N
+        // 2: e(S, g_2^alpha) = e(T, g_2)
+        //    which essentially checks e("g_1^{alpha^i}", g_2^alpha) = e(g_1^{alpha^{i+1}}, g_2) for all 1<=i<=N-1
+        // 3: e(g_1^{alpha^N}, g_2^alpha) = "e(g_1, g_2)^{alpha^{N+1}}"
+        // 4: e(T, g_2^{alpha^N}) = e(U_1, g_2)
+        // 5: e(U_1, g_2) = e(g_1, U_2)
+        // Raising equation j to a random weight c_j and multiplying them all
+        // together, we check the single equation
+        // e(c_1 R_1 - c_2 T + (c_5 - c_4) U_1, g_2) * e(-c_1 g_1 , R_2)
+        //     * e(c_2 S + c_3 g_1^{alpha^N}, g_2^alpha) * e(c_4 T, g_2^{alpha^N})
+        //     * e(-c_5 g_1, U_2) = "e(g_1, g_2)^{alpha^{N+1}}"^{c_3}
+        // which holds with probability at most 2^-k if any of the five equations does not.
+        let mut c5_minus_c4 = c[4];
+        c5_minus_c4.sub_assign(&c[3]);
+
+        let with_g2 = {
+            let mut tmp = pt_r1;
+            tmp.mul_assign(c[0]);
+            let mut t = pt_t;
+            t.mul_assign(c[1]);
+            tmp.sub_assign(&t);
+            let mut u = pt_u1;
+            u.mul_assign(c5_minus_c4);
+            tmp.add_assign(&u);
+            tmp
+        };
+        let with_r2 = scaled_neg_g1(c[0]);
+        let with_g2alpha = {
+            let mut tmp = pt_s;
+            tmp.mul_assign(c[1]);
+            tmp.add_assign(&params.g1_alpha_1_to_n[n - 1].mul(c[2]));
+            tmp
+        };
+        let with_g2alphan = {
+            let mut tmp = pt_t;
+            tmp.mul_assign(c[3]);
+            tmp
+        };
+        let with_u2 = scaled_neg_g1(c[4]);
+
+        step(5)?;
+        let mut g1s = [with_g2, with_r2, with_g2alpha, with_g2alphan, with_u2];
+        G1::batch_normalization(&mut g1s);
+        let g1s: Vec<G1Prepared> = g1s.iter().map(|p| p.into_affine().prepare()).collect();
+        let g2s = [pt_r2.prepare(), pt_u2.prepare()];
+        let terms = [
+            (&g1s[0], &self.g2),
+            (&g1s[1], &g2s[0]),
+            (&g1s[2], &fixed_g2[0]),
+            (&g1s[3], &fixed_g2[1]),
+            (&g1s[4], &g2s[1]),
+        ];
+        let lhs = Bls12::final_exponentiation(&Bls12::miller_loop(terms.iter()));
+        let rhs: Fq12 = params.gt_alpha_nplus1.pow(c[2].into_repr());
+        Ok(lhs == Some(rhs))
+    }
+}
+
+impl fmt::Display for ConsistencyChecker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.paranoid {
+            write!(f, "exhaustive check")
+        } else {
+            write!(f, "soundness error at most 2^-{}", self.soundness_bits())
+        }
+    }
+}
+
+// Checks that all points are nonzero and not the generator
+// (Subgroup check is already done in our deserialization code)
+fn points_ok(params: &PointproofsParams) -> bool {
+    let g1_ok = |x: &G1Affine| !x.is_zero() && *x != G1Affine::one();
+    let g2_ok = |x: &G2Affine| !x.is_zero() && *x != G2Affine::one();
+    params.g1_alpha_1_to_n.iter().all(g1_ok)
+        && params.g1_alpha_nplus2_to_2n.iter().all(g1_ok)
+        && params.g2_alpha_1_to_n.iter().all(g2_ok)
+        && params.g2_alpha_nplus2_to_2n.iter().all(g2_ok)
+}
+
+// -c * g_1
+fn scaled_neg_g1(c: Fr) -> G1 {
+    let mut tmp = G1Affine::one().mul(c);
+    tmp.negate();
+    tmp
+}
+
+/// Deterministic source of challenges: SHA-512 in counter mode, keyed by the
+/// digest of the parameters and a verifier-chosen seed. Output block i is
+/// SHA-512("PointproofsConsistency" || digest || len(seed) || seed || i),
+/// with the length and i as 8-byte big-endian integers.
+pub struct ChallengeRng {
+    prefix: Sha512,
+    counter: u64,
+    block: [u8; 64],
+    pos: usize,
+}
+
+impl ChallengeRng {
+    pub fn new(digest: &Digest, seed: &[u8]) -> Self {
+        let mut prefix = Sha512::new();
+        prefix.input(b"PointproofsConsistency"); // domain separation
+        prefix.input(&digest[..]);
+        prefix.input((seed.len() as u64).to_be_bytes());
+        prefix.input(seed);
+        ChallengeRng::with_prefix(prefix)
+    }
+
+    /// The source of the challenges of one round, whose output block i is
+    /// SHA-512("PointproofsConsistencyRound" || round_seed || i).
+    pub fn for_round(round_seed: &[u8; 32]) -> Self {
+        let mut prefix = Sha512::new();
+        prefix.input(b"PointproofsConsistencyRound"); // domain separation
+        prefix.input(&round_seed[..]);
+        ChallengeRng::with_prefix(prefix)
+    }
+
+    fn with_prefix(prefix: Sha512) -> Self {
+        ChallengeRng {
+            prefix,
+            counter: 0,
+            block: [0; 64],
+            pos: 64,
+        }
+    }
+}
+
+impl RngCore for ChallengeRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest.iter_mut() {
+            if self.pos == self.block.len() {
+                let mut hasher = self.prefix.clone();
+                hasher.input(self.counter.to_be_bytes());
+                self.block.copy_from_slice(&hasher.result());
+                self.counter += 1;
+                self.pos = 0;
+            }
+            *byte = self.block[self.pos];
+            self.pos += 1;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
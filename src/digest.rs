@@ -0,0 +1,43 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the digests used to identify parameter sets.
+use crate::PointproofsParams;
+use pairing_plus::serdes::SerDes;
+use sha2::{Digest as _, Sha512};
+
+/// A SHA-512 digest.
+pub type Digest = [u8; 64];
+
+/// SHA-512 of arbitrary bytes.
+pub fn digest_bytes<B: AsRef<[u8]>>(input: B) -> Digest {
+    let mut hasher = Sha512::new();
+    hasher.input(input);
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&hasher.result());
+    out
+}
+
+/// SHA-512 of the compressed, headerless serialization of the parameters.
+/// Two parameter sets have the same digest exactly when they are equal,
+/// regardless of the settings they were stored with.
+pub fn params_digest(params: &PointproofsParams) -> Digest {
+    let mut buf: Vec<u8> = vec![];
+    // serializing into a Vec only fails for n >= 2^32, which deserialization never produces
+    params.serialize(&mut buf, true).unwrap();
+    digest_bytes(&buf)
+}
+
+/// Lowercase hex encoding.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes hex as written by `to_hex` (either case), or returns `None`.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,98 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines how individual group elements are encoded, in either the
+//! compressed or the uncompressed form.
+//!
+//! Decoding a compressed point costs a square root, which dominates the
+//! time to load large files; the uncompressed form avoids it at the price of
+//! twice the size. Both forms are the standard zcash encodings of BLS12-381.
+use crate::error::{Error, Result};
+use crate::subgroup::PointChecks;
+use pairing_plus::bls12_381::Fr;
+use pairing_plus::serdes::SerDes;
+use pairing_plus::{CurveAffine, EncodedPoint, GroupDecodingError};
+use std::io::{Read, Write};
+
+/// Size in bytes of an encoded point of `G`.
+pub fn point_size<G: CurveAffine>(compressed: bool) -> usize {
+    if compressed {
+        G::Compressed::size()
+    } else {
+        G::Uncompressed::size()
+    }
+}
+
+pub fn write_point<G: CurveAffine, W: Write>(w: &mut W, p: &G, compressed: bool) -> Result<()> {
+    if compressed {
+        w.write_all(G::Compressed::from_affine(*p).as_ref())?;
+    } else {
+        w.write_all(G::Uncompressed::from_affine(*p).as_ref())?;
+    }
+    Ok(())
+}
+
+/// Reads a point, checking that it is on the curve and in the prime-order subgroup.
+pub fn read_point<G: CurveAffine, R: Read>(r: &mut R, compressed: bool) -> Result<G> {
+    let res = if compressed {
+        let mut buf = G::Compressed::empty();
+        r.read_exact(buf.as_mut())?;
+        buf.into_affine()
+    } else {
+        let mut buf = G::Uncompressed::empty();
+        r.read_exact(buf.as_mut())?;
+        buf.into_affine()
+    };
+    res.map_err(decoding_error)
+}
+
+/// Reads a point, checking only that it is on the curve.
+/// The caller must establish subgroup membership some other way.
+pub fn read_point_unchecked<G: PointChecks, R: Read>(r: &mut R, compressed: bool) -> Result<G> {
+    let res = if compressed {
+        // decompression only finds points on the curve
+        let mut buf = G::Compressed::empty();
+        r.read_exact(buf.as_mut())?;
+        buf.into_affine_unchecked()
+    } else {
+        // but the uncompressed form can hold any pair of coordinates
+        let mut buf = G::Uncompressed::empty();
+        r.read_exact(buf.as_mut())?;
+        buf.into_affine_unchecked().and_then(|p| {
+            if p.is_on_curve() {
+                Ok(p)
+            } else {
+                Err(GroupDecodingError::NotOnCurve)
+            }
+        })
+    };
+    res.map_err(decoding_error)
+}
+
+// The Display impl of GroupDecodingError formats itself recursively until the
+// stack overflows, so the messages are spelled out here
+fn decoding_error(e: GroupDecodingError) -> Error {
+    let msg = match e {
+        GroupDecodingError::NotInSubgroup => {
+            return Error::NotInSubgroup("point is not in the prime-order subgroup".to_string())
+        }
+        GroupDecodingError::NotOnCurve => "point is not on the curve".to_string(),
+        GroupDecodingError::CoordinateDecodingError(coordinate, e) => {
+            format!("point has an invalid {}: {}", coordinate, e)
+        }
+        GroupDecodingError::UnexpectedCompressionMode => {
+            "point has the wrong compression flag".to_string()
+        }
+        GroupDecodingError::UnexpectedInformation => {
+            "point encoding has unexpected flags set".to_string()
+        }
+    };
+    Error::Format(msg)
+}
+
+/// Scalars have a single 32-byte encoding, whichever form the points use.
+pub fn write_scalar<W: Write>(w: &mut W, s: &Fr) -> Result<()> {
+    Ok(s.serialize(w, true)?)
+}
+
+pub fn read_scalar<R: Read>(r: &mut R) -> Result<Fr> {
+    Ok(Fr::deserialize(r, true)?)
+}
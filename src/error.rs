@@ -0,0 +1,89 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the error type shared by the whole crate.
+use crate::Rejection;
+use std::fmt;
+use std::io;
+
+/// Largest parameter n that files may hold.
+pub const MAX_N: usize = 65535;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing failed.
+    Io(io::Error),
+    /// The input is not in the expected format.
+    Format(String),
+    /// The parameter n is 0 or larger than `MAX_N`.
+    SizeLimit(usize),
+    /// A point or GT element is not in the prime-order subgroup.
+    NotInSubgroup(String),
+    /// The parameters are not of the form g^{alpha^i}.
+    Inconsistent,
+    /// A contribution was rejected.
+    Rejected(Rejection),
+    /// Alpha is trivially known; the string says how.
+    WeakAlpha(String),
+    /// An argument is out of range or not understood.
+    InvalidInput(String),
+    /// The operation was cancelled through its `CancelToken`.
+    Cancelled,
+    /// A result disagrees with its recomputation, which points to a hardware fault.
+    Fault(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Format(msg) => write!(f, "malformed input: {}", msg),
+            Error::SizeLimit(n) => write!(f, "parameter n = {} is not in 1..={}", n, MAX_N),
+            Error::NotInSubgroup(msg) => write!(f, "{}", msg),
+            Error::Inconsistent => write!(f, "parameters are not consistent"),
+            Error::Rejected(reason) => write!(f, "contribution rejected: {}", reason),
+            Error::WeakAlpha(msg) => write!(f, "alpha is trivially known: {}", msg),
+            Error::InvalidInput(msg) => write!(f, "{}", msg),
+            Error::Cancelled => write!(f, "cancelled"),
+            Error::Fault(msg) => write!(f, "possible hardware fault: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // truncated or undecodable input is a problem with the input, not with reading it
+            io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData => {
+                Error::Format(e.to_string())
+            }
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl From<Rejection> for Error {
+    fn from(r: Rejection) -> Self {
+        Error::Rejected(r)
+    }
+}
+
+/// For the `SerDes` implementations, which must return `io::Error`.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::InvalidInput(msg) => io::Error::new(io::ErrorKind::InvalidInput, msg),
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        }
+    }
+}
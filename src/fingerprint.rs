@@ -0,0 +1,622 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It renders digests in forms that people can compare over a call or at a
+//! glance, rather than by reading out 128 hex digits.
+//!
+//! The word fingerprint spells out the first 32 bytes of a digest as in the
+//! PGP word list: bytes at even positions come from a list of two-syllable
+//! words and bytes at odd positions from a list of three-syllable words, so
+//! that a skipped, repeated or swapped word is noticed. Stopping at half the
+//! digest keeps the line short enough to read out, and still leaves 128-bit
+//! security: finding two files whose words match takes about 2^128 hashes.
+//! The picture is OpenSSH's "randomart": a bishop starts in the middle of a
+//! board and takes four diagonal steps for each byte of the digest, two bits
+//! per step, and every square shows how often it was visited.
+use crate::digest::Digest;
+
+/// Bytes of the digest that the word fingerprint covers.
+pub const WORD_BYTES: usize = 32;
+
+/// Security level of the word fingerprint in bits, against collisions.
+pub const WORD_SECURITY_BITS: usize = WORD_BYTES * 8 / 2;
+
+// Size of the randomart board
+const ART_WIDTH: usize = 17;
+const ART_HEIGHT: usize = 9;
+
+// How a square looks after 0, 1, 2, ... visits; the last is for anything more
+const ART_SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^";
+
+/// The word fingerprint of a digest, one word per byte.
+pub fn words(digest: &Digest) -> Vec<&'static str> {
+    digest[..WORD_BYTES]
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if i % 2 == 0 {
+                EVEN_WORDS[b as usize]
+            } else {
+                ODD_WORDS[b as usize]
+            }
+        })
+        .collect()
+}
+
+/// The word fingerprint as a line of text.
+pub fn word_string(digest: &Digest) -> String {
+    words(digest).join(" ")
+}
+
+/// The randomart picture of a digest, framed, as lines of text without a
+/// final line break.
+pub fn randomart(digest: &Digest) -> String {
+    let mut board = [[0usize; ART_WIDTH]; ART_HEIGHT];
+    let start = (ART_WIDTH / 2, ART_HEIGHT / 2);
+    let (mut x, mut y) = start;
+    for &byte in digest.iter() {
+        for step in 0..4 {
+            let bits = byte >> (2 * step);
+            x = if bits & 1 == 1 {
+                (x + 1).min(ART_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if bits & 2 == 2 {
+                (y + 1).min(ART_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            board[y][x] += 1;
+        }
+    }
+
+    let mut lines = vec![frame("[SHA-512]")];
+    for (row, counts) in board.iter().enumerate() {
+        let squares: String = counts
+            .iter()
+            .enumerate()
+            .map(|(col, &count)| {
+                if (col, row) == (x, y) {
+                    'E'
+                } else if (col, row) == start {
+                    'S'
+                } else {
+                    ART_SYMBOLS[count.min(ART_SYMBOLS.len() - 1)] as char
+                }
+            })
+            .collect();
+        lines.push(format!("|{}|", squares));
+    }
+    lines.push(frame(""));
+    lines.join("\n")
+}
+
+// A top or bottom border with the title in the middle
+fn frame(title: &str) -> String {
+    let dashes = ART_WIDTH - title.len();
+    format!(
+        "+{}{}{}+",
+        "-".repeat(dashes / 2),
+        title,
+        "-".repeat(dashes - dashes / 2)
+    )
+}
+
+// Two-syllable words, for bytes at even positions
+const EVEN_WORDS: [&str; 256] = [
+    "aardvark",
+    "absurd",
+    "accrue",
+    "acme",
+    "adrift",
+    "adult",
+    "afflict",
+    "ahead",
+    "aimless",
+    "algol",
+    "allow",
+    "alone",
+    "ammo",
+    "ancient",
+    "apple",
+    "artist",
+    "assume",
+    "athens",
+    "atlas",
+    "aztec",
+    "baboon",
+    "backfield",
+    "backward",
+    "banjo",
+    "beaming",
+    "bedlamp",
+    "beehive",
+    "beeswax",
+    "befriend",
+    "belfast",
+    "berserk",
+    "billiard",
+    "bison",
+    "blackjack",
+    "blockade",
+    "blowtorch",
+    "bluebird",
+    "bombast",
+    "bookshelf",
+    "brackish",
+    "breadline",
+    "breakup",
+    "brickyard",
+    "briefcase",
+    "burbank",
+    "button",
+    "buzzard",
+    "cement",
+    "chairlift",
+    "chatter",
+    "checkup",
+    "chisel",
+    "choking",
+    "chopper",
+    "christmas",
+    "clamshell",
+    "classic",
+    "classroom",
+    "cleanup",
+    "clockwork",
+    "cobra",
+    "commence",
+    "concert",
+    "cowbell",
+    "crackdown",
+    "cranky",
+    "crowfoot",
+    "crucial",
+    "crumpled",
+    "crusade",
+    "cubic",
+    "dashboard",
+    "deadbolt",
+    "deckhand",
+    "dogsled",
+    "dragnet",
+    "drainage",
+    "dreadful",
+    "drifter",
+    "dropper",
+    "drumbeat",
+    "drunken",
+    "dupont",
+    "dwelling",
+    "eating",
+    "edict",
+    "egghead",
+    "eightball",
+    "endorse",
+    "endow",
+    "enlist",
+    "erase",
+    "escape",
+    "exceed",
+    "eyeglass",
+    "eyetooth",
+    "facial",
+    "fallout",
+    "flagpole",
+    "flatfoot",
+    "flytrap",
+    "fracture",
+    "framework",
+    "freedom",
+    "frighten",
+    "gazelle",
+    "geiger",
+    "glitter",
+    "glucose",
+    "goggles",
+    "goldfish",
+    "gremlin",
+    "guidance",
+    "hamlet",
+    "highchair",
+    "hockey",
+    "indoors",
+    "indulge",
+    "inverse",
+    "involve",
+    "island",
+    "jawbone",
+    "keyboard",
+    "kickoff",
+    "kiwi",
+    "klaxon",
+    "locale",
+    "lockup",
+    "merit",
+    "minnow",
+    "miser",
+    "mohawk",
+    "mural",
+    "music",
+    "necklace",
+    "neptune",
+    "newborn",
+    "nightbird",
+    "oakland",
+    "obtuse",
+    "offload",
+    "optic",
+    "orca",
+    "payday",
+    "peachy",
+    "pheasant",
+    "physique",
+    "playhouse",
+    "pluto",
+    "preclude",
+    "prefer",
+    "preshrunk",
+    "printer",
+    "prowler",
+    "pupil",
+    "puppy",
+    "python",
+    "quadrant",
+    "quiver",
+    "quota",
+    "ragtime",
+    "ratchet",
+    "rebirth",
+    "reform",
+    "regain",
+    "reindeer",
+    "rematch",
+    "repay",
+    "retouch",
+    "revenge",
+    "reward",
+    "rhythm",
+    "ribcage",
+    "ringbolt",
+    "robust",
+    "rocker",
+    "ruffled",
+    "sailboat",
+    "sawdust",
+    "scallion",
+    "scenic",
+    "scorecard",
+    "scotland",
+    "seabird",
+    "select",
+    "sentence",
+    "shadow",
+    "shamrock",
+    "showgirl",
+    "skullcap",
+    "skydive",
+    "slingshot",
+    "slowdown",
+    "snapline",
+    "snapshot",
+    "snowcap",
+    "snowslide",
+    "solo",
+    "southward",
+    "soybean",
+    "spaniel",
+    "spearhead",
+    "spellbind",
+    "spheroid",
+    "spigot",
+    "spindle",
+    "spyglass",
+    "stagehand",
+    "stagnate",
+    "stairway",
+    "standard",
+    "stapler",
+    "steamship",
+    "sterling",
+    "stockman",
+    "stopwatch",
+    "stormy",
+    "sugar",
+    "surmount",
+    "suspense",
+    "sweatband",
+    "swelter",
+    "tactics",
+    "talon",
+    "tapeworm",
+    "tempest",
+    "tiger",
+    "tissue",
+    "tonic",
+    "topmost",
+    "tracker",
+    "transit",
+    "trauma",
+    "treadmill",
+    "trojan",
+    "trouble",
+    "tumor",
+    "tunnel",
+    "tycoon",
+    "uncut",
+    "unearth",
+    "unwind",
+    "uproot",
+    "upset",
+    "upshot",
+    "vapor",
+    "village",
+    "virus",
+    "vulcan",
+    "waffle",
+    "wallet",
+    "watchword",
+    "wayside",
+    "willow",
+    "woodlark",
+    "zulu",
+];
+
+// Three-syllable words, for bytes at odd positions
+const ODD_WORDS: [&str; 256] = [
+    "adroitness",
+    "adviser",
+    "aftermath",
+    "aggregate",
+    "alkali",
+    "almighty",
+    "amulet",
+    "amusement",
+    "antenna",
+    "apollo",
+    "applicant",
+    "armistice",
+    "article",
+    "asteroid",
+    "atlantic",
+    "atmosphere",
+    "autopsy",
+    "babylon",
+    "backwater",
+    "barbecue",
+    "belowground",
+    "bifocals",
+    "bodyguard",
+    "bookseller",
+    "borderline",
+    "bottomless",
+    "bradbury",
+    "bravado",
+    "brazilian",
+    "breakaway",
+    "burlington",
+    "businessman",
+    "butterfat",
+    "camelot",
+    "candidate",
+    "cannonball",
+    "capricorn",
+    "caravan",
+    "caretaker",
+    "celebrate",
+    "cellulose",
+    "certify",
+    "chambermaid",
+    "cherokee",
+    "chicago",
+    "clergyman",
+    "coherence",
+    "combustion",
+    "commando",
+    "company",
+    "component",
+    "concurrent",
+    "confidence",
+    "conformist",
+    "congregate",
+    "consensus",
+    "consulting",
+    "corporate",
+    "corrosion",
+    "councilman",
+    "crossover",
+    "crucifix",
+    "cumbersome",
+    "customer",
+    "dakota",
+    "decadence",
+    "december",
+    "decimal",
+    "designing",
+    "detector",
+    "detergent",
+    "determine",
+    "dictator",
+    "dinosaur",
+    "direction",
+    "disable",
+    "disbelief",
+    "disruptive",
+    "distortion",
+    "document",
+    "embezzle",
+    "enchanting",
+    "enrollment",
+    "enterprise",
+    "equation",
+    "equipment",
+    "escapade",
+    "eskimo",
+    "everyday",
+    "examine",
+    "existence",
+    "exodus",
+    "fascinate",
+    "filament",
+    "finicky",
+    "forever",
+    "fortitude",
+    "frequency",
+    "gadgetry",
+    "galveston",
+    "getaway",
+    "glossary",
+    "gossamer",
+    "graduate",
+    "gravity",
+    "guitarist",
+    "hamburger",
+    "hamilton",
+    "handiwork",
+    "hazardous",
+    "headwaters",
+    "hemisphere",
+    "hesitate",
+    "hideaway",
+    "holiness",
+    "hurricane",
+    "hydraulic",
+    "impartial",
+    "impetus",
+    "inception",
+    "indigo",
+    "inertia",
+    "infancy",
+    "inferno",
+    "informant",
+    "insincere",
+    "insurgent",
+    "integrate",
+    "intention",
+    "inventive",
+    "istanbul",
+    "jamaica",
+    "jupiter",
+    "leprosy",
+    "letterhead",
+    "liberty",
+    "maritime",
+    "matchmaker",
+    "maverick",
+    "medusa",
+    "megaton",
+    "microscope",
+    "microwave",
+    "midsummer",
+    "millionaire",
+    "miracle",
+    "misnomer",
+    "molasses",
+    "molecule",
+    "montana",
+    "monument",
+    "mosquito",
+    "narrative",
+    "nebula",
+    "newsletter",
+    "norwegian",
+    "october",
+    "ohio",
+    "onlooker",
+    "opulent",
+    "orlando",
+    "outfielder",
+    "pacific",
+    "pandemic",
+    "pandora",
+    "paperweight",
+    "paragon",
+    "paragraph",
+    "paramount",
+    "passenger",
+    "pedigree",
+    "pegasus",
+    "penetrate",
+    "perceptive",
+    "performance",
+    "pharmacy",
+    "phonetic",
+    "photograph",
+    "picnic",
+    "platform",
+    "politeness",
+    "portuguese",
+    "potato",
+    "processor",
+    "provincial",
+    "proximate",
+    "puberty",
+    "publisher",
+    "pyramid",
+    "quantity",
+    "racketeer",
+    "rebellion",
+    "recipe",
+    "recover",
+    "repellent",
+    "replica",
+    "reproduce",
+    "resistor",
+    "responsive",
+    "retraction",
+    "retrieval",
+    "retrospect",
+    "revenue",
+    "revival",
+    "revolver",
+    "sandalwood",
+    "sardonic",
+    "saturday",
+    "savagery",
+    "scavenger",
+    "sensation",
+    "sociable",
+    "souvenir",
+    "specialist",
+    "speculate",
+    "stethoscope",
+    "stupendous",
+    "supportive",
+    "surrender",
+    "suspicious",
+    "sympathy",
+    "tambourine",
+    "telephone",
+    "therapist",
+    "tobacco",
+    "tolerance",
+    "tomorrow",
+    "torpedo",
+    "tradition",
+    "travesty",
+    "trombonist",
+    "truncated",
+    "typewriter",
+    "ultimate",
+    "undaunted",
+    "underfoot",
+    "unicorn",
+    "unify",
+    "universe",
+    "unravel",
+    "upcoming",
+    "vacancy",
+    "vagabond",
+    "vertigo",
+    "virginia",
+    "visitor",
+    "vocalist",
+    "voyager",
+    "warranty",
+    "waterloo",
+    "whimsical",
+    "wichita",
+    "wilmington",
+    "wyoming",
+    "yesteryear",
+    "yucatan",
+];
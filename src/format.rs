@@ -0,0 +1,279 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the optional header that precedes the parameters in a file.
+//!
+//! Files written before the header existed start directly with the 4-byte
+//! little-endian parameter n. Since n never exceeds 65535, a file starting
+//! with `MAGIC` is unambiguously a headered file, and any other file is read
+//! as a legacy one. A ceremony that uses the legacy settings keeps writing
+//! legacy files, byte-for-byte identical to what older versions produced.
+use crate::armor::read_armored_proof;
+use crate::digest::digest_bytes;
+use crate::error::{Error, Result};
+use crate::signature::{
+    read_trailer, write_trailer, ContributionSignature, HashingReader, SigningKey,
+};
+use crate::subgroup::SubgroupChecks;
+use crate::{ContributionProof, PointproofsParams};
+use std::io::{Read, Write};
+
+/// Marks the start of a headered parameter file.
+pub const MAGIC: [u8; 4] = *b"PPPG";
+
+/// Current version of the header layout.
+pub const VERSION: u8 = 1;
+
+/// Length of the header in a headered file.
+pub const HEADER_LEN: usize = 7;
+
+// Flag bits in the last header byte
+const FLAG_OMIT_GT: u8 = 0x01;
+const FLAG_UNCOMPRESSED: u8 = 0x02;
+
+/// How a contributor proves that they know the exponent they mixed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofMode {
+    /// A Schnorr proof of knowledge over G1, bound to the contributor's id string.
+    Schnorr,
+    /// A pairing-checked update proof in the style of the Ethereum KZG ceremony.
+    Pairing,
+}
+
+impl ProofMode {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            ProofMode::Schnorr => 0,
+            ProofMode::Pairing => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(b: u8) -> Result<Self> {
+        match b {
+            0 => Ok(ProofMode::Schnorr),
+            1 => Ok(ProofMode::Pairing),
+            _ => Err(Error::Format("Unknown proof mode".to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ProofMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ProofMode::Schnorr => write!(f, "schnorr"),
+            ProofMode::Pairing => write!(f, "pairing"),
+        }
+    }
+}
+
+impl std::str::FromStr for ProofMode {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "schnorr" => Ok(ProofMode::Schnorr),
+            "pairing" => Ok(ProofMode::Pairing),
+            _ => Err(Error::InvalidInput(
+                "Proof mode must be one of: schnorr, pairing".to_string(),
+            )),
+        }
+    }
+}
+
+/// Per-ceremony settings recorded at the start of every parameter file.
+/// `init` chooses them, and every later step carries them forward unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileHeader {
+    /// The kind of proof appended to each contribution.
+    pub proof_mode: ProofMode,
+    /// Whether gt_alpha_nplus1 is stored. It can always be recomputed as
+    /// e(g1^{alpha^N}, g2^alpha), so leaving it out saves 576 bytes and
+    /// rules out files where it disagrees with the other points.
+    pub include_gt: bool,
+    /// Whether points are stored compressed. Uncompressed files are twice as
+    /// large but load much faster, since no square roots are needed.
+    pub compressed: bool,
+}
+
+impl Default for FileHeader {
+    fn default() -> Self {
+        FileHeader {
+            proof_mode: ProofMode::Schnorr,
+            include_gt: true,
+            compressed: true,
+        }
+    }
+}
+
+impl FileHeader {
+    /// Returns true if files with this header are written without one.
+    pub fn is_legacy(&self) -> bool {
+        *self == FileHeader::default()
+    }
+
+    /// Writes the header, or nothing at all for legacy settings.
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        if self.is_legacy() {
+            return Ok(());
+        }
+        let mut flags = 0u8;
+        if !self.include_gt {
+            flags |= FLAG_OMIT_GT;
+        }
+        if !self.compressed {
+            flags |= FLAG_UNCOMPRESSED;
+        }
+        w.write_all(&MAGIC)?;
+        w.write_all(&[VERSION, self.proof_mode.to_byte(), flags])?;
+        Ok(())
+    }
+
+    /// Reads the rest of a header whose magic has already been consumed.
+    fn read_after_magic<R: Read>(r: &mut R) -> Result<Self> {
+        let mut buf = [0u8; 3];
+        r.read_exact(&mut buf)?;
+        if buf[0] != VERSION {
+            return Err(Error::Format(
+                "Unsupported parameter file version".to_string(),
+            ));
+        }
+        let proof_mode = ProofMode::from_byte(buf[1])?;
+        if buf[2] & !(FLAG_OMIT_GT | FLAG_UNCOMPRESSED) != 0 {
+            return Err(Error::Format("Unknown parameter file flags".to_string()));
+        }
+        Ok(FileHeader {
+            proof_mode,
+            include_gt: buf[2] & FLAG_OMIT_GT == 0,
+            compressed: buf[2] & FLAG_UNCOMPRESSED == 0,
+        })
+    }
+}
+
+/// Parses the header at the start of `bytes`, returning it along with its
+/// length in bytes (0 for a legacy file, which has no header).
+pub fn parse_header(bytes: &[u8]) -> Result<(FileHeader, usize)> {
+    if bytes.len() >= MAGIC.len() && bytes[..MAGIC.len()] == MAGIC {
+        let mut rest = &bytes[MAGIC.len()..];
+        let header = FileHeader::read_after_magic(&mut rest)?;
+        Ok((header, HEADER_LEN))
+    } else {
+        Ok((FileHeader::default(), 0))
+    }
+}
+
+/// Reads a header (if any) followed by a set of parameters.
+/// Anything after the parameters, such as a proof, is left in the reader.
+pub fn read_params<R: Read>(r: &mut R) -> Result<(FileHeader, PointproofsParams)> {
+    read_params_with_checks(r, SubgroupChecks::PerPoint)
+}
+
+/// Like `read_params`, but lets the caller choose how points are subgroup-checked.
+pub fn read_params_with_checks<R: Read>(
+    r: &mut R,
+    checks: SubgroupChecks,
+) -> Result<(FileHeader, PointproofsParams)> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    if buf == MAGIC {
+        let header = FileHeader::read_after_magic(r)?;
+        let params = PointproofsParams::deserialize_with_checks(r, &header, checks)?;
+        Ok((header, params))
+    } else {
+        // legacy file: the four bytes we consumed are the parameter n
+        let header = FileHeader::default();
+        let params =
+            PointproofsParams::deserialize_with_checks(&mut (&buf[..]).chain(r), &header, checks)?;
+        Ok((header, params))
+    }
+}
+
+/// Reads a file written by `write_contribution`.
+pub fn read_contribution<R: Read>(
+    r: &mut R,
+) -> Result<(FileHeader, PointproofsParams, ContributionProof)> {
+    read_contribution_with_checks(r, SubgroupChecks::PerPoint)
+}
+
+/// Like `read_contribution`, but lets the caller choose how the parameters are subgroup-checked.
+/// The few points of the proof are always checked one by one.
+/// The proof may also be armored, as by `armor::armor_proof`.
+pub fn read_contribution_with_checks<R: Read>(
+    r: &mut R,
+    checks: SubgroupChecks,
+) -> Result<(FileHeader, PointproofsParams, ContributionProof)> {
+    let (header, params) = read_params_with_checks(r, checks)?;
+    let mut first = [0u8; 1];
+    r.read_exact(&mut first)?;
+    // a binary proof starts with a point, whose first byte is never '-'
+    let proof = if first[0] == b'-' {
+        read_armored_proof(&mut (&first[..]).chain(&mut *r), header.proof_mode)?
+    } else {
+        ContributionProof::deserialize(
+            &mut (&first[..]).chain(&mut *r),
+            header.proof_mode,
+            header.compressed,
+        )?
+    };
+    Ok((header, params, proof))
+}
+
+/// Writes a header (if any) followed by a set of parameters and the proof for them.
+pub fn write_contribution<W: Write>(
+    w: &mut W,
+    header: &FileHeader,
+    params: &PointproofsParams,
+    proof: &ContributionProof,
+) -> Result<()> {
+    write_params(w, header, params)?;
+    proof.serialize(w, header.compressed)
+}
+
+/// Like `read_contribution_with_checks`, but also reads the signature that
+/// may follow the proof.
+pub fn read_signed_contribution_with_checks<R: Read>(
+    r: &mut R,
+    checks: SubgroupChecks,
+) -> Result<(
+    FileHeader,
+    PointproofsParams,
+    ContributionProof,
+    Option<ContributionSignature>,
+)> {
+    let mut hashing = HashingReader::new(&mut *r);
+    let (header, params, proof) = read_contribution_with_checks(&mut hashing, checks)?;
+    let digest = hashing.finish();
+    let signature = read_trailer(r)?.map(|signature| ContributionSignature { digest, signature });
+    Ok((header, params, proof, signature))
+}
+
+/// Writes a contribution like `write_contribution`, followed by the
+/// signature of `key` on its digest.
+pub fn write_signed_contribution<W: Write>(
+    w: &mut W,
+    header: &FileHeader,
+    params: &PointproofsParams,
+    proof: &ContributionProof,
+    key: &SigningKey,
+) -> Result<()> {
+    let mut buf = vec![];
+    write_contribution(&mut buf, header, params, proof)?;
+    let digest = digest_bytes(&buf);
+    let signature = ContributionSignature {
+        signature: key.sign(&digest),
+        digest,
+    };
+    if !signature.verify(&key.public_key()) {
+        return Err(Error::Fault(
+            "the signature on the new contribution does not verify".to_string(),
+        ));
+    }
+    w.write_all(&buf)?;
+    write_trailer(w, &signature.signature)
+}
+
+/// Writes a header (if any) followed by a set of parameters.
+pub fn write_params<W: Write>(
+    w: &mut W,
+    header: &FileHeader,
+    params: &PointproofsParams,
+) -> Result<()> {
+    header.write(w)?;
+    params.serialize_with(w, header)
+}
@@ -1,4 +1,4 @@
-#![cfg_attr(feature = "cargo-clippy", allow(clippy::many_single_char_names))]
+#![allow(clippy::many_single_char_names)]
 extern crate ff_zeroize as ff;
 extern crate pairing_plus as pairing_plus;
 extern crate rand;
@@ -7,25 +7,73 @@ extern crate zeroize;
 #[cfg(test)]
 mod test;
 
+pub mod armor;
+pub mod audit;
+pub mod checkpoint;
+pub mod consistency;
+pub mod digest;
+pub mod encoding;
+pub mod error;
+pub mod fingerprint;
+pub mod format;
 pub mod hash_to_field_pointproofs;
+pub mod mailbox;
+pub mod manifest;
+pub mod openpgp;
+pub mod paranoid;
+pub mod progress;
+pub mod receipt;
 pub mod schnorr;
-
+pub mod signature;
+pub mod small_exponent;
+pub mod spot_check;
+pub mod subgroup;
+pub mod transcript;
+pub mod update_proof;
+pub mod view;
+
+use crate::checkpoint::PartialParams;
+use crate::consistency::{CheckProgress, ConsistencyChecker};
+use crate::digest::{params_digest, Digest};
+use crate::encoding::{read_point, read_point_unchecked, write_point};
+use crate::error::{Error, Result, MAX_N};
+use crate::format::{FileHeader, ProofMode};
 use crate::hash_to_field_pointproofs::*;
+use crate::progress::{Hooks, Phase};
 use crate::schnorr::{make_pok, verify_pok, PoK};
+use crate::small_exponent::{degenerate_exponent_g1, degenerate_exponent_g2};
+use crate::spot_check::{spot_check, SPOT_CHECKS};
+use crate::subgroup::{first_not_in_subgroup, SubgroupChecks};
+use crate::update_proof::{make_update_proof, verify_update_proof, UpdateProof};
 use ff::Field;
 use ff::PrimeField;
-use pairing_plus::bls12_381;
-use pairing_plus::bls12_381::{Bls12, Fq12, Fr, FrRepr, G1Affine, G2Affine, G1, G2};
+use pairing_plus::bls12_381::{Bls12, Fq12, Fr, G1Affine, G2Affine, G1, G2};
 use pairing_plus::serdes::SerDes;
 use pairing_plus::Engine;
 use pairing_plus::{CurveAffine, CurveProjective};
 use rand::rngs::OsRng;
 use rand::RngCore;
 use std::convert::TryInto;
-use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::fmt;
+use std::io::{Read, Write};
 use zeroize::Zeroize;
 //const N: usize = 1024;
 
+/// The value hashed to obtain the alpha of the initial parameters:
+/// the first 100 digits of pi.
+// 3 .
+// 1 4 1 5 9 2 6 5 3 5 8 9 7 9 3 2 3 8 4 6
+// 2 6 4 3 3 8 3 2 7 9 5 0 2 8 8 4 1 9 7 1
+// 6 9 3 9 9 3 7 5 1 0 5 8 2 0 9 7 4 9 4 4
+// 5 9 2 3 0 7 8 1 6 4 0 6 2 8 6 2 0 8 9 9
+// 8 6 2 8 0 3 4 8 2 5 3 4 2 1 1 7 0 6 7 9
+pub const INIT_ALPHA_SEED: &str = "31415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+
+/// The alpha of the initial parameters, which has no entropy.
+pub fn init_alpha() -> Fr {
+    hash_to_field_pointproofs(INIT_ALPHA_SEED)
+}
+
 #[derive(Debug, PartialEq)]
 pub struct PointproofsParams {
     /// parameter N
@@ -47,54 +95,51 @@ pub struct PointproofsParams {
     pub gt_alpha_nplus1: Fq12,
 }
 
-impl SerDes for PointproofsParams {
-    fn serialize<W: Write>(&self, w: &mut W, compressed: bool) -> Result<()> {
-        if !compressed {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Pointproofs params can only be (de)serialized with compressed=true",
-            ));
-        }
-        if self.n > (u32::max_value() as usize) {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid n",
-            ));
+impl PointproofsParams {
+    /// Serializes the parameters with the given file settings.
+    /// The header itself is not written; see `format::write_params`.
+    pub fn serialize_with<W: Write>(&self, w: &mut W, header: &FileHeader) -> Result<()> {
+        if self.n > (u32::MAX as usize) {
+            return Err(Error::SizeLimit(self.n));
         }
         w.write_all(&(self.n as u32).to_le_bytes())?;
         for pt in &self.g1_alpha_1_to_n {
-            pt.serialize(w, true)?;
+            write_point(w, pt, header.compressed)?;
         }
         for pt in &self.g1_alpha_nplus2_to_2n {
-            pt.serialize(w, true)?;
+            write_point(w, pt, header.compressed)?;
         }
         for pt in &self.g2_alpha_1_to_n {
-            pt.serialize(w, true)?;
+            write_point(w, pt, header.compressed)?;
         }
         for pt in &self.g2_alpha_nplus2_to_2n {
-            pt.serialize(w, true)?;
+            write_point(w, pt, header.compressed)?;
+        }
+        if header.include_gt {
+            self.gt_alpha_nplus1.serialize(w, true)?;
         }
-        self.gt_alpha_nplus1.serialize(w, true)?;
         Ok(())
     }
-    fn deserialize<R: Read>(r: &mut R, compressed: bool) -> Result<Self> {
-        if !compressed {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Pointproofs params can only be (de)serialized with compressed=true",
-            ));
-        }
 
+    /// Deserializes parameters written with the given file settings.
+    /// If the file leaves out gt_alpha_nplus1, it is recomputed from the other points.
+    pub fn deserialize_with<R: Read>(r: &mut R, header: &FileHeader) -> Result<Self> {
+        PointproofsParams::deserialize_with_checks(r, header, SubgroupChecks::PerPoint)
+    }
+
+    /// Like `deserialize_with`, but lets the caller choose how points are subgroup-checked.
+    pub fn deserialize_with_checks<R: Read>(
+        r: &mut R,
+        header: &FileHeader,
+        checks: SubgroupChecks,
+    ) -> Result<Self> {
         // read parameter n
         let mut buf = [0u8; 4];
         r.read_exact(&mut buf)?;
         let n = u32::from_le_bytes(buf) as usize;
 
-        if n > 65535 || n == 0 {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "The size of n has passed the maximal allowed value.",
-            ));
+        if n > MAX_N || n == 0 {
+            return Err(Error::SizeLimit(n));
         }
 
         let mut g1_alpha_1_to_n: Vec<G1Affine> = vec![];
@@ -103,24 +148,67 @@ impl SerDes for PointproofsParams {
         let mut g2_alpha_nplus2_to_2n: Vec<G2Affine> = vec![];
         let gt_alpha_nplus1: Fq12;
 
+        // with batched checks, points are only checked to be on the curve here
+        let read_g1 = |r: &mut R| -> Result<G1Affine> {
+            match checks {
+                SubgroupChecks::PerPoint => read_point(r, header.compressed),
+                SubgroupChecks::Batched => read_point_unchecked(r, header.compressed),
+            }
+        };
+        let read_g2 = |r: &mut R| -> Result<G2Affine> {
+            match checks {
+                SubgroupChecks::PerPoint => read_point(r, header.compressed),
+                SubgroupChecks::Batched => read_point_unchecked(r, header.compressed),
+            }
+        };
+
         for _ in 0..n {
-            let tmp = G1Affine::deserialize(r, true)?;
+            let tmp = read_g1(r)?;
             g1_alpha_1_to_n.push(tmp);
         }
         for _ in 0..n - 1 {
-            let tmp = G1Affine::deserialize(r, true)?;
+            let tmp = read_g1(r)?;
             g1_alpha_nplus2_to_2n.push(tmp);
         }
         for _ in 0..n {
-            let tmp = G2Affine::deserialize(r, true)?;
+            let tmp = read_g2(r)?;
             g2_alpha_1_to_n.push(tmp);
         }
         for _ in 0..n - 1 {
-            let tmp = G2Affine::deserialize(r, true)?;
+            let tmp = read_g2(r)?;
             g2_alpha_nplus2_to_2n.push(tmp);
         }
 
-        gt_alpha_nplus1 = Fq12::deserialize(r, true)?;
+        if checks == SubgroupChecks::Batched {
+            let g1s: Vec<G1Affine> = g1_alpha_1_to_n
+                .iter()
+                .chain(g1_alpha_nplus2_to_2n.iter())
+                .copied()
+                .collect();
+            if let Some(i) = first_not_in_subgroup(&g1s) {
+                return Err(not_in_subgroup_error("G1", i, n));
+            }
+            let g2s: Vec<G2Affine> = g2_alpha_1_to_n
+                .iter()
+                .chain(g2_alpha_nplus2_to_2n.iter())
+                .copied()
+                .collect();
+            if let Some(i) = first_not_in_subgroup(&g2s) {
+                return Err(not_in_subgroup_error("G2", i, n));
+            }
+        }
+
+        if header.include_gt {
+            gt_alpha_nplus1 = Fq12::deserialize(r, true)?;
+            if !gt_in_subgroup(&gt_alpha_nplus1) {
+                return Err(Error::NotInSubgroup(
+                    "gt_alpha_nplus1 is not an element of order r in GT".to_string(),
+                ));
+            }
+        } else {
+            // e(g1^{alpha^N}, g2^alpha) = e(g1, g2)^{alpha^{N+1}}
+            gt_alpha_nplus1 = g1_alpha_1_to_n[n - 1].pairing_with(&g2_alpha_1_to_n[0]);
+        }
 
         Ok(PointproofsParams {
             n,
@@ -133,6 +221,54 @@ impl SerDes for PointproofsParams {
     }
 }
 
+// Describes which point failed a batched subgroup check; `i` is its position
+// among the 2N - 1 points of the group
+fn not_in_subgroup_error(group: &str, i: usize, n: usize) -> Error {
+    let power = if i < n { i + 1 } else { i + 2 };
+    Error::NotInSubgroup(format!(
+        "{} point for alpha^{} is not in the subgroup",
+        group, power
+    ))
+}
+
+impl SerDes for PointproofsParams {
+    fn serialize<W: Write>(&self, w: &mut W, compressed: bool) -> std::io::Result<()> {
+        let header = FileHeader {
+            compressed,
+            ..FileHeader::default()
+        };
+        Ok(self.serialize_with(w, &header)?)
+    }
+    fn deserialize<R: Read>(r: &mut R, compressed: bool) -> std::io::Result<Self> {
+        let header = FileHeader {
+            compressed,
+            ..FileHeader::default()
+        };
+        Ok(PointproofsParams::deserialize_with(r, &header)?)
+    }
+}
+
+/// Returns true if x is a nonzero element of the cyclotomic subgroup of Fq12
+/// whose order is exactly r, i.e., a non-identity element of GT.
+pub fn gt_in_subgroup(x: &Fq12) -> bool {
+    if x.is_zero() || *x == Fq12::one() {
+        return false;
+    }
+
+    // x is in the cyclotomic subgroup iff x^{p^4 - p^2 + 1} = 1, i.e. x^{p^4} * x = x^{p^2}
+    let mut lhs = *x;
+    lhs.frobenius_map(4);
+    lhs.mul_assign(x);
+    let mut rhs = *x;
+    rhs.frobenius_map(2);
+    if lhs != rhs {
+        return false;
+    }
+
+    // r is prime, so x^r = 1 for x != 1 means x has order r
+    x.pow(Fr::char()) == Fq12::one()
+}
+
 fn random_scalar() -> Fr {
     let mut r: [u8; 64] = [0; 64];
     OsRng {}.fill_bytes(&mut r[..]);
@@ -144,145 +280,265 @@ fn random_scalar() -> Fr {
 
 // Checks that a set of parameters are in the correct form (g2^alpha, g2^alpha^2, etc.) for some alpha
 pub fn consistent(params: &PointproofsParams) -> bool {
-    // First, check all points are in the group, nonzero, and not the generator
-    // (Subgroup check is already done in our deserialization code)
-    if params
-        .g2_alpha_1_to_n
-        .iter()
-        .any(|&x| x == G2Affine::zero() || x == G2Affine::one())
-    {
-        return false;
-    }
+    ConsistencyChecker::new().check(params)
+}
 
-    if params
-        .g2_alpha_nplus2_to_2n
-        .iter()
-        .any(|&x| x == G2Affine::zero() || x == G2Affine::one())
-    {
-        return false;
-    }
+/// Like `consistent`, but draws the random challenges from `rng`.
+pub fn consistent_with_rng<R: RngCore>(params: &PointproofsParams, rng: &mut R) -> bool {
+    ConsistencyChecker::new().check_with_rng(params, rng)
+}
 
-    if params
-        .g1_alpha_1_to_n
-        .iter()
-        .any(|&x| x == G1Affine::zero() || x == G1Affine::one())
-    {
-        return false;
-    }
+pub fn check_rerandomization(
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &PoK,
+    id: &[u8],
+) -> bool {
+    degenerate_exponent_g1(&proof.g1x).is_none()
+        && check_pok_link(params, g2alpha_old, proof, id)
+        && consistent(params)
+}
 
-    if params
-        .g1_alpha_nplus2_to_2n
-        .iter()
-        .any(|&x| x == G1Affine::zero() || x == G1Affine::one())
-    {
-        return false;
-    }
+// Checks the proof of knowledge, and that the exponent it proves knowledge of
+// is the one that takes g2alpha_old to the new g_2^alpha
+fn check_pok_link(
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &PoK,
+    id: &[u8],
+) -> bool {
+    let g1inv = {
+        let mut g = G1Affine::one();
+        g.negate();
+        g
+    };
 
-    // Generate N random scalars r_1, ..., r_N
-    let mut rs_owned: Vec<FrRepr> = vec![];
-    let mut rs: Vec<&[u64; 4]> = vec![];
-    for _ in 0..params.n {
-        let r = random_scalar().into_repr();
-        rs_owned.push(r);
-    }
-    for item in rs_owned.iter() {
-        rs.push(&item.0);
-    }
+    verify_pok(proof, id)
+        && (Bls12::pairing_product(proof.g1x, g2alpha_old, g1inv, params.g2_alpha_1_to_n[0])
+            == Fq12::one())
+}
 
-    // Compute:
-    // S = prod_{i=1}^{N-1} ("g_1^{alpha^i}")^{r_i}
-    // R_1 = prod_{i=1}^{N} ("g_1^{alpha^i}")^{r_i} = S * ("g_1^{alpha^N}")^{r_N}
-    // R_2 = prod_{i=1}^{N} ("g_2^{alpha^i}")^{r_i}
-    // T = prod{i=1}^{N-1} ("g_1^{alpha^{i+1}}")^{r_i}
-    // U_1 = prod{i=1}^{N-1} ("g_1^{alpha^{i+N+1}")^{r_i}
-    // U_2 = prod{i=1}^{N-1} ("g_2^{alpha^{i+N+1}")^{r_i}
-
-    let pt_s: bls12_381::G1Affine = G1Affine::sum_of_products(
-        &params.g1_alpha_1_to_n[0..params.n - 1],
-        &rs[0..params.n - 1],
-    )
-    .into_affine();
-    let pt_r1: bls12_381::G1Affine = {
-        let mut tmp = params.g1_alpha_1_to_n[params.n - 1]
-            .mul(Fr::from_repr(rs_owned[params.n - 1]).unwrap());
-        tmp.add_assign_mixed(&pt_s);
-        tmp.into_affine()
-    };
-    let pt_r2 = G2Affine::sum_of_products(&params.g2_alpha_1_to_n[0..params.n], &rs[0..params.n])
-        .into_affine();
-    let pt_t =
-        G1Affine::sum_of_products(&params.g1_alpha_1_to_n[1..params.n], &rs[0..params.n - 1])
-            .into_affine();
-    let pt_u1 = G1Affine::sum_of_products(
-        &params.g1_alpha_nplus2_to_2n[0..params.n - 1],
-        &rs[0..params.n - 1],
-    )
-    .into_affine();
-    let pt_u2 = G2Affine::sum_of_products(
-        &params.g2_alpha_nplus2_to_2n[0..params.n - 1],
-        &rs[0..params.n - 1],
-    )
-    .into_affine();
+/// Why a contribution was rejected.
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    /// No properly signed message was broadcast.
+    Missing,
+    /// The message could not be read or parsed.
+    Malformed(String),
+    /// The message was written with different file settings than the ceremony uses.
+    SettingsMismatch,
+    /// The mixed-in exponent is k or -k for a small k, so anyone can guess it.
+    DegenerateExponent(u64),
+    /// The new parameters are identical to those of the earlier message with this index.
+    Replayed(usize),
+    /// The proof does not verify against the previous parameters and id string.
+    BadProof,
+    /// The new parameters are not of the form g^{alpha^i}.
+    Inconsistent,
+    /// The message was not broadcast during the participant's timeslot.
+    OutsideSlot,
+    /// The id string embeds a public key, but the message carries no valid signature under it.
+    BadSignature,
+    /// A receipt does not describe the files it was checked against.
+    ReceiptMismatch(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::Missing => write!(f, "no message"),
+            Rejection::Malformed(e) => write!(f, "malformed message: {}", e),
+            Rejection::SettingsMismatch => write!(f, "file settings differ from the ceremony's"),
+            Rejection::DegenerateExponent(k) => {
+                write!(f, "mixed-in exponent is trivially known (+/-{})", k)
+            }
+            Rejection::Replayed(i) => write!(f, "parameters are identical to message {}", i),
+            Rejection::BadProof => write!(f, "proof incorrect"),
+            Rejection::Inconsistent => write!(f, "parameters are not consistent"),
+            Rejection::BadSignature => write!(f, "signature missing or incorrect"),
+            Rejection::ReceiptMismatch(e) => write!(f, "{}", e),
+            Rejection::OutsideSlot => write!(f, "not broadcast during the participant's timeslot"),
+        }
+    }
+}
 
-    let g2 = G2Affine::one();
-    let g1 = G1Affine::one();
-    let g2alpha = &params.g2_alpha_1_to_n[0];
+/// The proof appended to a contribution, in whichever mode the ceremony uses.
+pub enum ContributionProof {
+    Schnorr(PoK),
+    Pairing(UpdateProof),
+}
 
-    // Then check
-    // 1: e(R_1, g_2) = e(g_1, R_2)
-    // which essentially checks e("g_1^{alpha^i}", g_2) = e(g_1, "g_2^{alpha^i}") for all 1<=i<=N
-    if g2.pairing_with(&pt_r1) != g1.pairing_with(&pt_r2) {
-        return false;
+impl ContributionProof {
+    pub fn mode(&self) -> ProofMode {
+        match self {
+            ContributionProof::Schnorr(_) => ProofMode::Schnorr,
+            ContributionProof::Pairing(_) => ProofMode::Pairing,
+        }
     }
 
-    // 2: e(S, g_2^alpha) = e(T, g_2)
-    // which essentially checks e("g_1^{alpha^i}", g_2^alpha) = e(g_1^{alpha^{i+1}}, g_2) for all 1<=i<=N-1
-    if pt_s.pairing_with(g2alpha) != pt_t.pairing_with(&g2) {
-        return false;
+    pub fn deserialize<R: Read>(r: &mut R, mode: ProofMode, compressed: bool) -> Result<Self> {
+        match mode {
+            ProofMode::Schnorr => Ok(ContributionProof::Schnorr(PoK::deserialize(r, compressed)?)),
+            ProofMode::Pairing => Ok(ContributionProof::Pairing(UpdateProof::deserialize(
+                r, compressed,
+            )?)),
+        }
     }
 
-    // 3: e(g_1^{alpha^N}, g_2^alpha) = "e(g_1, g_2)^{alpha^{N+1}}"
-    let mut tmp = params.g1_alpha_1_to_n[params.n - 1].pairing_with(g2alpha);
-    tmp.sub_assign(&params.gt_alpha_nplus1);
-    if !tmp.is_zero() {
-        return false;
+    pub fn serialize<W: Write>(&self, w: &mut W, compressed: bool) -> Result<()> {
+        match self {
+            ContributionProof::Schnorr(pok) => pok.serialize(w, compressed)?,
+            ContributionProof::Pairing(proof) => proof.serialize(w, compressed)?,
+        }
+        Ok(())
     }
+}
 
-    // 4: e(T, g_2^{alpha^N}) = e(U_1, g_2) = e(g_1, U_2)
-    let tmp = pt_t.pairing_with(&params.g2_alpha_1_to_n[params.n - 1]);
-    if tmp != pt_u1.pairing_with(&g2) || tmp != pt_u2.pairing_with(&g1) {
-        return false;
+/// Checks a contribution made in either proof mode.
+/// In pairing mode the id string is not bound to the proof; it is up to the
+/// signature on the broadcast message to tie the contribution to a participant.
+pub fn check_contribution(
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &ContributionProof,
+    id: &[u8],
+) -> bool {
+    check_contribution_with_reason(params, g2alpha_old, proof, id, &[]).is_ok()
+}
+
+/// Like `check_contribution`, but also rejects parameters identical to any of
+/// the `earlier` messages, and reports why a contribution was rejected.
+/// `earlier` holds the `params_digest` of every earlier message in the transcript.
+pub fn check_contribution_with_reason(
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &ContributionProof,
+    id: &[u8],
+    earlier: &[Digest],
+) -> std::result::Result<(), Rejection> {
+    check_contribution_with_checker(
+        &ConsistencyChecker::new(),
+        params,
+        g2alpha_old,
+        proof,
+        id,
+        earlier,
+    )
+}
+
+/// Like `check_contribution_with_reason`, but runs the consistency check with
+/// `checker`, so that one checker can be reused across a whole transcript.
+pub fn check_contribution_with_checker(
+    checker: &ConsistencyChecker,
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &ContributionProof,
+    id: &[u8],
+    earlier: &[Digest],
+) -> std::result::Result<(), Rejection> {
+    check_proof(params, g2alpha_old, proof, id, earlier)?;
+    // like `ConsistencyChecker::check`, this treats a check that could not run as failed
+    if !checker.check(params) {
+        return Err(Rejection::Inconsistent);
     }
+    Ok(())
+}
 
-    true
+/// Like `check_contribution_with_checker`, but reports the progress of the
+/// consistency check and can be cancelled through `hooks`.
+/// Fails with `Error::Rejected` or `Error::Cancelled`.
+pub fn check_contribution_with_hooks(
+    checker: &ConsistencyChecker,
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &ContributionProof,
+    id: &[u8],
+    earlier: &[Digest],
+    hooks: &Hooks,
+) -> Result<()> {
+    check_contribution_resumable(
+        checker,
+        params,
+        g2alpha_old,
+        proof,
+        id,
+        earlier,
+        CheckProgress::default(),
+        hooks,
+        &mut |_| Ok(()),
+    )
 }
 
-pub fn check_rerandomization(
+/// Like `check_contribution_with_hooks`, but resumes the consistency check
+/// from `progress`; see `ConsistencyChecker::check_resumable`.
+/// The other checks are cheap and always run in full.
+#[allow(clippy::too_many_arguments)]
+pub fn check_contribution_resumable(
+    checker: &ConsistencyChecker,
     params: &PointproofsParams,
     g2alpha_old: G2Affine,
-    proof: &PoK,
+    proof: &ContributionProof,
     id: &[u8],
-) -> bool {
-    let g1inv = {
-        let mut g = G1Affine::one();
-        g.negate();
-        g
+    earlier: &[Digest],
+    progress: CheckProgress,
+    hooks: &Hooks,
+    on_progress: &mut dyn FnMut(&CheckProgress) -> Result<()>,
+) -> Result<()> {
+    check_proof(params, g2alpha_old, proof, id, earlier)?;
+    if !checker.check_resumable(params, progress, hooks, on_progress)? {
+        return Err(Rejection::Inconsistent.into());
+    }
+    Ok(())
+}
+
+// The checks of a contribution other than the consistency check, which are cheap
+fn check_proof(
+    params: &PointproofsParams,
+    g2alpha_old: G2Affine,
+    proof: &ContributionProof,
+    id: &[u8],
+    earlier: &[Digest],
+) -> std::result::Result<(), Rejection> {
+    let degenerate = match proof {
+        ContributionProof::Schnorr(pok) => degenerate_exponent_g1(&pok.g1x),
+        ContributionProof::Pairing(proof) => degenerate_exponent_g2(&proof.g2_delta),
     };
+    if let Some(k) = degenerate {
+        return Err(Rejection::DegenerateExponent(k));
+    }
 
-    verify_pok(&proof, id)
-        && (Bls12::pairing_product(proof.g1x, g2alpha_old, g1inv, params.g2_alpha_1_to_n[0])
-            == Fq12::one())
-        && consistent(params)
+    let digest = params_digest(params);
+    if let Some(i) = earlier.iter().position(|d| d[..] == digest[..]) {
+        return Err(Rejection::Replayed(i));
+    }
+
+    let proof_ok = match proof {
+        ContributionProof::Schnorr(pok) => check_pok_link(params, g2alpha_old, pok, id),
+        ContributionProof::Pairing(proof) => {
+            verify_update_proof(proof, g2alpha_old, params.g1_alpha_1_to_n[0])
+        }
+    };
+    if !proof_ok {
+        return Err(Rejection::BadProof);
+    }
+    Ok(())
 }
 
 pub fn generate(alpha: Fr, n: usize) -> PointproofsParams {
+    generate_with_hooks(alpha, n, &Hooks::default()).expect("only cancellation can fail")
+}
+
+/// Like `generate`, but reports progress and can be cancelled through `hooks`.
+pub fn generate_with_hooks(alpha: Fr, n: usize, hooks: &Hooks) -> Result<PointproofsParams> {
+    let total = 2 * n - 1;
     let mut g1_alpha_1_to_n: Vec<G1Affine> = vec![]; // [G1Affine; N] = [G1Affine::zero(); N];
     let mut g1_alpha_nplus2_to_2n: Vec<G1Affine> = vec![]; //[G1Affine; N - 1] = [G2Affine::zero(); N - 1];
     let mut g2_alpha_1_to_n: Vec<G2Affine> = vec![]; //[G2Affine; N] = [G2Affine::zero(); N];
     let mut g2_alpha_nplus2_to_2n: Vec<G2Affine> = vec![]; //[G2Affine; N - 1] = [G2Affine::zero(); N - 1];
 
     let mut scalar: Fr = alpha;
-    for _ in 1..=n {
+    for i in 1..=n {
+        hooks.step(Phase::Generate, i - 1, total)?;
         // scalar = alpha^i
 
         let mut pt1 = G2::one();
@@ -306,7 +562,8 @@ pub fn generate(alpha: Fr, n: usize) -> PointproofsParams {
     scalar.mul_assign(&alpha);
 
     // scalar = alpha^{N+2}
-    for _ in 1..n {
+    for i in 1..n {
+        hooks.step(Phase::Generate, n + i - 1, total)?;
         let mut pt1 = G2::one();
         pt1.mul_assign(scalar);
         g2_alpha_nplus2_to_2n.push(pt1.into_affine());
@@ -319,15 +576,16 @@ pub fn generate(alpha: Fr, n: usize) -> PointproofsParams {
 
         scalar.mul_assign(&alpha);
     }
+    hooks.step(Phase::Generate, total, total)?;
 
-    PointproofsParams {
+    Ok(PointproofsParams {
         n,
         g1_alpha_1_to_n,
         g1_alpha_nplus2_to_2n,
         g2_alpha_1_to_n,
         g2_alpha_nplus2_to_2n,
         gt_alpha_nplus1,
-    }
+    })
 }
 
 pub fn rerandomize<B: AsRef<[u8]>>(
@@ -335,61 +593,65 @@ pub fn rerandomize<B: AsRef<[u8]>>(
     entropy: B,
     id: &[u8],
 ) -> (PointproofsParams, PoK) {
-    // alpha = HashToScalar("Rerandomize" || len(entropy) as 8-byte big-endian || entropy)
-    let alpha: Fr = {
-        let mut hash_input: Vec<u8> = vec![];
-        hash_input.extend_from_slice(b"Rerandomize"); // domain separation
-        let len_entropy: u64 = id.len().try_into().unwrap(); // This unwrap would only fail if entropy were more than 2^64 bytes long
-        hash_input.extend_from_slice(&len_entropy.to_be_bytes());
-        hash_input.extend_from_slice(&entropy.as_ref());
-        let alpha: Fr = hash_to_field_pointproofs(&hash_input);
-        hash_input.zeroize();
-        alpha
-    };
-
-    let n = params.n;
-    let mut g2_alpha_1_to_n: Vec<G2Affine> = vec![]; //[G2Affine; N] = [G2Affine::zero(); N];
-    let mut g2_alpha_nplus2_to_2n: Vec<G2Affine> = vec![]; //[G2Affine; N - 1] = [G2Affine::zero(); N - 1];
-    let mut g1_alpha_1_to_n: Vec<G1Affine> = vec![]; //[G1Affine; N] = [G1Affine::zero(); N];
-    let mut g1_alpha_nplus2_to_2n: Vec<G1Affine> = vec![]; //[G1Affine; N - 1] = [G1Affine::zero(); N - 1];
+    let alpha = derive_rerandomization_scalar(entropy, id);
+    let params = rerandomize_with_scalar(params, alpha, &Hooks::default())
+        .expect("only cancellation can fail");
+    (params, make_pok(alpha, id))
+}
 
-    let mut scalar: Fr = alpha;
+/// Like `rerandomize`, but produces the kind of proof that `mode` asks for,
+/// and spot-checks the new parameters. Fails with `Error::Fault` if the spot
+/// checks find a miscomputed point.
+pub fn rerandomize_with_mode<B: AsRef<[u8]>>(
+    params: &PointproofsParams,
+    entropy: B,
+    id: &[u8],
+    mode: ProofMode,
+) -> Result<(PointproofsParams, ContributionProof)> {
+    rerandomize_with_hooks(params, entropy, id, mode, &Hooks::default())
+}
 
-    for i in 1..=n {
-        // scalar = alpha^i
-        g2_alpha_1_to_n.push(params.g2_alpha_1_to_n[i - 1].mul(scalar).into_affine());
-        g1_alpha_1_to_n.push(params.g1_alpha_1_to_n[i - 1].mul(scalar).into_affine());
-        scalar.mul_assign(&alpha);
-        // scalar = alpha^{i+1}
-    }
-    // scalar = alpha^{N+1}
-    let gt_alpha_nplus1 = g2_alpha_1_to_n[n - 1].pairing_with(&g1_alpha_1_to_n[0]);
-    scalar.mul_assign(&alpha);
+/// Like `rerandomize_with_mode`, but reports progress and can be cancelled through `hooks`.
+pub fn rerandomize_with_hooks<B: AsRef<[u8]>>(
+    params: &PointproofsParams,
+    entropy: B,
+    id: &[u8],
+    mode: ProofMode,
+    hooks: &Hooks,
+) -> Result<(PointproofsParams, ContributionProof)> {
+    let mut alpha = derive_rerandomization_scalar(entropy, id);
+    let proof = match mode {
+        ProofMode::Schnorr => ContributionProof::Schnorr(make_pok(alpha, id)),
+        ProofMode::Pairing => {
+            ContributionProof::Pairing(make_update_proof(params.g1_alpha_1_to_n[0], alpha))
+        }
+    };
+    let new_params = rerandomize_with_scalar(params, alpha, hooks).and_then(|new_params| {
+        spot_check(params, &new_params, alpha, SPOT_CHECKS, &mut OsRng)?;
+        Ok(new_params)
+    });
+    alpha.zeroize();
+    Ok((new_params?, proof))
+}
 
-    // scalar = alpha^{N+2}
-    for i in 1..n {
-        g2_alpha_nplus2_to_2n.push(
-            params.g2_alpha_nplus2_to_2n[i - 1]
-                .mul(scalar)
-                .into_affine(),
-        );
-        g1_alpha_nplus2_to_2n.push(
-            params.g1_alpha_nplus2_to_2n[i - 1]
-                .mul(scalar)
-                .into_affine(),
-        );
-        scalar.mul_assign(&alpha);
-    }
+pub(crate) fn derive_rerandomization_scalar<B: AsRef<[u8]>>(entropy: B, id: &[u8]) -> Fr {
+    // alpha = HashToScalar("Rerandomize" || len(entropy) as 8-byte big-endian || entropy)
+    let mut hash_input: Vec<u8> = vec![];
+    hash_input.extend_from_slice(b"Rerandomize"); // domain separation
+    let len_entropy: u64 = id.len().try_into().unwrap(); // This unwrap would only fail if entropy were more than 2^64 bytes long
+    hash_input.extend_from_slice(&len_entropy.to_be_bytes());
+    hash_input.extend_from_slice(entropy.as_ref());
+    let alpha: Fr = hash_to_field_pointproofs(&hash_input);
+    hash_input.zeroize();
+    alpha
+}
 
-    (
-        PointproofsParams {
-            n,
-            g1_alpha_1_to_n,
-            g1_alpha_nplus2_to_2n,
-            g2_alpha_1_to_n,
-            g2_alpha_nplus2_to_2n,
-            gt_alpha_nplus1,
-        },
-        make_pok(alpha, id),
-    )
+fn rerandomize_with_scalar(
+    params: &PointproofsParams,
+    alpha: Fr,
+    hooks: &Hooks,
+) -> Result<PointproofsParams> {
+    let mut partial = PartialParams::new();
+    partial.advance(params, alpha, 2 * params.n - 1, hooks)?;
+    partial.finish(params.n)
 }
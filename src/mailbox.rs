@@ -0,0 +1,447 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It collects the contributions broadcast on a ceremony's mailing list from
+//! an mbox archive, and writes the transcript that `verify-transcript` reads.
+//!
+//! Each message is attributed to a participant of the ceremony manifest: to
+//! the one named by the armored receipt it carries (see `armor` and
+//! `receipt`), or, if it carries none, to the one whose timeslot contains its
+//! `Date` header. The contribution is the one attachment that is neither a
+//! receipt nor a signature, and an attachment named like it with `.asc`
+//! appended is its detached OpenPGP signature (see `openpgp`).
+//!
+//! A participant's candidates are their messages that were sent within their
+//! slot, carry a contribution whose signatures verify, and agree with their
+//! receipt, if any. As in `verify_transcript`, participants are taken in
+//! order, and each one's contribution is their first candidate that checks
+//! out against the latest contribution chosen before it, so that neither a
+//! broken first attempt nor a message from someone else locks out a later
+//! good one. Their other candidates are ignored.
+use crate::consistency::ConsistencyChecker;
+use crate::digest::{params_digest, Digest};
+use crate::error::{Error, Result};
+use crate::format::{read_params_with_checks, read_signed_contribution_with_checks, FileHeader};
+use crate::manifest::Manifest;
+use crate::openpgp::{check_detached_signature, Keyring};
+use crate::receipt::{Receipt, RECEIPT_HEADER};
+use crate::signature::check_signature;
+use crate::subgroup::SubgroupChecks;
+use crate::transcript::{Transcript, TranscriptEntry};
+use crate::{check_contribution_with_checker, ContributionProof, PointproofsParams, Rejection};
+use mailparse::{parse_mail, MailHeaderMap, ParsedMail};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+// Marks an armored receipt anywhere in a message
+const ARMORED_RECEIPT: &str = "-----BEGIN POINTPROOFS RECEIPT-----";
+
+/// Splits an mbox archive into its messages. Each message starts with a
+/// `From ` line, which is dropped, and `>From ` lines are unquoted as in the
+/// mboxrd format.
+pub fn split_mbox(mbox: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = vec![];
+    let mut current: Option<Vec<u8>> = None;
+    for line in mbox.split_inclusive(|&b| b == b'\n') {
+        if line.starts_with(b"From ") {
+            messages.extend(current.replace(vec![]));
+            continue;
+        }
+        // text before the first From line is not a message
+        if let Some(message) = current.as_mut() {
+            let quotes = line.iter().take_while(|&&b| b == b'>').count();
+            if quotes > 0 && line[quotes..].starts_with(b"From ") {
+                message.extend_from_slice(&line[1..]);
+            } else {
+                message.extend_from_slice(line);
+            }
+        }
+    }
+    messages.extend(current);
+    messages
+}
+
+pub struct Attachment {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+/// What the ingestion needs from one mail message.
+pub struct MailMessage {
+    pub from: String,
+    /// The `Date` header in Unix seconds, if it has a valid one.
+    pub date: Option<u64>,
+    /// The receipt in the message body or attached to it, if any.
+    pub receipt: Option<Receipt>,
+    /// The attachments other than the receipt.
+    pub attachments: Vec<Attachment>,
+}
+
+impl MailMessage {
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mail = parse_mail(raw).map_err(bad)?;
+        let from = mail.headers.get_first_value("From").unwrap_or_default();
+        let date = mail
+            .headers
+            .get_first_value("Date")
+            .and_then(|d| mailparse::dateparse(&d).ok())
+            .and_then(|d| if d >= 0 { Some(d as u64) } else { None });
+        let mut message = MailMessage {
+            from,
+            date,
+            receipt: None,
+            attachments: vec![],
+        };
+        message.collect(&mail)?;
+        Ok(message)
+    }
+
+    // Walks the MIME tree, sorting leaves into the receipt and attachments
+    fn collect(&mut self, part: &ParsedMail) -> Result<()> {
+        if !part.subparts.is_empty() {
+            for subpart in part.subparts.iter() {
+                self.collect(subpart)?;
+            }
+            return Ok(());
+        }
+        let filename = part
+            .get_content_disposition()
+            .params
+            .get("filename")
+            .or_else(|| part.ctype.params.get("name"))
+            .cloned();
+        let data = part.get_body_raw().map_err(bad)?;
+        let text = String::from_utf8_lossy(&data);
+        let is_receipt = text.contains(ARMORED_RECEIPT)
+            || (filename.is_some() && text.starts_with(RECEIPT_HEADER));
+        if is_receipt {
+            if self.receipt.is_some() {
+                return Err(Error::Format(
+                    "Bad mail message: it carries several receipts".to_string(),
+                ));
+            }
+            self.receipt = Some(Receipt::parse(&text)?);
+        } else if let Some(filename) = filename {
+            self.attachments.push(Attachment { filename, data });
+        }
+        Ok(())
+    }
+
+    /// The attached contribution: the one attachment that is not a signature.
+    pub fn contribution(&self) -> std::result::Result<&Attachment, Rejection> {
+        let mut candidates = self
+            .attachments
+            .iter()
+            .filter(|a| !a.filename.ends_with(".asc"));
+        match (candidates.next(), candidates.next()) {
+            (Some(attachment), None) => Ok(attachment),
+            (None, _) => Err(Rejection::Missing),
+            (Some(_), Some(_)) => Err(Rejection::Malformed(
+                "several attachments could be the contribution".to_string(),
+            )),
+        }
+    }
+
+    /// The detached signature attached next to the attachment with this name.
+    pub fn detached_signature(&self, filename: &str) -> Option<&Attachment> {
+        let name = format!("{}.asc", filename);
+        self.attachments.iter().find(|a| a.filename == name)
+    }
+}
+
+/// What became of one message of the archive.
+pub enum MailOutcome {
+    /// The message is the participant's contribution.
+    Accepted(usize),
+    /// The message is from the participant, but cannot be their contribution.
+    Rejected(usize, Rejection),
+    /// The participant's contribution was taken from another message.
+    Ignored(usize),
+    /// The message could not be attributed to any participant, for this reason.
+    Unattributed(String),
+}
+
+pub struct IngestedMessage {
+    pub from: String,
+    pub date: Option<u64>,
+    pub outcome: MailOutcome,
+}
+
+pub struct IngestReport {
+    /// One entry per message of the archive, in order.
+    pub messages: Vec<IngestedMessage>,
+    /// The transcript that was written.
+    pub transcript: Transcript,
+}
+
+/// How an archive is ingested.
+pub struct IngestOptions {
+    pub checks: SubgroupChecks,
+    pub checker: ConsistencyChecker,
+    pub manifest: Manifest,
+    /// Certificates of the participants who sign with OpenPGP.
+    pub keyring: Keyring,
+}
+
+/// Name of the transcript that `ingest_mbox` writes.
+pub const TRANSCRIPT_FILE: &str = "transcript";
+
+/// Picks every participant's contribution out of the archive `mbox` and
+/// writes them to `dir`, as `i.params` (and `i.params.asc`) for participant
+/// `i`, together with a transcript starting from the parameters at
+/// `initial`. The transcript lists the participants up to the last one with
+/// a contribution, with the time each was sent.
+pub fn ingest_mbox(
+    mbox: &[u8],
+    initial: &Path,
+    dir: &Path,
+    options: &IngestOptions,
+) -> Result<IngestReport> {
+    let manifest = &options.manifest;
+    manifest.validate()?;
+    let (header, initial_params) =
+        read_params_with_checks(&mut File::open(initial)?, options.checks)?;
+    std::fs::create_dir_all(dir)?;
+
+    // each participant's candidates, with the position of their message
+    let mut candidates: Vec<Vec<(usize, Candidate)>> =
+        manifest.participants.iter().map(|_| vec![]).collect();
+    let mut messages = vec![];
+    for raw in split_mbox(mbox) {
+        let message = match MailMessage::parse(&raw) {
+            Ok(message) => message,
+            Err(e) => {
+                messages.push(IngestedMessage {
+                    from: String::new(),
+                    date: None,
+                    outcome: MailOutcome::Unattributed(e.to_string()),
+                });
+                continue;
+            }
+        };
+        let outcome = match attribute(&message, manifest) {
+            Err(reason) => MailOutcome::Unattributed(reason),
+            Ok(j) => match check_message(&message, j, &header, dir, options) {
+                Ok(candidate) => {
+                    candidates[j].push((messages.len(), candidate));
+                    // settled once the candidates are compared
+                    MailOutcome::Ignored(j + 1)
+                }
+                Err(reason) => MailOutcome::Rejected(j + 1, reason),
+            },
+        };
+        messages.push(IngestedMessage {
+            from: message.from,
+            date: message.date,
+            outcome,
+        });
+    }
+
+    // digests of the contributions chosen so far, and the participant each is from
+    let mut digests: Vec<Digest> = vec![params_digest(&initial_params)];
+    let mut digest_index: Vec<usize> = vec![0];
+    let mut latest = initial_params;
+    let mut accepted: Vec<Option<u64>> = vec![None; manifest.participants.len()];
+    for (j, participant) in manifest.participants.iter().enumerate() {
+        for (k, candidate) in candidates[j].drain(..) {
+            if accepted[j].is_some() {
+                continue;
+            }
+            let result = check_contribution_with_checker(
+                &options.checker,
+                &candidate.params,
+                latest.g2_alpha_1_to_n[0],
+                &candidate.proof,
+                participant.id.as_bytes(),
+                &digests,
+            );
+            messages[k].outcome = match result {
+                Ok(()) => {
+                    candidate.write(dir, j).map_err(Error::from)?;
+                    digests.push(params_digest(&candidate.params));
+                    digest_index.push(j + 1);
+                    accepted[j] = Some(candidate.time);
+                    latest = candidate.params;
+                    MailOutcome::Accepted(j + 1)
+                }
+                Err(Rejection::Replayed(i)) => {
+                    MailOutcome::Rejected(j + 1, Rejection::Replayed(digest_index[i]))
+                }
+                Err(reason) => MailOutcome::Rejected(j + 1, reason),
+            };
+        }
+        if accepted[j].is_none() {
+            remove_files(dir, j);
+        }
+    }
+
+    let last = accepted
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |j| j + 1);
+    let entries = manifest.participants[..last]
+        .iter()
+        .zip(accepted.iter())
+        .enumerate()
+        .map(|(j, (p, time))| TranscriptEntry {
+            id: p.id.clone(),
+            path: time.map(|_| PathBuf::from(params_file(j + 1))),
+            time: *time,
+        })
+        .collect();
+    let transcript = Transcript {
+        initial: initial.to_path_buf(),
+        entries,
+    };
+    std::fs::write(dir.join(TRANSCRIPT_FILE), transcript.to_string())?;
+    Ok(IngestReport {
+        messages,
+        transcript,
+    })
+}
+
+// The position in the manifest of the participant who sent the message
+fn attribute(message: &MailMessage, manifest: &Manifest) -> std::result::Result<usize, String> {
+    let participants = &manifest.participants;
+    if message.receipt.is_none() && message.attachments.is_empty() {
+        return Err("it carries neither a receipt nor an attachment".to_string());
+    }
+    match (&message.receipt, message.date) {
+        (Some(receipt), _) => participants
+            .iter()
+            .position(|p| p.id == receipt.id)
+            .ok_or_else(|| {
+                format!(
+                    "the receipt is from {:?}, who is not registered",
+                    receipt.id
+                )
+            }),
+        (None, Some(date)) => participants
+            .iter()
+            .position(|p| p.slot.contains(date))
+            .ok_or_else(|| "it has no receipt and was sent outside every slot".to_string()),
+        (None, None) => Err("it has neither a receipt nor a valid Date header".to_string()),
+    }
+}
+
+// A message that passed every check short of the contribution itself
+struct Candidate {
+    time: u64,
+    params: PointproofsParams,
+    proof: ContributionProof,
+    contribution: Vec<u8>,
+    signature: Option<Vec<u8>>,
+}
+
+impl Candidate {
+    // Writes the files of participant j's contribution
+    fn write(&self, dir: &Path, j: usize) -> std::io::Result<()> {
+        write_files(dir, j, &self.contribution, self.signature.as_deref())
+    }
+}
+
+// Checks the message from participant j, short of the contribution itself
+fn check_message(
+    message: &MailMessage,
+    j: usize,
+    header: &FileHeader,
+    dir: &Path,
+    options: &IngestOptions,
+) -> std::result::Result<Candidate, Rejection> {
+    let participant = &options.manifest.participants[j];
+    let time = match message.date {
+        Some(time) if participant.slot.contains(time) => time,
+        _ => return Err(Rejection::OutsideSlot),
+    };
+    if let Some(receipt) = &message.receipt {
+        if receipt.index != j + 1 {
+            return Err(Rejection::ReceiptMismatch(format!(
+                "the receipt gives index {} for participant {}",
+                receipt.index,
+                j + 1
+            )));
+        }
+    }
+    let contribution = message.contribution()?;
+    let signature = message
+        .detached_signature(&contribution.filename)
+        .map(|a| a.data.clone());
+    // the detached signature is checked against the files as verify_transcript reads them
+    let result = write_files(dir, j, &contribution.data, signature.as_deref())
+        .map_err(|e| Rejection::Malformed(e.to_string()))
+        .and_then(|()| check_files(message, header, dir, j, options));
+    remove_files(dir, j);
+    let (params, proof) = result?;
+    Ok(Candidate {
+        time,
+        params,
+        proof,
+        contribution: contribution.data.clone(),
+        signature,
+    })
+}
+
+fn check_files(
+    message: &MailMessage,
+    header: &FileHeader,
+    dir: &Path,
+    j: usize,
+    options: &IngestOptions,
+) -> std::result::Result<(PointproofsParams, ContributionProof), Rejection> {
+    let participant = &options.manifest.participants[j];
+    let path = dir.join(params_file(j + 1));
+    let (h, params, proof, signature) = File::open(&path)
+        .map_err(Error::from)
+        .and_then(|mut f| read_signed_contribution_with_checks(&mut f, options.checks))
+        .map_err(|e| Rejection::Malformed(e.to_string()))?;
+    if &h != header {
+        return Err(Rejection::SettingsMismatch);
+    }
+    check_signature(participant.id.as_bytes(), signature.as_ref())?;
+    check_detached_signature(
+        &path,
+        participant.id.as_bytes(),
+        Some(participant.public_key.as_str()),
+        &options.keyring,
+    )?;
+    match &message.receipt {
+        Some(receipt) if receipt.output_digest != params_digest(&params) => Err(
+            Rejection::ReceiptMismatch("the receipt does not describe the attachment".to_string()),
+        ),
+        _ => Ok((params, proof)),
+    }
+}
+
+// Writes participant j's contribution, and its detached signature if any
+fn write_files(
+    dir: &Path,
+    j: usize,
+    contribution: &[u8],
+    signature: Option<&[u8]>,
+) -> std::io::Result<()> {
+    std::fs::write(dir.join(params_file(j + 1)), contribution)?;
+    let signature_path = dir.join(signature_file(j + 1));
+    match signature {
+        Some(signature) => std::fs::write(signature_path, signature),
+        // a signature left over from an earlier run must not vouch for this file
+        None => match std::fs::remove_file(signature_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn remove_files(dir: &Path, j: usize) {
+    let _ = std::fs::remove_file(dir.join(params_file(j + 1)));
+    let _ = std::fs::remove_file(dir.join(signature_file(j + 1)));
+}
+
+fn params_file(index: usize) -> String {
+    format!("{}.params", index)
+}
+
+fn signature_file(index: usize) -> String {
+    format!("{}.asc", params_file(index))
+}
+
+fn bad(e: mailparse::MailParseError) -> Error {
+    Error::Format(format!("Bad mail message: {}", e))
+}
@@ -2,140 +2,1657 @@ extern crate atoi;
 extern crate pairing_plus as pairing_plus;
 extern crate pointproofs_paramgen;
 extern crate rand;
+extern crate serde_json;
+
+mod worker;
 
 use atoi::atoi;
-use pairing_plus::serdes::SerDes;
+use pairing_plus::{CurveAffine, EncodedPoint};
+use pointproofs_paramgen::armor::{armor_digest, armor_proof};
+use pointproofs_paramgen::audit::{audit_alpha, AuditOptions};
+use pointproofs_paramgen::checkpoint::{CheckpointKey, EvolveCheckpoint, VerifyCheckpoint};
+use pointproofs_paramgen::consistency::{CheckProgress, ConsistencyChecker, SecurityLevel};
+use pointproofs_paramgen::digest::{digest_bytes, params_digest, to_hex, Digest};
+use pointproofs_paramgen::error::{Error, Result, MAX_N};
+use pointproofs_paramgen::fingerprint::{randomart, word_string};
+use pointproofs_paramgen::format::{
+    read_contribution_with_checks, read_params_with_checks, read_signed_contribution_with_checks,
+    write_contribution, write_params, write_signed_contribution, FileHeader, ProofMode,
+};
+use pointproofs_paramgen::mailbox::{ingest_mbox, IngestOptions, MailOutcome, TRANSCRIPT_FILE};
+use pointproofs_paramgen::manifest::Manifest;
+use pointproofs_paramgen::openpgp::{
+    check_detached_signature, fingerprint_hex, named_fingerprint, parse_certificate, Keyring,
+};
+use pointproofs_paramgen::progress::{Hooks, Phase, Progress};
+use pointproofs_paramgen::receipt::Receipt;
+use pointproofs_paramgen::signature::{check_signature, embedded_key, SigningKey};
+use pointproofs_paramgen::spot_check::{checked_powers, SPOT_CHECKS};
+use pointproofs_paramgen::subgroup::SubgroupChecks;
+use pointproofs_paramgen::transcript::{
+    verify_transcript_with_options, Transcript, TranscriptOptions,
+};
 use pointproofs_paramgen::*;
 use rand::rngs::OsRng;
 use rand::RngCore;
+use serde_json::{json, Map, Value};
 use std::fs::{File, OpenOptions};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use worker::Message;
 use zeroize::Zeroize;
 
+// Exit codes, as documented in the usage text
+const EXIT_USAGE: i32 = 1;
+const EXIT_IO: i32 = 2;
+const EXIT_MALFORMED: i32 = 3;
+const EXIT_INCONSISTENT: i32 = 4;
+const EXIT_REJECTED: i32 = 5;
+const EXIT_WEAK_ALPHA: i32 = 6;
+const EXIT_CANCELLED: i32 = 7;
+const EXIT_FAULT: i32 = 8;
+
 fn usage(progname: &str) {
     eprintln!("Usage:
-	{0} init /tmp/params.out parameter_n
-		Generates starting parameters using a fixed value of alpha
-	{0} evolve id_string /tmp/params.in /tmp/params.out
-		Reads old params from /tmp/params.in, rerandomizes them and writes them (with a proof of knowledge of the mixed-in exponent) to /tmp/params.out, using id_string as your identity
+	{0} init /tmp/params.out parameter_n [--proof-mode schnorr|pairing] [--omit-gt] [--uncompressed]
+		Generates starting parameters using a fixed value of alpha. The proof mode (default schnorr) is recorded in the file and used by every later contribution. With --omit-gt, the ceremony's files leave out e(g1,g2)^{{alpha^{{N+1}}}}, which is recomputed on load. With --uncompressed, the ceremony's files store uncompressed points, which are twice as large but load faster
+	{0} keygen name /tmp/signing.key
+		Generates an Ed25519 key pair, writes the secret key to /tmp/signing.key and prints the id_string to register, which is name followed by the public key.
+	{0} evolve id_string /tmp/params.in /tmp/params.out [--key /tmp/signing.key] [--no-isolation]
+		Reads old params from /tmp/params.in, rerandomizes them and writes them (with a proof of knowledge of the mixed-in exponent) to /tmp/params.out, using id_string as your identity. If id_string embeds a public key (see keygen), --key must give its secret key, and the contribution is signed with it. On Linux, everything after opening the two files runs in a worker process that cannot dump core and can only read /tmp/params.in and write /tmp/params.out; --no-isolation runs it in-process instead, and --checkpoint requires it
+	{0} evolve ... --receipt /tmp/receipt --ceremony ceremony_id --index i
+		Also writes a receipt for the contribution to /tmp/receipt: a few lines of text with the ceremony id, your index i in the ceremony, id_string, the digests of the old and new params, the new g2^alpha, the commitment to the mixed-in exponent and the proof. Receipts are small enough to post anywhere.
+		With --armor, the receipt is written in armored form (see armor).
+	{0} verify-receipt /tmp/receipt /tmp/params.old /tmp/params.new
+		Checks that the receipt describes these two files, then verifies the contribution like verify does, with the id_string from the receipt. The receipt may be armored.
+	{0} armor receipt|proof|digest /tmp/file
+		Prints a receipt, the proof of the contribution in /tmp/file, or the SHA-512 digest of /tmp/file (as printed by digest) as base64 between BEGIN and END lines with a checksum, which survives being sent by email. Wherever a contribution is read, its proof may be replaced by the armored proof.
 	{0} verify id_string /tmp/params.old /tmp/params.new
-		Given assumed-good old params and a newly rerandomized version (with a proof of knowledge of the mixed-in exponent), verify that the new parameters were rerandomized correctly (i.e., check that the parameters are self-consistent and that the proof is correct for the given prover identity).
-	{0} finalize beacon_value /tmp/params.in /tmp/params.final
-		Given assumed-good params in /tmp/params.in and the value of the shared random beacon, output the final set of parameters.
+		Given assumed-good old params and a newly rerandomized version (with a proof of knowledge of the mixed-in exponent), verify that the new parameters were rerandomized correctly (i.e., check that the parameters are self-consistent and that the proof is correct for the given prover identity). If id_string embeds a public key, the new params must also carry a valid signature under it. If id_string ends in openpgp:fingerprint, the detached signature /tmp/params.new.asc must verify against that certificate, which is looked up in the file given with --keyring.
+	{0} digest /tmp/params
+		Fully checks the given params and prints the SHA-512 digest of the file, which can be pinned to open the file later without re-checking it.
+	{0} fingerprint /tmp/file
+		Prints the SHA-512 digest of a parameter, receipt or transcript file along with its fingerprint: a line of words to read out on a call and a randomart picture to compare at a glance. Receipts are digested in plain form, so an armored receipt has the same fingerprint. Every digest the tool prints comes with the same fingerprint.
+	{0} audit /tmp/params
+		Checks that the alpha of the given params is not trivially known: not a small number, not a small multiple of the initial alpha, and not the hash of a well-known string.
+	{0} verify-transcript /tmp/transcript
+		Runs the latest-good-message selection over every message listed in /tmp/transcript (see usage.md for the format), reporting why each rejected message was rejected. With --manifest /tmp/manifest.json, the transcript must also follow the ceremony manifest: its participants in order, the initial params it fixes (which are also checked for consistency), and every message inside its sender's timeslot. --organizers works as for manifest check. --keyring works as for verify, and certificates in the manifest are used as well.
+	{0} ingest-mbox --manifest /tmp/manifest.json /tmp/archive.mbox /tmp/params.init /tmp/ceremony
+		Picks each participant's contribution out of the mailing list archive /tmp/archive.mbox: of the messages attributed to them (by their armored receipt, or else by their Date) that were sent within their slot and whose attachment carries valid signatures and agrees with its receipt, the first that verifies against the contribution picked before theirs, as in verify-transcript. Writes the contributions and a transcript starting from /tmp/params.init to /tmp/ceremony, ready for verify-transcript. --organizers and --keyring work as for verify-transcript.
+	{0} manifest check /tmp/manifest.json [--organizers /tmp/organizers.asc]
+		Validates a ceremony manifest (see usage.md for the format) and prints the SHA-512 digest of the file, which participants compare. With --organizers, the detached OpenPGP signature /tmp/manifest.json.asc must also verify against the organizers' certificate in /tmp/organizers.asc.
+	{0} finalize beacon_value /tmp/params.in /tmp/params.final [--omit-gt] [--uncompressed]
+		Given assumed-good params in /tmp/params.in and the value of the shared random beacon, output the final set of parameters. The output uses the original file layout unless --omit-gt or --uncompressed is given.
+	{0} convert /tmp/params.in /tmp/params.out [--omit-gt] [--uncompressed]
+		Rewrites the params in /tmp/params.in (dropping any proof) in the original compressed layout, or in the layout selected by the flags.
+	Any command that checks consistency accepts --security 64|128|full (default full) and --rounds k (default 1). The consistency check then accepts inconsistent params with probability at most 2^-(security * k), where full counts as 253; lower levels use shorter random challenges, and the check takes time roughly proportional to their length.
+	Any command that checks consistency also accepts --seed seed_string, which derives the random challenges from seed_string and the digest of the params instead of drawing them at random, so that anyone can replay the check. verify and digest then print a (seed, digest, result) record.
+	Any command that checks consistency also accepts --paranoid, which instead checks every relation between the params with its own pairing equation. This is much slower but does not rely on the probabilistic argument.
+	Any command that loads params accepts --batch-checks, which checks subgroup membership with the endomorphisms of BLS12-381 instead of multiplying by the group order, on all available threads. It is several times faster and rejects exactly the same files.
+	evolve and verify accept --checkpoint /tmp/checkpoint, which saves their progress there as they go and resumes from it if it already exists; it is deleted once the command finishes. An evolve checkpoint holds the secret of the contribution, encrypted under a passphrase read from the POINTPROOFS_CHECKPOINT_PASSPHRASE environment variable or from stdin. Checkpoints are off by default.
+	Every command accepts --json, which replaces the usual output with a single JSON object on stdout describing the inputs, outputs, the result of each check, and timings. Otherwise, long-running steps draw a progress bar with their throughput on stderr.
+	A command refuses flags it does not use, and options given without a value.
+Exit codes:
+	0 success; 1 bad arguments; 2 a file could not be read or written; 3 an input file is malformed, has n out of range or has points outside the subgroup; 4 params are not consistent; 5 verify rejected the contribution; 6 audit found that alpha is trivially known; 7 the operation was cancelled; 8 evolve found its own output faulty and did not write it.
 ", progname);
 }
 
-fn main() {
-    // let n = 1024;
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 4 {
-        usage(&args[0]);
-        return;
+// Removes `--name value` from args and returns the value, if the option is present
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let pos = match args.iter().position(|a| a == name) {
+        Some(pos) => pos,
+        None => return Ok(None),
+    };
+    args.remove(pos);
+    if pos < args.len() && !args[pos].starts_with("--") {
+        Ok(Some(args.remove(pos)))
+    } else {
+        Err(Error::InvalidInput(format!("{} needs a value", name)))
     }
-    match args[1].as_str() {
-        "init" => {
-            // parse the parameter n, a usize
-            let n = match atoi::<usize>(args[3].as_bytes()) {
-                Some(p) => p,
-                None => {
-                    usage(&args[0]);
+}
+
+// Removes `--name` from args and returns whether it was present
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+fn exit_code(e: &Error) -> i32 {
+    match e {
+        Error::InvalidInput(_) => EXIT_USAGE,
+        Error::Io(_) => EXIT_IO,
+        Error::Format(_) | Error::SizeLimit(_) | Error::NotInSubgroup(_) => EXIT_MALFORMED,
+        Error::Inconsistent => EXIT_INCONSISTENT,
+        Error::Rejected(_) => EXIT_REJECTED,
+        Error::WeakAlpha(_) => EXIT_WEAK_ALPHA,
+        Error::Cancelled => EXIT_CANCELLED,
+        Error::Fault(_) => EXIT_FAULT,
+    }
+}
+
+fn wrong_arguments() -> Error {
+    Error::InvalidInput("Wrong number of arguments".to_string())
+}
+
+// Settings given by flags. Each command takes only the groups of flags it
+// uses (see take_options), and the others keep their defaults.
+struct Options {
+    // init
+    proof_mode: ProofMode,
+    // every command that loads params
+    checks: SubgroupChecks,
+    layout: LayoutOptions,
+    check: CheckOptions,
+    // evolve and verify
+    checkpoint: Option<String>,
+    // the commands that check signatures
+    keyring: Option<String>,
+    ceremony: CeremonyOptions,
+    evolve: EvolveOptions,
+}
+
+// Layout of the params a command writes
+#[derive(Default)]
+struct LayoutOptions {
+    omit_gt: bool,
+    uncompressed: bool,
+}
+
+// How consistency is checked
+#[derive(Default)]
+struct CheckOptions {
+    checker: ConsistencyChecker,
+    seed: Option<String>,
+}
+
+// The ceremony a transcript or mailbox belongs to
+#[derive(Default)]
+struct CeremonyOptions {
+    manifest: Option<String>,
+    organizers: Option<String>,
+}
+
+#[derive(Default)]
+struct EvolveOptions {
+    no_isolation: bool,
+    key: Option<String>,
+    receipt: Option<String>,
+    ceremony: Option<String>,
+    index: Option<usize>,
+    armor: bool,
+}
+
+// Collects what a command did, and prints either progress lines or, with --json,
+// a single JSON object at the end
+struct Report {
+    json: bool,
+    fields: Map<String, Value>,
+    // milliseconds spent in each phase
+    timings: Map<String, Value>,
+    // in the evolve worker, the pipe everything is sent to instead
+    parent: Option<Arc<File>>,
+}
+
+impl Report {
+    fn new(json: bool, command: &str) -> Self {
+        let mut fields = Map::new();
+        fields.insert("command".to_string(), json!(command));
+        Report {
+            json,
+            fields,
+            timings: Map::new(),
+            parent: None,
+        }
+    }
+
+    // A report that sends everything to the parent of the evolve worker
+    fn forwarding(parent: Arc<File>) -> Self {
+        Report {
+            json: false,
+            fields: Map::new(),
+            timings: Map::new(),
+            parent: Some(parent),
+        }
+    }
+
+    // Prints a progress line, unless the output is JSON
+    fn say(&self, msg: &str) {
+        if let Some(parent) = &self.parent {
+            Message::Say(msg.to_string()).send(parent);
+        } else if !self.json {
+            println!("{}", msg);
+        }
+    }
+
+    fn set<V: Into<Value>>(&mut self, key: &str, value: V) {
+        let value = value.into();
+        if let Some(parent) = &self.parent {
+            Message::Set(key.to_string(), value.clone()).send(parent);
+        }
+        self.fields.insert(key.to_string(), value);
+    }
+
+    fn set_time(&mut self, phase: &str, ms: u64) {
+        if let Some(parent) = &self.parent {
+            Message::Time(phase.to_string(), ms).send(parent);
+        }
+        self.timings.insert(phase.to_string(), json!(ms));
+    }
+
+    // Runs f, recording how long it took as the given phase
+    fn time<T, F: FnOnce() -> T>(&mut self, phase: &str, f: F) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.set_time(phase, start.elapsed().as_millis() as u64);
+        res
+    }
+
+    // A progress bar for the command, drawn unless the output is JSON
+    fn progress_bar(&self) -> ProgressBar {
+        ProgressBar {
+            enabled: !self.json,
+            state: Mutex::new(None),
+            parent: self.parent.clone(),
+        }
+    }
+
+    // Prints the JSON object, if the output is JSON
+    fn finish(self, result: &Result<()>) {
+        if self.json {
+            println!("{}", self.into_json(result));
+        }
+    }
+
+    // The JSON object describing the command and its result
+    fn into_json(mut self, result: &Result<()>) -> Value {
+        self.set("ok", result.is_ok());
+        if let Err(e) = result {
+            self.set("error", e.to_string());
+            self.set("exit_code", exit_code(e));
+        }
+        let timings = std::mem::replace(&mut self.timings, Map::new());
+        self.set("timings_ms", Value::Object(timings));
+        Value::Object(self.fields)
+    }
+}
+
+// How often the progress bar is redrawn
+const PROGRESS_REDRAW: Duration = Duration::from_millis(100);
+const PROGRESS_WIDTH: usize = 40;
+
+// Draws the progress of long-running steps on stderr, unless the output is JSON
+struct ProgressBar {
+    enabled: bool,
+    // the phase being drawn, when it started, and when the bar was last drawn
+    state: Mutex<Option<(Phase, Instant, Instant)>>,
+    // in the evolve worker, the pipe updates are sent to instead
+    parent: Option<Arc<File>>,
+}
+
+impl ProgressBar {
+    fn hooks(&self) -> Hooks<'_> {
+        Hooks {
+            progress: Some(self),
+            cancel: None,
+        }
+    }
+}
+
+impl Progress for ProgressBar {
+    fn update(&self, phase: Phase, done: usize, total: usize) {
+        if let Some(parent) = &self.parent {
+            Message::Progress(phase, done, total).send(parent);
+            return;
+        }
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let start = match *state {
+            Some((p, start, last_draw)) if p == phase => {
+                if done < total && now.duration_since(last_draw) < PROGRESS_REDRAW {
                     return;
                 }
-            };
-
-            let mut f = OpenOptions::new().write(true).create_new(true).open(&args[2]).unwrap();
-            println!("Generating...");
-            // the initial vector is set to the first 100 digits of pi:
-            // 3 .
-            // 1 4 1 5 9 2 6 5 3 5 8 9 7 9 3 2 3 8 4 6
-            // 2 6 4 3 3 8 3 2 7 9 5 0 2 8 8 4 1 9 7 1
-            // 6 9 3 9 9 3 7 5 1 0 5 8 2 0 9 7 4 9 4 4
-            // 5 9 2 3 0 7 8 1 6 4 0 6 2 8 6 2 0 8 9 9
-            // 8 6 2 8 0 3 4 8 2 5 3 4 2 1 1 7 0 6 7 9
-            let pi_100 = "31415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
-            let alpha = hash_to_field_pointproofs::hash_to_field_pointproofs(pi_100);
-
-            let params = generate(alpha, n);
-            println!("Generated.");
-            params.serialize(&mut f, true).unwrap();
-        }
-        "evolve" => {
-            if args.len() < 5 {
-                usage(&args[0]);
-                return;
+                start
+            }
+            _ => now,
+        };
+        let filled = PROGRESS_WIDTH * done / total.max(1);
+        let secs = now.duration_since(start).as_secs_f64();
+        let rate = if secs > 0.0 { done as f64 / secs } else { 0.0 };
+        eprint!(
+            "\r{} [{}{}] {}/{} ({:.1}/s)",
+            phase,
+            "#".repeat(filled),
+            " ".repeat(PROGRESS_WIDTH - filled),
+            done,
+            total,
+            rate
+        );
+        if done >= total {
+            eprintln!();
+            *state = None;
+        } else {
+            *state = Some((phase, start, now));
+        }
+    }
+}
+
+impl Drop for ProgressBar {
+    // ends the line of a bar that stopped early, e.g. on a failed check
+    fn drop(&mut self) {
+        if self.state.lock().unwrap().is_some() {
+            eprintln!();
+        }
+    }
+}
+
+// Hex of the compressed encoding of a point
+fn point_hex<G: CurveAffine>(p: &G) -> String {
+    to_hex(G::Compressed::from_affine(*p).as_ref())
+}
+
+// What the JSON output says about a set of parameters
+fn describe_params(params: &PointproofsParams) -> Value {
+    json!({
+        "n": params.n,
+        "digest": to_hex(&params_digest(params)),
+        "words": word_string(&params_digest(params)),
+        "randomart": randomart(&params_digest(params)),
+        "g2_alpha": point_hex(&params.g2_alpha_1_to_n[0]),
+    })
+}
+
+fn describe_header(header: &FileHeader) -> Value {
+    json!({
+        "proof_mode": match header.proof_mode {
+            ProofMode::Schnorr => "schnorr",
+            ProofMode::Pairing => "pairing",
+        },
+        "include_gt": header.include_gt,
+        "compressed": header.compressed,
+    })
+}
+
+// The points that commit to the mixed-in exponent delta
+fn describe_proof(proof: &ContributionProof) -> Value {
+    match proof {
+        ContributionProof::Schnorr(pok) => json!({ "g1_delta": point_hex(&pok.g1x()) }),
+        ContributionProof::Pairing(proof) => json!({ "g2_delta": point_hex(&proof.g2_delta()) }),
+    }
+}
+
+// The checks of `check_contribution_with_checker`, in the order it runs them
+const CONTRIBUTION_CHECKS: [&str; 4] = ["degenerate_exponent", "replay", "proof", "consistency"];
+
+// The outcome of each individual check, given the overall result
+fn describe_checks(result: &std::result::Result<(), Rejection>) -> Value {
+    let failed = match result {
+        Ok(()) => CONTRIBUTION_CHECKS.len(),
+        Err(Rejection::DegenerateExponent(_)) => 0,
+        Err(Rejection::Replayed(_)) => 1,
+        Err(Rejection::BadProof) => 2,
+        Err(_) => 3,
+    };
+    let mut checks = Map::new();
+    for (i, name) in CONTRIBUTION_CHECKS.iter().enumerate() {
+        let outcome = if i < failed {
+            "pass"
+        } else if i == failed {
+            "fail"
+        } else {
+            "not run"
+        };
+        checks.insert(name.to_string(), json!(outcome));
+    }
+    Value::Object(checks)
+}
+
+// Removes all flags from args and parses them
+fn take_options(args: &mut Vec<String>) -> Result<Options> {
+    // read by main before anything else
+    take_flag(args, "--json");
+    let command = args.get(1).cloned().unwrap_or_default();
+    let command = command.as_str();
+    let loads_params = command != "keygen" && command != "manifest";
+    let mut options = Options {
+        proof_mode: ProofMode::Schnorr,
+        checks: SubgroupChecks::PerPoint,
+        layout: LayoutOptions::default(),
+        check: CheckOptions::default(),
+        checkpoint: None,
+        keyring: None,
+        ceremony: CeremonyOptions::default(),
+        evolve: EvolveOptions::default(),
+    };
+    if command == "init" {
+        if let Some(m) = take_option(args, "--proof-mode")? {
+            options.proof_mode = m.parse::<ProofMode>()?;
+        }
+    }
+    if loads_params && take_flag(args, "--batch-checks") {
+        options.checks = SubgroupChecks::Batched;
+    }
+    if let "init" | "convert" | "finalize" = command {
+        options.layout = take_layout_options(args);
+    }
+    if let "evolve" | "verify" | "verify-receipt" | "digest" | "verify-transcript" | "ingest-mbox" =
+        command
+    {
+        options.check = take_check_options(args)?;
+    }
+    if let "evolve" | "verify" = command {
+        options.checkpoint = take_option(args, "--checkpoint")?;
+    }
+    if let "verify" | "verify-receipt" | "verify-transcript" | "ingest-mbox" = command {
+        options.keyring = take_option(args, "--keyring")?;
+    }
+    if let "verify-transcript" | "ingest-mbox" = command {
+        options.ceremony.manifest = take_option(args, "--manifest")?;
+    }
+    if let "manifest" | "verify-transcript" | "ingest-mbox" = command {
+        options.ceremony.organizers = take_option(args, "--organizers")?;
+    }
+    if command == "evolve" {
+        options.evolve = take_evolve_options(args)?;
+    }
+    if let Some(flag) = args.iter().skip(2).find(|a| a.starts_with("--")) {
+        return Err(Error::InvalidInput(format!(
+            "{} does not take {}",
+            command, flag
+        )));
+    }
+    Ok(options)
+}
+
+fn take_layout_options(args: &mut Vec<String>) -> LayoutOptions {
+    LayoutOptions {
+        omit_gt: take_flag(args, "--omit-gt"),
+        uncompressed: take_flag(args, "--uncompressed"),
+    }
+}
+
+fn take_check_options(args: &mut Vec<String>) -> Result<CheckOptions> {
+    let level = match take_option(args, "--security")? {
+        None => SecurityLevel::Full,
+        Some(l) => l.parse::<SecurityLevel>()?,
+    };
+    let rounds = match take_option(args, "--rounds")? {
+        None => 1,
+        Some(k) => match atoi::<usize>(k.as_bytes()) {
+            Some(k) if k > 0 => k,
+            _ => {
+                return Err(Error::InvalidInput(
+                    "--rounds must be a positive number".to_string(),
+                ))
             }
-            let id = args[2].as_bytes();
-            println!("Loading params...");
-            let mut f = File::open(&args[3]).unwrap();
-            let params_in = PointproofsParams::deserialize(&mut f, true).unwrap();
-            println!("Loaded.");
-            println!("Checking...");
-            if !consistent(&params_in) {
-                panic!("Input params are not consistent");
-            } else {
-                println!("Input params OK");
+        },
+    };
+    let seed = take_option(args, "--seed")?;
+    let mut checker = ConsistencyChecker::new()
+        .with_security_level(level)
+        .with_rounds(rounds);
+    if take_flag(args, "--paranoid") {
+        checker = checker.paranoid();
+    }
+    if let Some(seed) = &seed {
+        checker = checker.with_seed(seed.as_bytes());
+    }
+    Ok(CheckOptions { checker, seed })
+}
+
+fn take_evolve_options(args: &mut Vec<String>) -> Result<EvolveOptions> {
+    let no_isolation = take_flag(args, "--no-isolation");
+    let key = take_option(args, "--key")?;
+    let receipt = take_option(args, "--receipt")?;
+    let ceremony = take_option(args, "--ceremony")?;
+    let index = match take_option(args, "--index")? {
+        None => None,
+        Some(i) => match atoi::<usize>(i.as_bytes()) {
+            Some(i) if i > 0 => Some(i),
+            _ => {
+                return Err(Error::InvalidInput(
+                    "--index must be a positive number".to_string(),
+                ))
             }
+        },
+    };
+    let armor = take_flag(args, "--armor");
+    if receipt.is_some() && (ceremony.is_none() || index.is_none()) {
+        return Err(Error::InvalidInput(
+            "--receipt needs --ceremony and --index".to_string(),
+        ));
+    }
+    Ok(EvolveOptions {
+        no_isolation,
+        key,
+        receipt,
+        ceremony,
+        index,
+        armor,
+    })
+}
+
+// How many powers evolve computes between two checkpoints
+const CHECKPOINT_INTERVAL: usize = 256;
+
+// Environment variable that can hold the passphrase of evolve checkpoints
+const PASSPHRASE_VAR: &str = "POINTPROOFS_CHECKPOINT_PASSPHRASE";
+
+// Reads the passphrase of an evolve checkpoint from the environment, or else from stdin
+fn checkpoint_passphrase() -> Result<Vec<u8>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase.into_bytes());
+    }
+    eprint!("Checkpoint passphrase: ");
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let passphrase = line.trim_end_matches(&['\r', '\n'][..]).as_bytes().to_vec();
+    line.zeroize();
+    Ok(passphrase)
+}
+
+// Replaces the checkpoint at path through a temporary file, so that an
+// interruption leaves either the old checkpoint or the new one
+fn save_checkpoint<F: FnOnce(&mut File) -> Result<()>>(path: &str, write: F) -> Result<()> {
+    let tmp = format!("{}.tmp", path);
+    let mut open = OpenOptions::new();
+    open.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open.mode(0o600);
+    }
+    let mut f = open.open(&tmp)?;
+    write(&mut f)?;
+    f.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Starts an evolve checkpoint at path, or resumes from the one already there
+fn open_evolve_checkpoint(
+    path: &str,
+    params_in: &PointproofsParams,
+    entropy: &[u8],
+    id: &[u8],
+    mode: ProofMode,
+    report: &mut Report,
+) -> Result<(EvolveCheckpoint, CheckpointKey)> {
+    eprintln!(
+        "WARNING: {} will hold the secret of your contribution, encrypted under your passphrase. \
+         Anyone who obtains both can undo your contribution. \
+         It is deleted when evolve finishes; if you give up on this run, delete it yourself.",
+        path
+    );
+    let mut passphrase = checkpoint_passphrase()?;
+    let resuming = std::path::Path::new(path).exists();
+    let opened = if resuming {
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(|mut f| EvolveCheckpoint::read(&mut f, &passphrase))
+    } else {
+        CheckpointKey::new(&passphrase)
+            .map(|key| (EvolveCheckpoint::new(params_in, entropy, id, mode), key))
+    };
+    passphrase.zeroize();
+    let (checkpoint, key) = opened?;
+    if !checkpoint.matches(params_in) || checkpoint.id != id || checkpoint.mode != mode {
+        return Err(Error::InvalidInput(format!(
+            "The checkpoint {} is for a different contribution",
+            path
+        )));
+    }
+    if resuming {
+        report.say(&format!(
+            "Resuming from checkpoint: {} of {} powers done",
+            checkpoint.partial.done(),
+            2 * params_in.n - 1
+        ));
+        report.set("resumed_from", checkpoint.partial.done());
+    }
+    Ok((checkpoint, key))
+}
+
+// Finishes the rerandomization, saving the checkpoint every CHECKPOINT_INTERVAL powers
+fn rerandomize_with_checkpoint(
+    params_in: &PointproofsParams,
+    mut checkpoint: EvolveCheckpoint,
+    key: &CheckpointKey,
+    path: &str,
+    hooks: &Hooks,
+) -> Result<(PointproofsParams, ContributionProof)> {
+    while !checkpoint.is_complete(params_in) {
+        checkpoint.advance(params_in, CHECKPOINT_INTERVAL, hooks)?;
+        save_checkpoint(path, |f| checkpoint.write(f, key))?;
+    }
+    checkpoint.finish(params_in)
+}
+
+// Starts a verify checkpoint at path, or resumes from the one already there
+fn open_verify_checkpoint(
+    path: &str,
+    params_old: &PointproofsParams,
+    params_new: &PointproofsParams,
+    checker: &ConsistencyChecker,
+    report: &mut Report,
+) -> Result<VerifyCheckpoint> {
+    let fresh = VerifyCheckpoint::new(params_old, params_new, checker);
+    if !std::path::Path::new(path).exists() {
+        return Ok(fresh);
+    }
+    let checkpoint = VerifyCheckpoint::read(&mut File::open(path)?)?;
+    if !checkpoint.same_check(&fresh) {
+        return Err(Error::InvalidInput(format!(
+            "The checkpoint {} is for different params or check settings",
+            path
+        )));
+    }
+    let progress = &checkpoint.progress;
+    if progress.rounds_passed > checker.rounds() {
+        return Err(Error::Format(format!(
+            "Malformed checkpoint {}: it has {} of {} rounds passed",
+            path,
+            progress.rounds_passed,
+            checker.rounds()
+        )));
+    }
+    report.say(&format!(
+        "Resuming from checkpoint: {} of {} rounds passed, {} of 5 sums of the next round done",
+        progress.rounds_passed,
+        checker.rounds(),
+        progress.sums_done()
+    ));
+    if checker.rounds_resumed(progress) < progress.rounds_passed {
+        report.say("Every round had passed; running the last one again");
+    }
+    report.set("resumed_from", progress.rounds_passed);
+    report.set("resumed_sums", progress.sums_done());
+    Ok(checkpoint)
+}
+
+// Creates a file for writing, refusing to overwrite an existing one
+fn create_new(path: &str) -> Result<File> {
+    Ok(OpenOptions::new().write(true).create_new(true).open(path)?)
+}
+
+// Like create_new, but only the owner may read the file
+fn create_secret(path: &str) -> Result<File> {
+    let mut open = OpenOptions::new();
+    open.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open.mode(0o600);
+    }
+    Ok(open.open(path)?)
+}
+
+fn main() {
+    // let n = 1024;
+    let mut args: Vec<String> = std::env::args().collect();
+    let progname = args
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "pointproofs-paramgen".to_string());
+    let json = args.iter().any(|a| a == "--json");
+    let mut report = Report::new(json, args.get(1).map_or("", |c| c.as_str()));
+    let result = take_options(&mut args).and_then(|options| run(&args, options, &mut report));
+    report.finish(&result);
+    if let Err(e) = result {
+        if let Error::InvalidInput(_) = e {
+            usage(&progname);
+        }
+        eprintln!("Error: {}", e);
+        std::process::exit(exit_code(&e));
+    }
+}
+
+fn run(args: &[String], options: Options, report: &mut Report) -> Result<()> {
+    if args.len() < 3 {
+        return Err(wrong_arguments());
+    }
+    // number of arguments each command takes after its name
+    let arity = match args[1].as_str() {
+        "init" | "convert" | "manifest" | "keygen" | "armor" => 2,
+        "evolve" | "verify" | "finalize" | "verify-receipt" | "ingest-mbox" => 3,
+        "digest" | "audit" | "verify-transcript" | "fingerprint" => 1,
+        _ => return Err(Error::InvalidInput(format!("Unknown command: {}", args[1]))),
+    };
+    if args.len() < 2 + arity {
+        return Err(wrong_arguments());
+    }
+    report.set("command", args[1].as_str());
+    match args[1].as_str() {
+        "init" => init(&args[2], &args[3], &options, report),
+        "evolve" => evolve(&args[2], &args[3], &args[4], &options, report),
+        "verify" => verify(&args[2], &args[3], &args[4], None, &options, report),
+        "verify-receipt" => verify_receipt(&args[2], &args[3], &args[4], &options, report),
+        "convert" => convert(&args[2], &args[3], &options, report),
+        "digest" => digest(&args[2], &options, report),
+        "fingerprint" => fingerprint(&args[2], &options, report),
+        "audit" => audit(&args[2], &options, report),
+        "verify-transcript" => verify_transcript(&args[2], options, report),
+        "ingest-mbox" => ingest(&args[2], &args[3], &args[4], options, report),
+        "manifest" => manifest(&args[2], &args[3], &options, report),
+        "keygen" => keygen(&args[2], &args[3], report),
+        "armor" => armor(&args[2], &args[3], &options, report),
+        _ => finalize(&args[2], &args[3], &args[4], &options, report),
+    }
+}
+
+fn init(path: &str, n: &str, options: &Options, report: &mut Report) -> Result<()> {
+    // parse the parameter n, a usize
+    let n = match atoi::<usize>(n.as_bytes()) {
+        Some(n) => n,
+        None => {
+            return Err(Error::InvalidInput(
+                "parameter_n must be a number".to_string(),
+            ))
+        }
+    };
+    report.set("n", n);
+    if n == 0 || n > MAX_N {
+        return Err(Error::SizeLimit(n));
+    }
 
-            println!("Randomizing...");
-            let mut r: [u8; 64] = [0; 64];
-            OsRng {}.fill_bytes(&mut r[..]);
-            let (params_out, proof) = rerandomize(&params_in, &r[..], &id);
-            r.zeroize();
-            println!("Sanity-checking proof we just created...");
-            println!(
-                "{}",
-                check_rerandomization(&params_out, params_in.g2_alpha_1_to_n[0], &proof, &id)
-            );
-
-            println!("Serializing params and proof to {}", &args[4]);
-            let mut f = OpenOptions::new().write(true).create_new(true).open(&args[4]).unwrap();
-            params_out.serialize(&mut f, true).unwrap();
-            proof.serialize(&mut f, true).unwrap();
-            println!("Done!");
-        }
-        "verify" => {
-            if args.len() < 5 {
-                usage(&args[0]);
-                return;
+    let mut f = create_new(path)?;
+    report.say("Generating...");
+    // the initial vector is set to the first 100 digits of pi
+    let alpha = init_alpha();
+
+    let bar = report.progress_bar();
+    let params = report.time("generate", || generate_with_hooks(alpha, n, &bar.hooks()))?;
+    report.say("Generated.");
+    let header = FileHeader {
+        proof_mode: options.proof_mode,
+        include_gt: !options.layout.omit_gt,
+        compressed: !options.layout.uncompressed,
+    };
+    report.time("write", || write_params(&mut f, &header, &params))?;
+    report.set("settings", describe_header(&header));
+    report.set("output", describe_params(&params));
+    Ok(())
+}
+
+fn evolve(
+    id: &str,
+    input: &str,
+    output: &str,
+    options: &Options,
+    report: &mut Report,
+) -> Result<()> {
+    report.set("id", id);
+    // checkpoints need file access that the isolated worker does not have
+    if worker::SUPPORTED && !options.evolve.no_isolation && options.checkpoint.is_some() {
+        return Err(Error::InvalidInput(
+            "--checkpoint cannot be used in the isolated worker; add --no-isolation to run evolve in-process"
+                .to_string(),
+        ));
+    }
+    let key = signing_key(id, options)?;
+    report.set("signed", key.is_some());
+    let f_in = File::open(input)?;
+    let f_out = create_new(output)?;
+    let f_receipt = match &options.evolve.receipt {
+        None => None,
+        Some(path) => match create_new(path) {
+            Ok(f) => Some(f),
+            Err(e) => {
+                let _ = std::fs::remove_file(output);
+                return Err(e);
             }
-            let id = args[2].as_bytes();
-            println!("Loading old (assumed-good) params from {}", &args[3]);
-            let params_old = {
-                let mut f = File::open(&args[3]).unwrap();
-                PointproofsParams::deserialize(&mut f, true).unwrap()
-            };
-            println!("Loading new params (with proof) from {}", &args[4]);
-            let mut f = File::open(&args[4]).unwrap();
-            let params_new = PointproofsParams::deserialize(&mut f, true).unwrap();
-            let proof = schnorr::PoK::deserialize(&mut f, true).unwrap();
-
-            println!("Verifying...");
-            if check_rerandomization(&params_new, params_old.g2_alpha_1_to_n[0], &proof, &id) {
-                println!("Success!");
-            } else {
-                println!("FAILURE: Parameters or proof incorrect");
-                println!("consistent: {}", consistent(&params_new));
+        },
+    };
+    let isolate = worker::SUPPORTED && !options.evolve.no_isolation;
+    report.set("isolated", isolate);
+    let result = if isolate {
+        worker::run_isolated(f_in, f_out, report, |f_in, f_out, report| {
+            evolve_files(
+                id.as_bytes(),
+                key.as_ref(),
+                f_in,
+                f_out,
+                output,
+                options,
+                report,
+            )
+        })
+    } else {
+        evolve_files(
+            id.as_bytes(),
+            key.as_ref(),
+            f_in,
+            f_out,
+            output,
+            options,
+            report,
+        )
+    };
+    let result = result.and_then(|()| match f_receipt {
+        None => Ok(()),
+        Some(mut f) => write_receipt(&mut f, report),
+    });
+    if result.is_err() {
+        // do not leave a partial contribution behind
+        let _ = std::fs::remove_file(output);
+        if let Some(path) = &options.evolve.receipt {
+            let _ = std::fs::remove_file(path);
+        }
+        return result;
+    }
+    if let Some(path) = &options.checkpoint {
+        std::fs::remove_file(path)?;
+        report.say(&format!("Deleted checkpoint {}", path));
+    }
+    report.say("Done!");
+    Ok(())
+}
+
+// Writes the receipt that evolve_files put in the report
+fn write_receipt(f: &mut File, report: &mut Report) -> Result<()> {
+    use std::io::Write;
+    match report.fields.get("receipt").and_then(|r| r.as_str()) {
+        Some(receipt) => Ok(f.write_all(receipt.as_bytes())?),
+        None => Err(Error::Fault(
+            "the worker did not send a receipt".to_string(),
+        )),
+    }
+}
+
+// Loads the key given with --key, which evolve needs exactly when the id
+// string embeds a public key, and which must match that key
+fn signing_key(id: &str, options: &Options) -> Result<Option<SigningKey>> {
+    let embedded = embedded_key(id.as_bytes())?;
+    let key = match &options.evolve.key {
+        None => None,
+        Some(path) => Some(SigningKey::read(&mut File::open(path)?)?),
+    };
+    match (embedded, &key) {
+        (None, None) => Ok(None),
+        (Some(public), Some(k)) if k.public_key() == public => Ok(key),
+        (Some(_), Some(_)) => Err(Error::InvalidInput(
+            "The key given with --key is not the one in the id string".to_string(),
+        )),
+        (Some(_), None) => Err(Error::InvalidInput(
+            "The id string embeds a public key; give its secret key with --key".to_string(),
+        )),
+        (None, Some(_)) => Err(Error::InvalidInput(
+            "--key needs an id string that embeds its public key (see keygen)".to_string(),
+        )),
+    }
+}
+
+// The part of evolve that handles the secret, which runs in the isolated
+// worker if there is one; `output` is only used in messages
+#[allow(clippy::too_many_arguments)]
+fn evolve_files(
+    id: &[u8],
+    key: Option<&SigningKey>,
+    mut f_in: File,
+    mut f_out: File,
+    output: &str,
+    options: &Options,
+    report: &mut Report,
+) -> Result<()> {
+    report.say("Loading params...");
+    let (header, params_in) = report.time("load", || {
+        read_params_with_checks(&mut f_in, options.checks)
+    })?;
+    report.set("input", describe_params(&params_in));
+    report.say("Loaded.");
+    report.say("Checking...");
+    let bar = report.progress_bar();
+    let input_ok = report.time("check_input", || {
+        options
+            .check
+            .checker
+            .check_with_hooks(&params_in, &bar.hooks())
+    })?;
+    report.set("input_consistent", input_ok);
+    if !input_ok {
+        return Err(Error::Inconsistent);
+    }
+    report.say("Input params OK");
+
+    report.say("Randomizing...");
+    let mut r: [u8; 64] = [0; 64];
+    OsRng {}.fill_bytes(&mut r[..]);
+    let checkpoint = match &options.checkpoint {
+        None => None,
+        Some(path) => {
+            let opened =
+                open_evolve_checkpoint(path, &params_in, &r[..], id, header.proof_mode, report);
+            if opened.is_err() {
+                r.zeroize();
             }
+            Some((path, opened?))
+        }
+    };
+    let params_out_and_proof = report.time("rerandomize", || match checkpoint {
+        None => rerandomize_with_hooks(&params_in, &r[..], id, header.proof_mode, &bar.hooks()),
+        Some((path, (checkpoint, key))) => {
+            rerandomize_with_checkpoint(&params_in, checkpoint, &key, path, &bar.hooks())
+        }
+    });
+    r.zeroize();
+    let (params_out, proof) = params_out_and_proof?;
+    report.set("spot_checks", checked_powers(params_in.n, SPOT_CHECKS));
+    report.say("Sanity-checking proof we just created...");
+    let self_check = report.time("self_check", || {
+        check_contribution_with_reason(&params_out, params_in.g2_alpha_1_to_n[0], &proof, id, &[])
+    });
+    report.set("self_check", self_check.is_ok());
+    if let Err(reason) = self_check {
+        // never publish a contribution that we would reject ourselves
+        report.say(&format!("FAILURE: {}", reason));
+        return Err(Error::Fault(format!(
+            "the new contribution fails its own check ({})",
+            reason
+        )));
+    }
+    report.say("Self-check passed");
+
+    report.say(&format!("Serializing params and proof to {}", output));
+    report.time("write", || match key {
+        None => write_contribution(&mut f_out, &header, &params_out, &proof),
+        Some(key) => write_signed_contribution(&mut f_out, &header, &params_out, &proof, key),
+    })?;
+    if key.is_some() {
+        report.say("Signed the contribution");
+    }
+    report.set("output", describe_params(&params_out));
+    report.set("proof", describe_proof(&proof));
+    if let (Some(ceremony), Some(index)) = (&options.evolve.ceremony, options.evolve.index) {
+        let id = String::from_utf8_lossy(id);
+        let receipt = Receipt::new(ceremony, index, &id, &params_in, &params_out, &proof)?;
+        if options.evolve.armor {
+            report.set("receipt", receipt.to_armored().to_string());
+        } else {
+            report.set("receipt", receipt.to_string());
         }
-        "finalize" => {
-            if args.len() < 5 {
-                usage(&args[0]);
-                return;
+    }
+    Ok(())
+}
+
+fn verify_receipt(
+    path: &str,
+    old: &str,
+    new: &str,
+    options: &Options,
+    report: &mut Report,
+) -> Result<()> {
+    report.say(&format!("Loading receipt from {}", path));
+    let receipt = Receipt::read_from_file(path)?;
+    report.say(&format!(
+        "Receipt for message {} of ceremony {:?} from {}",
+        receipt.index, receipt.ceremony, receipt.id
+    ));
+    report.set("ceremony", receipt.ceremony.as_str());
+    report.set("index", receipt.index);
+    verify(&receipt.id, old, new, Some(&receipt), options, report)
+}
+
+// Checks a contribution, and that `receipt` describes it if one is given
+fn verify(
+    id: &str,
+    old: &str,
+    new: &str,
+    receipt: Option<&Receipt>,
+    options: &Options,
+    report: &mut Report,
+) -> Result<()> {
+    report.set("id", id);
+    let id = id.as_bytes();
+    report.say(&format!("Loading old (assumed-good) params from {}", old));
+    let mut f = File::open(old)?;
+    let (header_old, params_old) = report.time("load_old", || {
+        read_params_with_checks(&mut f, options.checks)
+    })?;
+    report.set("old", describe_params(&params_old));
+    report.say(&format!("Loading new params (with proof) from {}", new));
+    let mut f = File::open(new)?;
+    let (header_new, params_new, proof, signature) = report.time("load_new", || {
+        read_signed_contribution_with_checks(&mut f, options.checks)
+    })?;
+    report.set("new", describe_params(&params_new));
+    report.set("proof", describe_proof(&proof));
+    let settings_ok = header_new == header_old;
+    report.set("settings_match", settings_ok);
+    if !settings_ok {
+        report.say("FAILURE: New params do not use the same file settings as the old ones");
+        return Err(Error::Rejected(Rejection::SettingsMismatch));
+    }
+    if let Some(receipt) = receipt {
+        let receipt_ok = receipt.check_files(&params_old, &params_new, &proof);
+        report.set("receipt_matches", receipt_ok.is_ok());
+        if let Err(reason) = receipt_ok {
+            report.say(&format!("FAILURE: {}", reason));
+            return Err(Error::Rejected(reason));
+        }
+        report.say("Receipt matches the files");
+    }
+    if let Ok(None) = embedded_key(id) {
+        report.set("signature", "not checked");
+    } else {
+        let signature_ok = check_signature(id, signature.as_ref());
+        report.set(
+            "signature",
+            if signature_ok.is_ok() { "pass" } else { "fail" },
+        );
+        if let Err(reason) = signature_ok {
+            report.say(&format!("FAILURE: {}", reason));
+            return Err(Error::Rejected(reason));
+        }
+        report.say("Signature OK");
+    }
+    if let Ok(None) = named_fingerprint(id) {
+        report.set("openpgp_signature", "not checked");
+    } else {
+        let keyring = load_keyring(options, report)?;
+        let signature_ok = check_detached_signature(std::path::Path::new(new), id, None, &keyring);
+        report.set(
+            "openpgp_signature",
+            if signature_ok.is_ok() { "pass" } else { "fail" },
+        );
+        if let Err(reason) = signature_ok {
+            report.say(&format!("FAILURE: OpenPGP {}", reason));
+            return Err(Error::Rejected(reason));
+        }
+        report.say("OpenPGP signature OK");
+    }
+
+    let mut checkpoint = match &options.checkpoint {
+        None => None,
+        Some(path) => Some(open_verify_checkpoint(
+            path,
+            &params_old,
+            &params_new,
+            &options.check.checker,
+            report,
+        )?),
+    };
+    let progress = checkpoint
+        .as_ref()
+        .map_or_else(CheckProgress::default, |c| c.progress.clone());
+    // how many rounds of the check were taken from the checkpoint
+    let checkpoint_rounds = checkpoint
+        .as_ref()
+        .map(|c| options.check.checker.rounds_resumed(&c.progress));
+    // records each sum and round of the consistency check as it is done
+    let mut save_progress = |progress: &CheckProgress| -> Result<()> {
+        match (checkpoint.as_mut(), &options.checkpoint) {
+            (Some(checkpoint), Some(path)) => {
+                checkpoint.progress = progress.clone();
+                save_checkpoint(path, |f| checkpoint.write(f))
             }
-            let beacon = args[2].as_bytes();
-            println!("Loading params...");
-            let mut f = File::open(&args[3]).unwrap();
-            let params_in = PointproofsParams::deserialize(&mut f, true).unwrap();
-            println!("Loaded.");
-            println!("Computing final parameters...");
-            let (params_out, _) = rerandomize(&params_in, &beacon, b""); // Since the beacon value is public, we don't care about the schnorr proof, so we don't care about id_string here
-            println!("Computed.");
-            println!("Serializing final params to {}", &args[4]);
-            let mut f = OpenOptions::new().write(true).create_new(true).open(&args[4]).unwrap();
-            params_out.serialize(&mut f, true).unwrap();
-            println!("Done!");
+            _ => Ok(()),
+        }
+    };
+
+    report.say(&format!("Verifying ({})...", options.check.checker));
+    let bar = report.progress_bar();
+    let result = match report.time("verify", || {
+        check_contribution_resumable(
+            &options.check.checker,
+            &params_new,
+            params_old.g2_alpha_1_to_n[0],
+            &proof,
+            id,
+            &[params_digest(&params_old)],
+            progress,
+            &bar.hooks(),
+            &mut save_progress,
+        )
+    }) {
+        Ok(()) => Ok(()),
+        Err(Error::Rejected(reason)) => Err(reason),
+        Err(e) => return Err(e),
+    };
+    // a check that stopped before its first sum never wrote the checkpoint
+    if let Some(path) = &options.checkpoint {
+        if std::path::Path::new(path).exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    report.set("checks", describe_checks(&result));
+    report.set("soundness", options.check.checker.to_string());
+    if let Some(rounds) = checkpoint_rounds {
+        report.set("checkpoint_rounds", rounds);
+    }
+    let verdict = match &result {
+        Ok(()) => "Success!".to_string(),
+        Err(reason) => format!("FAILURE: {}", reason),
+    };
+    report.say(&verdict);
+    let digest = params_digest(&params_new);
+    if let Some(seed) = &options.check.seed {
+        report.set("seed", seed.as_str());
+        let resumed = checkpoint_rounds.map_or(String::new(), |rounds| {
+            format!(" checkpoint_rounds={}", rounds)
+        });
+        report.say(&format!(
+            "Record: seed={:?} digest={} result={:?}{}",
+            seed,
+            to_hex(&digest),
+            verdict,
+            resumed
+        ));
+    } else {
+        report.say(&format!("Digest of the new params: {}", to_hex(&digest)));
+    }
+    say_fingerprint(report, &digest);
+    Ok(result?)
+}
+
+fn convert(input: &str, output: &str, options: &Options, report: &mut Report) -> Result<()> {
+    report.say(&format!("Loading params from {}", input));
+    let mut f = File::open(input)?;
+    let (header_in, params) =
+        report.time("load", || read_params_with_checks(&mut f, options.checks))?;
+    let header = FileHeader {
+        include_gt: !options.layout.omit_gt,
+        compressed: !options.layout.uncompressed,
+        ..header_in
+    };
+    report.say(&format!("Writing params to {}", output));
+    let mut f = create_new(output)?;
+    report.time("write", || write_params(&mut f, &header, &params))?;
+    report.set("params", describe_params(&params));
+    report.set("settings", describe_header(&header));
+    report.say("Done!");
+    Ok(())
+}
+
+fn digest(path: &str, options: &Options, report: &mut Report) -> Result<()> {
+    report.say(&format!("Loading params from {}", path));
+    let bytes = std::fs::read(path)?;
+    let (_, params) = report.time("load", || {
+        read_params_with_checks(&mut &bytes[..], options.checks)
+    })?;
+    report.set("params", describe_params(&params));
+    report.say(&format!("Checking ({})...", options.check.checker));
+    let bar = report.progress_bar();
+    let ok = report.time("check", || {
+        options
+            .check
+            .checker
+            .check_with_hooks(&params, &bar.hooks())
+    })?;
+    report.set("consistent", ok);
+    report.set("soundness", options.check.checker.to_string());
+    if let Some(seed) = &options.check.seed {
+        report.set("seed", seed.as_str());
+        report.say(&format!(
+            "Record: seed={:?} digest={} result={:?}",
+            seed,
+            to_hex(&params_digest(&params)),
+            if ok { "consistent" } else { "inconsistent" }
+        ));
+        say_fingerprint(report, &params_digest(&params));
+    }
+    if !ok {
+        return Err(Error::Inconsistent);
+    }
+    let file_digest = digest_bytes(&bytes);
+    report.say(&format!("File digest: {}", to_hex(&file_digest)));
+    say_fingerprint(report, &file_digest);
+    report.set("file_digest", to_hex(&file_digest));
+    report.set("file_words", word_string(&file_digest));
+    report.set("file_randomart", randomart(&file_digest));
+    Ok(())
+}
+
+// Prints the word and picture fingerprints of a digest just printed, for
+// people who compare it over a call or at a glance
+fn say_fingerprint(report: &Report, digest: &Digest) {
+    report.say(&format!("Words: {}", word_string(digest)));
+    report.say(&randomart(digest));
+}
+
+// Prints the fingerprint of a parameter, receipt or transcript file
+fn fingerprint(path: &str, options: &Options, report: &mut Report) -> Result<()> {
+    let bytes = std::fs::read(path)?;
+    let text = std::str::from_utf8(&bytes).ok();
+    let (kind, digest) = if let Some(receipt) = text.and_then(|t| Receipt::parse(t).ok()) {
+        // the same receipt has the same fingerprint, armored or not
+        ("receipt", digest_bytes(receipt.to_string()))
+    } else if read_params_with_checks(&mut &bytes[..], options.checks).is_ok() {
+        ("params", digest_bytes(&bytes))
+    } else if text.map_or(false, |t| {
+        Transcript::parse(t, std::path::Path::new("")).is_ok()
+    }) {
+        ("transcript", digest_bytes(&bytes))
+    } else {
+        return Err(Error::Format(format!(
+            "{} is not a parameter, receipt or transcript file",
+            path
+        )));
+    };
+    report.say(&format!("{} {}", kind, path));
+    report.say(&format!("Digest: {}", to_hex(&digest)));
+    say_fingerprint(report, &digest);
+    report.set("kind", kind);
+    report.set("digest", to_hex(&digest));
+    report.set("words", word_string(&digest));
+    report.set("randomart", randomart(&digest));
+    Ok(())
+}
+
+// The certificates given with --keyring, if any
+fn load_keyring(options: &Options, report: &mut Report) -> Result<Keyring> {
+    match &options.keyring {
+        None => Ok(Keyring::new()),
+        Some(path) => {
+            let keyring = Keyring::read_from_file(path)?;
+            report.say(&format!(
+                "Loaded {} OpenPGP certificates from {}",
+                keyring.len(),
+                path
+            ));
+            report.set("keyring", path.as_str());
+            Ok(keyring)
+        }
+    }
+}
+
+// Prints a receipt, the proof of a contribution, or the digest of a file in armored form
+fn armor(kind: &str, path: &str, options: &Options, report: &mut Report) -> Result<()> {
+    let digest = match kind {
+        "digest" => Some(digest_bytes(std::fs::read(path)?)),
+        _ => None,
+    };
+    let armored = match (kind, &digest) {
+        (_, Some(digest)) => armor_digest(digest),
+        ("receipt", _) => Receipt::read_from_file(path)?.to_armored(),
+        ("proof", _) => {
+            let mut f = File::open(path)?;
+            let (_, _, proof) = read_contribution_with_checks(&mut f, options.checks)?;
+            armor_proof(&proof)?
         }
         _ => {
-            usage(&args[0]);
+            return Err(Error::InvalidInput(
+                "armor takes one of: receipt, proof, digest".to_string(),
+            ))
+        }
+    };
+    let text = armored.to_string();
+    report.say(text.trim_end());
+    report.set("armored", text);
+    if let Some(digest) = &digest {
+        say_fingerprint(report, digest);
+        report.set("words", word_string(digest));
+        report.set("randomart", randomart(digest));
+    }
+    Ok(())
+}
+
+fn keygen(name: &str, path: &str, report: &mut Report) -> Result<()> {
+    let key = SigningKey::generate();
+    let id = key.id_string(name)?;
+    let mut f = create_secret(path)?;
+    key.write(&mut f)?;
+    report.say(&format!("Wrote the secret key to {}", path));
+    report.say(&format!("Register this id string: {}", id));
+    report.set("id", id);
+    Ok(())
+}
+
+// The manifest at path, whose signature is checked if --organizers gives the organizers' certificate
+fn load_manifest(path: &str, options: &Options, report: &mut Report) -> Result<Manifest> {
+    report.set("manifest", path);
+    match &options.ceremony.organizers {
+        None => {
+            report.say("Not checking the organizers' signature on the manifest (no --organizers)");
+            report.set("manifest_signed", false);
+            Manifest::read_from_file(path)
+        }
+        Some(cert) => {
+            let cert = parse_certificate(&std::fs::read_to_string(cert)?)?;
+            let manifest = Manifest::read_signed(path, &cert)?;
+            report.say(&format!(
+                "Manifest signed by the organizers ({})",
+                fingerprint_hex(&cert)
+            ));
+            report.set("manifest_signed", true);
+            Ok(manifest)
+        }
+    }
+}
+
+fn manifest(subcommand: &str, path: &str, options: &Options, report: &mut Report) -> Result<()> {
+    if subcommand != "check" {
+        return Err(Error::InvalidInput(format!(
+            "Unknown manifest command: {}",
+            subcommand
+        )));
+    }
+    report.say(&format!("Loading manifest from {}", path));
+    let bytes = std::fs::read(path)?;
+    let file_digest = digest_bytes(&bytes);
+    report.set("file_digest", to_hex(&file_digest));
+    report.set("file_words", word_string(&file_digest));
+    report.set("file_randomart", randomart(&file_digest));
+    let manifest = load_manifest(path, options, report)?;
+    report.set("n", manifest.n);
+    report.set("participants", manifest.participants.len());
+    report.say(&format!(
+        "n = {}, {} participants, beacon: {} ({})",
+        manifest.n,
+        manifest.participants.len(),
+        manifest.beacon.source,
+        manifest.beacon.rule
+    ));
+    let problems = manifest.problems();
+    for problem in problems.iter() {
+        report.say(&format!("PROBLEM: {}", problem));
+    }
+    report.set("problems", problems.clone());
+    report.say(&format!("File digest: {}", to_hex(&file_digest)));
+    say_fingerprint(report, &file_digest);
+    manifest.validate()
+}
+
+fn audit(path: &str, options: &Options, report: &mut Report) -> Result<()> {
+    report.say(&format!("Loading params from {}", path));
+    let mut f = File::open(path)?;
+    let (_, params) = report.time("load", || read_params_with_checks(&mut f, options.checks))?;
+    report.set("params", describe_params(&params));
+    report.say("Searching for a trivially known alpha...");
+    let found = report.time("audit", || {
+        audit_alpha(&params.g2_alpha_1_to_n[0], &AuditOptions::default())
+    });
+    let found: Vec<String> = found.iter().map(|w| w.to_string()).collect();
+    report.set("weaknesses", found.clone());
+    if found.is_empty() {
+        report.say("No weak alpha found");
+        return Ok(());
+    }
+    for weakness in found.iter() {
+        report.say(&format!("WEAK: {}", weakness));
+    }
+    Err(Error::WeakAlpha(found.join("; ")))
+}
+
+fn verify_transcript(path: &str, options: Options, report: &mut Report) -> Result<()> {
+    let transcript = Transcript::read_from_file(path)?;
+    report.say(&format!(
+        "Verifying {} messages ({} each)...",
+        transcript.entries.len(),
+        options.check.checker
+    ));
+    report.set("soundness", options.check.checker.to_string());
+    let manifest = match &options.ceremony.manifest {
+        None => None,
+        Some(path) => {
+            report.say(&format!("Enforcing the manifest {}", path));
+            Some(load_manifest(path, &options, report)?)
+        }
+    };
+    let keyring = load_keyring(&options, report)?;
+    let options = TranscriptOptions {
+        checks: options.checks,
+        checker: options.check.checker,
+        manifest,
+        keyring,
+    };
+    let summary = report.time("verify", || {
+        verify_transcript_with_options(&transcript, &options)
+    })?;
+    let mut outcomes = vec![];
+    for (j, outcome) in summary.outcomes.iter().enumerate() {
+        match &outcome.result {
+            Ok(()) => report.say(&format!("{} {}: good", j + 1, outcome.id)),
+            Err(reason) => report.say(&format!("{} {}: rejected ({})", j + 1, outcome.id, reason)),
+        }
+        outcomes.push(json!({
+            "index": j + 1,
+            "id": outcome.id,
+            "good": outcome.result.is_ok(),
+            "reason": outcome.result.as_ref().err().map(|r| r.to_string()),
+        }));
+    }
+    report.say(&format!("Latest good message: {}", summary.latest_good));
+    report.set("outcomes", outcomes);
+    report.set("latest_good", summary.latest_good);
+    Ok(())
+}
+
+fn ingest(
+    mbox: &str,
+    initial: &str,
+    dir: &str,
+    options: Options,
+    report: &mut Report,
+) -> Result<()> {
+    let manifest = match &options.ceremony.manifest {
+        None => {
+            return Err(Error::InvalidInput(
+                "ingest-mbox needs --manifest".to_string(),
+            ))
+        }
+        Some(path) => load_manifest(path, &options, report)?,
+    };
+    let keyring = load_keyring(&options, report)?;
+    // the transcript is read from dir, so the initial params must not be relative to here
+    let initial = std::fs::canonicalize(initial)?;
+    report.say(&format!("Reading messages from {}", mbox));
+    let bytes = std::fs::read(mbox)?;
+    report.say(&format!(
+        "Checking contributions ({} each)...",
+        options.check.checker
+    ));
+    report.set("soundness", options.check.checker.to_string());
+    let options = IngestOptions {
+        checks: options.checks,
+        checker: options.check.checker,
+        manifest,
+        keyring,
+    };
+    let summary = report.time("ingest", || {
+        ingest_mbox(&bytes, &initial, std::path::Path::new(dir), &options)
+    })?;
+    let mut messages = vec![];
+    for (k, message) in summary.messages.iter().enumerate() {
+        let (participant, verdict) = match &message.outcome {
+            MailOutcome::Accepted(i) => (Some(*i), "accepted".to_string()),
+            MailOutcome::Rejected(i, reason) => (Some(*i), format!("rejected ({})", reason)),
+            MailOutcome::Ignored(i) => (
+                Some(*i),
+                "ignored (another message is the contribution)".to_string(),
+            ),
+            MailOutcome::Unattributed(reason) => (None, format!("skipped ({})", reason)),
+        };
+        match participant {
+            Some(i) => report.say(&format!(
+                "message {} from {}: participant {}, {}",
+                k + 1,
+                message.from,
+                i,
+                verdict
+            )),
+            None => report.say(&format!(
+                "message {} from {}: {}",
+                k + 1,
+                message.from,
+                verdict
+            )),
         }
+        messages.push(json!({
+            "from": message.from,
+            "date": message.date,
+            "participant": participant,
+            "result": verdict,
+        }));
+    }
+    let transcript = std::path::Path::new(dir).join(TRANSCRIPT_FILE);
+    let contributions = summary
+        .transcript
+        .entries
+        .iter()
+        .filter(|e| e.path.is_some())
+        .count();
+    report.say(&format!(
+        "Wrote {} contributions and the transcript {}",
+        contributions,
+        transcript.display()
+    ));
+    report.set("messages", messages);
+    report.set("contributions", contributions);
+    report.set("transcript", transcript.display().to_string());
+    Ok(())
+}
+
+fn finalize(
+    beacon: &str,
+    input: &str,
+    output: &str,
+    options: &Options,
+    report: &mut Report,
+) -> Result<()> {
+    report.set("beacon", beacon);
+    let beacon = beacon.as_bytes();
+    report.say("Loading params...");
+    let mut f = File::open(input)?;
+    let (_, params_in) = report.time("load", || read_params_with_checks(&mut f, options.checks))?;
+    report.set("input", describe_params(&params_in));
+    report.say("Loaded.");
+    report.say("Computing final parameters...");
+    let bar = report.progress_bar();
+    // Since the beacon value is public, we don't care about the schnorr proof, so we don't care about id_string here
+    let (params_out, _) = report.time("rerandomize", || {
+        rerandomize_with_hooks(&params_in, beacon, b"", ProofMode::Schnorr, &bar.hooks())
+    })?;
+    report.say("Computed.");
+    let header = FileHeader {
+        include_gt: !options.layout.omit_gt,
+        compressed: !options.layout.uncompressed,
+        ..FileHeader::default()
+    };
+    report.say(&format!("Serializing final params to {}", output));
+    let mut f = create_new(output)?;
+    report.time("write", || write_params(&mut f, &header, &params_out))?;
+    report.set("output", describe_params(&params_out));
+    report.set("settings", describe_header(&header));
+    report.say("Done!");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Runs a command as main does with --json, and returns the JSON object it prints
+    fn run_json(args: &[&str]) -> Value {
+        let mut args: Vec<String> = std::iter::once("pointproofs-paramgen")
+            .chain(args.iter().cloned())
+            .chain(std::iter::once("--json"))
+            .map(String::from)
+            .collect();
+        let mut report = Report::new(true, &args[1]);
+        let result = take_options(&mut args).and_then(|options| run(&args, options, &mut report));
+        report.into_json(&result)
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn test_json_output() {
+        let dir = std::env::temp_dir().join(format!("pointproofs-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let (p0, p1) = (path("p0"), path("p1"));
+
+        let init = run_json(&["init", &p0, "4"]);
+        assert_eq!(
+            keys(&init),
+            ["command", "n", "ok", "output", "settings", "timings_ms"]
+        );
+        assert_eq!(
+            keys(&init["output"]),
+            ["digest", "g2_alpha", "n", "randomart", "words"]
+        );
+        assert_eq!(init["settings"]["proof_mode"], "schnorr");
+        assert!(init["timings_ms"]["generate"].is_u64());
+
+        let evolve = run_json(&["evolve", "alice", &p0, &p1, "--no-isolation"]);
+        assert_eq!(evolve["ok"], true, "{}", evolve);
+        assert_eq!(evolve["input"], init["output"]);
+        assert_eq!(evolve["input_consistent"], true);
+        assert_eq!(evolve["isolated"], false);
+        assert_eq!(evolve["spot_checks"], SPOT_CHECKS + 2);
+        assert_eq!(evolve["self_check"], true);
+        assert_eq!(evolve["signed"], false);
+        assert!(evolve["proof"]["g1_delta"].is_string());
+
+        let verify = run_json(&["verify", "alice", &p0, &p1]);
+        assert_eq!(
+            keys(&verify),
+            [
+                "checks",
+                "command",
+                "id",
+                "new",
+                "ok",
+                "old",
+                "openpgp_signature",
+                "proof",
+                "settings_match",
+                "signature",
+                "soundness",
+                "timings_ms"
+            ]
+        );
+        assert_eq!(verify["new"], evolve["output"]);
+        for check in ["consistency", "degenerate_exponent", "proof", "replay"].iter() {
+            assert_eq!(verify["checks"][check], "pass", "{}", verify["checks"]);
+        }
+
+        // a rejection is described, with the exit code main exits with
+        let rejected = run_json(&["verify", "bob", &p0, &p1]);
+        assert_eq!(rejected["ok"], false);
+        assert_eq!(rejected["exit_code"], EXIT_REJECTED);
+        assert!(rejected["error"].is_string());
+
+        let digest = run_json(&["digest", &p1]);
+        assert_eq!(digest["ok"], true);
+        assert_eq!(digest["consistent"], true);
+        assert_eq!(digest["params"], evolve["output"]);
+        assert!(digest["file_digest"].is_string());
+        assert!(digest["file_words"].is_string());
+        assert!(digest["file_randomart"].is_string());
+
+        // an armored digest comes with the fingerprint of the digest
+        let armored = run_json(&["armor", "digest", &p1]);
+        assert_eq!(armored["words"], digest["file_words"]);
+        assert_eq!(armored["randomart"], digest["file_randomart"]);
+
+        // flags are checked against the command
+        let wrong = run_json(&["digest", &p1, "--omit-gt"]);
+        assert_eq!(wrong["ok"], false);
+        assert_eq!(wrong["exit_code"], EXIT_USAGE);
+        let missing = run_json(&["verify", "alice", &p0, &p1, "--seed"]);
+        assert_eq!(missing["exit_code"], EXIT_USAGE);
+        assert!(missing["error"]
+            .as_str()
+            .unwrap()
+            .contains("--seed needs a value"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,302 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the ceremony manifest, which fixes everything about a ceremony
+//! that usage.md asks the organizers to publish in advance.
+//!
+//! A manifest is a JSON file:
+//! ```text
+//! {
+//!   "version": 1,
+//!   "n": 1024,
+//!   "init_seed": "31415926...",
+//!   "participants": [
+//!     {"id": "alice", "public_key": "...", "slot": {"start": 1767225600, "end": 1767312000}},
+//!     {"id": "bob", "public_key": "...", "slot": {"start": 1767312000, "end": 1767398400}}
+//!   ],
+//!   "beacon": {"source": "...", "rule": "...", "not_before": 1767484800}
+//! }
+//! ```
+//! Times are Unix timestamps in seconds, and a slot runs from `start` up to
+//! but not including `end`. Participants are listed in the order in which
+//! they contribute, which must be the sorted order of their id strings, and
+//! their slots must follow one another without overlapping. The beacon must
+//! not be sampled before the last slot ends.
+//!
+//! The organizers sign the manifest with OpenPGP, as an ASCII-armored
+//! detached signature at `path.asc` covering the whole file, and
+//! `read_signed` checks it against their certificate.
+use crate::digest::to_hex;
+use crate::error::{Error, Result, MAX_N};
+use crate::openpgp::{
+    detached_signature_path, fingerprint_hex, named_fingerprint, parse_certificate,
+    verify_detached, ARMORED_CERTIFICATE,
+};
+use crate::signature::embedded_key;
+use crate::transcript::Transcript;
+use crate::INIT_ALPHA_SEED;
+use pgp::SignedPublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Version of the manifest file format this tool reads. It only says how to
+/// read the file, and is not hashed into anything the ceremony computes.
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Version of the file format; must be `MANIFEST_VERSION`.
+    pub version: u32,
+    /// The parameter n of the ceremony.
+    pub n: usize,
+    /// The string hashed to obtain the initial alpha; must be `INIT_ALPHA_SEED`.
+    pub init_seed: String,
+    /// The registered participants, in the order in which they contribute.
+    pub participants: Vec<Participant>,
+    pub beacon: Beacon,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Participant {
+    /// The id string the participant registered.
+    pub id: String,
+    /// The key the participant signs their message with, in a form that
+    /// messages are checked against. If the id string embeds an Ed25519 key,
+    /// this must be the same key in hex. If it names an OpenPGP fingerprint,
+    /// this is either that fingerprint in hex or the ASCII-armored
+    /// certificate itself. Any other id string needs the certificate.
+    pub public_key: String,
+    /// When the participant must broadcast their message.
+    pub slot: Slot,
+}
+
+/// A time interval [start, end), in Unix seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Slot {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Slot {
+    pub fn contains(&self, time: u64) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+/// How the randomness beacon mixed in by `finalize` is obtained.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Beacon {
+    /// The public source of randomness.
+    pub source: String,
+    /// Which output of the source is used.
+    pub rule: String,
+    /// The beacon value must not be known before this time.
+    pub not_before: u64,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| Error::Format(format!("Bad manifest: {}", e)))
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Manifest::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Reads a manifest whose detached signature must verify under `organizers`.
+    pub fn read_signed<P: AsRef<Path>>(path: P, organizers: &SignedPublicKey) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let signature = std::fs::read_to_string(detached_signature_path(path))?;
+        if !verify_detached(organizers, &bytes, &signature) {
+            return Err(Error::Format(
+                "Manifest is not signed by the organizers".to_string(),
+            ));
+        }
+        let text = String::from_utf8(bytes)
+            .map_err(|_| Error::Format("Manifest is not UTF-8".to_string()))?;
+        Manifest::parse(&text)
+    }
+
+    /// Everything wrong with the manifest, in the order found.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.version != MANIFEST_VERSION {
+            problems.push(format!(
+                "version {} is not supported (expected {})",
+                self.version, MANIFEST_VERSION
+            ));
+        }
+        if self.n == 0 || self.n > MAX_N {
+            problems.push(format!("n = {} is not in 1..={}", self.n, MAX_N));
+        }
+        if self.init_seed != INIT_ALPHA_SEED {
+            problems
+                .push("init_seed is not the seed this tool generates parameters from".to_string());
+        }
+        if self.participants.is_empty() {
+            problems.push("there are no participants".to_string());
+        }
+
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        let mut keys: HashMap<&str, usize> = HashMap::new();
+        for (j, p) in self.participants.iter().enumerate() {
+            let index = j + 1;
+            if p.id.is_empty() || p.id.contains(['\t', '\n', '\r']) {
+                problems.push(format!(
+                    "participant {} has an empty id or one containing a tab or line break",
+                    index
+                ));
+            }
+            if let Some(first) = ids.insert(&p.id, index) {
+                problems.push(format!(
+                    "participants {} and {} have the same id {:?}",
+                    first, index, p.id
+                ));
+            }
+            if p.public_key.is_empty() {
+                problems.push(format!("participant {} has no public key", index));
+            } else if let Some(first) = keys.insert(&p.public_key, index) {
+                problems.push(format!(
+                    "participants {} and {} have the same public key",
+                    first, index
+                ));
+            }
+            if !p.public_key.is_empty() {
+                problems.extend(key_problem(p, index));
+            }
+            if p.slot.start >= p.slot.end {
+                problems.push(format!("the slot of participant {} is empty", index));
+            }
+            if j > 0 {
+                let prev = &self.participants[j - 1];
+                if prev.id > p.id {
+                    problems.push(format!(
+                        "participants are not sorted by id: {:?} comes before {:?}",
+                        prev.id, p.id
+                    ));
+                }
+                if prev.slot.end > p.slot.start {
+                    problems.push(format!(
+                        "the slots of participants {} and {} overlap or are out of order",
+                        j, index
+                    ));
+                }
+            }
+        }
+
+        if self.beacon.source.is_empty() || self.beacon.rule.is_empty() {
+            problems.push("the beacon source or rule is missing".to_string());
+        }
+        if let Some(last) = self.participants.last() {
+            if self.beacon.not_before < last.slot.end {
+                problems.push("the beacon may be sampled before the last slot ends".to_string());
+            }
+        }
+        problems
+    }
+
+    /// Fails with `Error::Format` listing every problem, if there are any.
+    pub fn validate(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Format(format!(
+                "Bad manifest: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+
+    /// Checks that the transcript lists the manifest's participants in
+    /// order, each at most once. A transcript of a ceremony still in
+    /// progress may stop before the last participant.
+    pub fn check_transcript(&self, transcript: &Transcript) -> Result<()> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (j, entry) in transcript.entries.iter().enumerate() {
+            let index = j + 1;
+            if let Some(first) = seen.insert(&entry.id, index) {
+                return Err(Error::Format(format!(
+                    "Transcript lists {:?} twice, as messages {} and {}",
+                    entry.id, first, index
+                )));
+            }
+            match self.participants.get(j) {
+                None => {
+                    return Err(Error::Format(format!(
+                        "Transcript has {} messages but the manifest only {} participants",
+                        transcript.entries.len(),
+                        self.participants.len()
+                    )))
+                }
+                Some(p) if p.id != entry.id => {
+                    let reason = if self.participants.iter().any(|p| p.id == entry.id) {
+                        "is out of order"
+                    } else {
+                        "is not registered in the manifest"
+                    };
+                    return Err(Error::Format(format!(
+                        "Message {} from {:?} {}; expected {:?}",
+                        index, entry.id, reason, p.id
+                    )));
+                }
+                Some(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+// What is wrong with the public key of participant `index`, if anything: it
+// must be one that their messages are checked against, or it would be
+// registered without ever being used
+fn key_problem(p: &Participant, index: usize) -> Option<String> {
+    let embedded = match embedded_key(p.id.as_bytes()) {
+        Ok(key) => key.map(|k| to_hex(k.as_bytes())),
+        Err(_) => {
+            return Some(format!(
+                "participant {} has an id string with a malformed embedded key",
+                index
+            ))
+        }
+    };
+    let named = match named_fingerprint(p.id.as_bytes()) {
+        Ok(named) => named.map(|f| to_hex(&f)),
+        Err(_) => {
+            return Some(format!(
+                "participant {} has an id string with a malformed fingerprint",
+                index
+            ))
+        }
+    };
+    let key = if p.public_key.trim_start().starts_with(ARMORED_CERTIFICATE) {
+        match parse_certificate(&p.public_key) {
+            // the certificate checks the detached signature whatever the id string
+            Ok(_) if embedded.is_none() && named.is_none() => return None,
+            Ok(cert) => fingerprint_hex(&cert),
+            Err(e) => return Some(format!("participant {}: {}", index, e)),
+        }
+    } else {
+        p.public_key.to_lowercase()
+    };
+    match (embedded, named) {
+        (Some(embedded), _) if embedded != key => Some(format!(
+            "participant {} has an id string that embeds a key other than their public key",
+            index
+        )),
+        (_, Some(named)) if named != key => Some(format!(
+            "participant {} has an id string that names a certificate other than their public key",
+            index
+        )),
+        (None, None) => Some(format!(
+            "participant {} has a public key that is not an OpenPGP certificate, \
+             and an id string that neither embeds nor names it",
+            index
+        )),
+        _ => None,
+    }
+}
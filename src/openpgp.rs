@@ -0,0 +1,262 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It checks the detached OpenPGP signatures that participants who sign
+//! with PGP broadcast next to their messages.
+//!
+//! An id string whose last word is `openpgp:<hex fingerprint>` names the
+//! participant's OpenPGP certificate. The certificate itself comes either
+//! from the ceremony manifest, whose `public_key` field may hold an
+//! ASCII-armored certificate, or from a keyring file of armored
+//! certificates given by the verifier. The signature on the message at
+//! `path` is the ASCII-armored detached signature at `path.asc`, and covers
+//! the whole file.
+use crate::digest::{from_hex, to_hex};
+use crate::error::{Error, Result};
+use crate::Rejection;
+use pgp::packet::{RevocationCode, SignatureType};
+use pgp::types::{KeyTrait, Tag};
+use pgp::{Deserializable, Signature, SignedPublicKey, StandaloneSignature};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the last word of an id string that names an OpenPGP certificate.
+pub const FINGERPRINT_PREFIX: &str = "openpgp:";
+
+/// The first line of an ASCII-armored certificate.
+pub const ARMORED_CERTIFICATE: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+
+/// The fingerprint named by an id string, if it names one.
+/// Fails if the id string ends in something that looks like a fingerprint but is not.
+pub fn named_fingerprint(id: &[u8]) -> Result<Option<Vec<u8>>> {
+    let id = match std::str::from_utf8(id) {
+        Ok(id) => id,
+        Err(_) => return Ok(None),
+    };
+    let hex = match id
+        .rsplit(' ')
+        .next()
+        .and_then(|w| w.strip_prefix(FINGERPRINT_PREFIX))
+    {
+        Some(hex) => hex,
+        None => return Ok(None),
+    };
+    match from_hex(hex) {
+        // v4 fingerprints are 20 bytes, v5 ones 32
+        Some(fingerprint) if fingerprint.len() == 20 || fingerprint.len() == 32 => {
+            Ok(Some(fingerprint))
+        }
+        _ => Err(Error::Format(
+            "The id string names a malformed OpenPGP fingerprint".to_string(),
+        )),
+    }
+}
+
+/// Parses one ASCII-armored certificate and checks its self-signatures.
+pub fn parse_certificate(armored: &str) -> Result<SignedPublicKey> {
+    let cert = SignedPublicKey::from_armor_single(Cursor::new(armored.as_bytes()))
+        .map(|(cert, _)| cert)
+        .map_err(|e| Error::Format(format!("Bad OpenPGP certificate: {}", e)))?;
+    cert.verify()
+        .map_err(|e| Error::Format(format!("Bad OpenPGP certificate: {}", e)))?;
+    Ok(cert)
+}
+
+/// Lowercase hex fingerprint of a certificate's primary key.
+pub fn fingerprint_hex(cert: &SignedPublicKey) -> String {
+    to_hex(&cert.fingerprint())
+}
+
+/// A set of certificates to look up by fingerprint.
+#[derive(Default)]
+pub struct Keyring {
+    certs: Vec<SignedPublicKey>,
+}
+
+impl Keyring {
+    pub fn new() -> Self {
+        Keyring::default()
+    }
+
+    /// Parses any number of concatenated ASCII-armored certificates.
+    pub fn parse(armored: &str) -> Result<Self> {
+        let bad = |e: pgp::errors::Error| Error::Format(format!("Bad OpenPGP keyring: {}", e));
+        let mut keyring = Keyring::new();
+        // the armor reader stops after the first block, so read them one by one
+        for block in armored.split(ARMORED_CERTIFICATE).skip(1) {
+            let block = format!("{}{}", ARMORED_CERTIFICATE, block);
+            let (cert, _) =
+                SignedPublicKey::from_armor_single(Cursor::new(block.as_bytes())).map_err(bad)?;
+            cert.verify().map_err(bad)?;
+            keyring.certs.push(cert);
+        }
+        Ok(keyring)
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Keyring::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.certs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certs.is_empty()
+    }
+
+    /// The certificate whose primary key has this fingerprint.
+    pub fn find(&self, fingerprint: &[u8]) -> Option<&SignedPublicKey> {
+        self.certs.iter().find(|c| c.fingerprint() == fingerprint)
+    }
+}
+
+/// Where the detached signature on the message at `path` is expected.
+pub fn detached_signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".asc");
+    PathBuf::from(name)
+}
+
+/// Seconds since the epoch at which `signature` was made, if it says.
+fn created(signature: &Signature) -> Option<i64> {
+    signature.created().map(|t| t.timestamp())
+}
+
+/// The most recent of `signatures`.
+fn latest<'a>(signatures: impl Iterator<Item = &'a Signature>) -> Option<&'a Signature> {
+    signatures.max_by_key(|s| created(s))
+}
+
+/// Whether a key created at `key_created` has run out by `at`, going by the
+/// lifetime its self-signature `binding` gives it (none or zero is forever).
+fn expired(key_created: i64, binding: &Signature, at: i64) -> bool {
+    match binding.key_expiration_time().map(|t| t.timestamp()) {
+        Some(lifetime) if lifetime > 0 => at >= key_created + lifetime,
+        _ => false,
+    }
+}
+
+/// Whether one of `revocations` disowns what the key signed at `at`. A key
+/// superseded or retired only stops vouching for what it signs afterwards;
+/// any other revocation, compromise in particular, reaches back to its
+/// creation.
+fn revoked<'a>(mut revocations: impl Iterator<Item = &'a Signature>, at: i64) -> bool {
+    revocations.any(|r| match r.revocation_reason_code() {
+        Some(RevocationCode::KeySuperseded) | Some(RevocationCode::KeyRetired) => {
+            created(r).map_or(true, |revoked| revoked <= at)
+        }
+        _ => true,
+    })
+}
+
+/// Returns true if `armored` is a detached signature on `message` by a key of
+/// `cert` that was fit to sign when the signature was made. That holds for the
+/// primary key or a subkey whose most recent self-signature gives it the
+/// signing flag, that existed and had not expired at that time, and that no
+/// revocation reaches (see `revoked`). A subkey also needs the primary key to
+/// be valid then. Back-signatures by signing subkeys are not checked.
+pub fn verify_detached(cert: &SignedPublicKey, message: &[u8], armored: &str) -> bool {
+    let signature = match StandaloneSignature::from_armor_single(Cursor::new(armored.as_bytes())) {
+        Ok((signature, _)) => signature.signature,
+        Err(_) => return false,
+    };
+    let at = match created(&signature) {
+        Some(at) => at,
+        None => return false,
+    };
+
+    let primary = &cert.primary_key;
+    let primary_created = primary.created_at().timestamp();
+    let self_signature = latest(cert.details.users.iter().flat_map(|user| {
+        user.signatures.iter().filter(move |s| {
+            s.is_certificate()
+                && s.typ() != SignatureType::CertRevocation
+                && s.verify_certificate(primary, Tag::UserId, &user.id).is_ok()
+        })
+    }));
+    let self_signature = match self_signature {
+        Some(s) => s,
+        None => return false,
+    };
+    let revocations = cert
+        .details
+        .revocation_signatures
+        .iter()
+        .filter(|r| r.verify_key(primary).is_ok());
+    if at < primary_created
+        || expired(primary_created, self_signature, at)
+        || revoked(revocations, at)
+    {
+        return false;
+    }
+    if self_signature.key_flags().sign() && signature.verify(primary, message).is_ok() {
+        return true;
+    }
+
+    cert.public_subkeys.iter().any(|subkey| {
+        let key_created = subkey.key.created_at().timestamp();
+        let bound = |typ| {
+            subkey.signatures.iter().filter(move |s| {
+                s.typ() == typ && s.verify_key_binding(primary, &subkey.key).is_ok()
+            })
+        };
+        let binding = match latest(bound(SignatureType::SubkeyBinding)) {
+            Some(binding) => binding,
+            None => return false,
+        };
+        binding.key_flags().sign()
+            && at >= key_created
+            && !expired(key_created, binding, at)
+            && !revoked(bound(SignatureType::SubkeyRevocation), at)
+            && signature.verify(&subkey.key, message).is_ok()
+    })
+}
+
+/// The certificate a participant must sign with: the one in their manifest
+/// entry if it holds one, else the one their id string names, looked up in
+/// `keyring`. Returns `None` if the participant does not sign with OpenPGP.
+pub fn participant_certificate(
+    id: &[u8],
+    manifest_key: Option<&str>,
+    keyring: &Keyring,
+) -> std::result::Result<Option<SignedPublicKey>, Rejection> {
+    let named = named_fingerprint(id).map_err(|_| Rejection::BadSignature)?;
+    let cert = match manifest_key {
+        Some(key) if key.trim_start().starts_with(ARMORED_CERTIFICATE) => {
+            parse_certificate(key).map_err(|_| Rejection::BadSignature)?
+        }
+        _ => match &named {
+            None => return Ok(None),
+            // a certificate we do not have cannot vouch for the message
+            Some(fingerprint) => keyring
+                .find(fingerprint)
+                .cloned()
+                .ok_or(Rejection::BadSignature)?,
+        },
+    };
+    match named {
+        Some(fingerprint) if fingerprint != cert.fingerprint() => Err(Rejection::BadSignature),
+        _ => Ok(Some(cert)),
+    }
+}
+
+/// Checks the detached signature next to the message at `path`, if the
+/// participant signs with OpenPGP (see `participant_certificate`).
+pub fn check_detached_signature(
+    path: &Path,
+    id: &[u8],
+    manifest_key: Option<&str>,
+    keyring: &Keyring,
+) -> std::result::Result<(), Rejection> {
+    let cert = match participant_certificate(id, manifest_key, keyring)? {
+        None => return Ok(()),
+        Some(cert) => cert,
+    };
+    let message = std::fs::read(path).map_err(|e| Rejection::Malformed(e.to_string()))?;
+    let signature = std::fs::read_to_string(detached_signature_path(path))
+        .map_err(|_| Rejection::BadSignature)?;
+    if verify_detached(&cert, &message, &signature) {
+        Ok(())
+    } else {
+        Err(Rejection::BadSignature)
+    }
+}
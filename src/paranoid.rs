@@ -0,0 +1,188 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines an exhaustive consistency check that does not rely on the
+//! random-linear-combination argument of consistencycheck.pdf.
+//!
+//! Every relation between the parameters is checked with its own pairing
+//! equation:
+//! * e(g1^{alpha^i}, g2^alpha) = e(g1^{alpha^{i+1}}, g2) for each adjacent pair of stored powers,
+//! * e(g1^{alpha^N}, g2^{alpha^2}) = e(g1^{alpha^{N+2}}, g2) across the missing power N+1,
+//! * e(g1^{alpha^i}, g2) = e(g1, g2^{alpha^i}) for every stored power i, and
+//! * e(g1^{alpha^N}, g2^alpha) = e(g1, g2)^{alpha^{N+1}}.
+//!
+//! Each equation costs a two-term multi-Miller loop and a final
+//! exponentiation, so this is far slower than `consistent`, but it is
+//! deterministic and shares no code with it, which makes it a reference to
+//! check `consistent` against. The equations are spread over all available
+//! threads.
+use crate::error::{Error, Result};
+use crate::progress::{Hooks, Phase};
+use crate::PointproofsParams;
+use ff::Field;
+use pairing_plus::bls12_381::{Bls12, Fq12, G1Affine, G2Affine, G2Prepared};
+use pairing_plus::{CurveAffine, Engine};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// One pairing equation; i is the exponent of alpha
+#[derive(Clone, Copy)]
+enum Equation {
+    // e(g1^{alpha^i}, g2^alpha) = e(g1^{alpha^{i+1}}, g2)
+    Adjacent(usize),
+    // e(g1^{alpha^N}, g2^{alpha^2}) = e(g1^{alpha^{N+2}}, g2)
+    Jump,
+    // e(g1^{alpha^i}, g2) = e(g1, g2^{alpha^i})
+    Cross(usize),
+    // e(g1^{alpha^N}, g2^alpha) = gt_alpha_nplus1
+    Gt,
+}
+
+// The G2 points that appear in many equations, prepared once
+struct Prepared {
+    g2: G2Prepared,
+    g2_alpha: G2Prepared,
+    g2_alpha2: Option<G2Prepared>,
+}
+
+fn g1_power(params: &PointproofsParams, i: usize) -> G1Affine {
+    if i <= params.n {
+        params.g1_alpha_1_to_n[i - 1]
+    } else {
+        params.g1_alpha_nplus2_to_2n[i - params.n - 2]
+    }
+}
+
+fn g2_power(params: &PointproofsParams, i: usize) -> G2Affine {
+    if i <= params.n {
+        params.g2_alpha_1_to_n[i - 1]
+    } else {
+        params.g2_alpha_nplus2_to_2n[i - params.n - 2]
+    }
+}
+
+// Returns true if e(a, b) = e(c, d)
+fn pairings_equal(a: G1Affine, b: &G2Prepared, c: G1Affine, d: &G2Prepared) -> bool {
+    let mut c = c;
+    c.negate();
+    let (a, c) = (a.prepare(), c.prepare());
+    Bls12::final_exponentiation(&Bls12::miller_loop([(&a, b), (&c, d)].iter())) == Some(Fq12::one())
+}
+
+impl Equation {
+    fn holds(self, params: &PointproofsParams, prepared: &Prepared) -> bool {
+        let n = params.n;
+        match self {
+            Equation::Adjacent(i) => pairings_equal(
+                g1_power(params, i),
+                &prepared.g2_alpha,
+                g1_power(params, i + 1),
+                &prepared.g2,
+            ),
+            Equation::Jump => match &prepared.g2_alpha2 {
+                Some(g2_alpha2) => pairings_equal(
+                    g1_power(params, n),
+                    g2_alpha2,
+                    g1_power(params, n + 2),
+                    &prepared.g2,
+                ),
+                None => false,
+            },
+            Equation::Cross(i) => pairings_equal(
+                g1_power(params, i),
+                &prepared.g2,
+                G1Affine::one(),
+                &g2_power(params, i).prepare(),
+            ),
+            Equation::Gt => {
+                let g1 = g1_power(params, n).prepare();
+                Bls12::final_exponentiation(&Bls12::miller_loop([(&g1, &prepared.g2_alpha)].iter()))
+                    == Some(params.gt_alpha_nplus1)
+            }
+        }
+    }
+}
+
+/// Checks that a set of parameters are in the correct form
+/// (g2^alpha, g2^alpha^2, etc.) for some alpha, one equation at a time.
+pub fn paranoid_consistent(params: &PointproofsParams) -> bool {
+    // without a cancel token, the check cannot fail
+    paranoid_consistent_with_hooks(params, &Hooks::default()).unwrap_or(false)
+}
+
+/// Like `paranoid_consistent`, but reports progress and can be cancelled through `hooks`.
+pub fn paranoid_consistent_with_hooks(params: &PointproofsParams, hooks: &Hooks) -> Result<bool> {
+    let n = params.n;
+    if n == 0
+        || params.g1_alpha_1_to_n.len() != n
+        || params.g2_alpha_1_to_n.len() != n
+        || params.g1_alpha_nplus2_to_2n.len() != n - 1
+        || params.g2_alpha_nplus2_to_2n.len() != n - 1
+    {
+        return Ok(false);
+    }
+
+    // All points must be nonzero and not the generator; in particular alpha is not 0 or 1
+    let powers = (1..=2 * n).filter(|&i| i != n + 1);
+    if powers.clone().any(|i| {
+        let (p, q) = (g1_power(params, i), g2_power(params, i));
+        p.is_zero() || p == G1Affine::one() || q.is_zero() || q == G2Affine::one()
+    }) {
+        return Ok(false);
+    }
+
+    let mut equations: Vec<Equation> = (1..2 * n)
+        .filter(|&i| i != n && i != n + 1)
+        .map(Equation::Adjacent)
+        .collect();
+    if n >= 2 {
+        equations.push(Equation::Jump);
+    }
+    equations.extend(powers.map(Equation::Cross));
+    equations.push(Equation::Gt);
+
+    let prepared = Prepared {
+        g2: G2Affine::one().prepare(),
+        g2_alpha: params.g2_alpha_1_to_n[0].prepare(),
+        g2_alpha2: if n >= 2 {
+            Some(params.g2_alpha_1_to_n[1].prepare())
+        } else {
+            None
+        },
+    };
+
+    let threads = std::thread::available_parallelism()
+        .map(|t| t.get())
+        .unwrap_or(1);
+    let chunk = (equations.len() + threads - 1) / threads;
+    let total = equations.len();
+    let done = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let cancelled = AtomicBool::new(false);
+    hooks.step(Phase::Check, 0, total)?;
+    std::thread::scope(|s| {
+        for part in equations.chunks(chunk) {
+            let (prepared, done, failed, cancelled) = (&prepared, &done, &failed, &cancelled);
+            s.spawn(move || {
+                for eq in part {
+                    if failed.load(Ordering::Relaxed) || cancelled.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if !eq.holds(params, prepared) {
+                        failed.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    if let Err(Error::Cancelled) = hooks.step(Phase::Check, done, total) {
+                        cancelled.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    if failed.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(Error::Cancelled);
+    }
+    Ok(true)
+}
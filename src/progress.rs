@@ -0,0 +1,79 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the hooks through which long-running operations report their
+//! progress and can be cancelled.
+//!
+//! Operations that take hooks call them between units of work: once per
+//! power of alpha when generating or rerandomizing, once per
+//! multi-exponentiation or pairing step of the probabilistic consistency
+//! check, and once per equation of the paranoid one. A cancelled operation
+//! stops at the next such point and returns `Error::Cancelled`.
+use crate::error::{Error, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The long-running operations that report progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Generate,
+    Rerandomize,
+    Check,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Generate => write!(f, "Generating"),
+            Phase::Rerandomize => write!(f, "Randomizing"),
+            Phase::Check => write!(f, "Checking"),
+        }
+    }
+}
+
+/// Receives progress updates. Updates may come from several threads at once.
+pub trait Progress: Sync {
+    /// `done` out of `total` units of `phase` are finished.
+    fn update(&self, phase: Phase, done: usize, total: usize);
+}
+
+/// Lets one thread ask an operation running in another to stop.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The optional progress observer and cancellation token of an operation.
+#[derive(Clone, Copy, Default)]
+pub struct Hooks<'a> {
+    pub progress: Option<&'a dyn Progress>,
+    pub cancel: Option<&'a CancelToken>,
+}
+
+impl<'a> Hooks<'a> {
+    /// Reports progress, or returns `Error::Cancelled` if the operation should stop.
+    pub(crate) fn step(&self, phase: Phase, done: usize, total: usize) -> Result<()> {
+        if let Some(cancel) = self.cancel {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+        }
+        if let Some(progress) = self.progress {
+            progress.update(phase, done, total);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines contribution receipts: a few hundred bytes of text that
+//! describe a contribution without the parameters themselves, so that they
+//! can be posted anywhere while the parameter files are stored elsewhere.
+//!
+//! A receipt looks like this:
+//! ```text
+//! pointproofs-paramgen receipt 1
+//! ceremony: <ceremony id>
+//! index: <the contributor's position in the ceremony>
+//! id: <the contributor's id string>
+//! input: <params_digest of the parameters the contribution started from>
+//! output: <params_digest of the new parameters>
+//! g2_alpha: <the new g2^alpha>
+//! proof_mode: schnorr|pairing
+//! g1_delta: <the commitment to the mixed-in exponent; g2_delta in pairing mode>
+//! proof: <the compressed proof>
+//! ```
+//! Digests, points and the proof are lowercase hex, and points are compressed.
+//! For mailing lists, a receipt can also be armored (see `armor`); `parse`
+//! accepts either form.
+use crate::armor::{ArmorKind, Armored};
+use crate::digest::{from_hex, params_digest, to_hex, Digest};
+use crate::encoding::read_point;
+use crate::error::{Error, Result};
+use crate::format::ProofMode;
+use crate::{ContributionProof, PointproofsParams, Rejection};
+use pairing_plus::bls12_381::G2Affine;
+use pairing_plus::{CurveAffine, EncodedPoint};
+use std::fmt;
+use std::path::Path;
+
+/// First line of a receipt in the current format.
+pub const RECEIPT_HEADER: &str = "pointproofs-paramgen receipt 1";
+
+pub struct Receipt {
+    pub ceremony: String,
+    pub index: usize,
+    pub id: String,
+    pub input_digest: Digest,
+    pub output_digest: Digest,
+    pub g2_alpha: G2Affine,
+    pub proof_mode: ProofMode,
+    /// The compressed serialization of the proof.
+    pub proof: Vec<u8>,
+}
+
+impl Receipt {
+    /// The receipt for rerandomizing `old` into `new` with `proof`.
+    pub fn new(
+        ceremony: &str,
+        index: usize,
+        id: &str,
+        old: &PointproofsParams,
+        new: &PointproofsParams,
+        proof: &ContributionProof,
+    ) -> Result<Self> {
+        if ceremony.contains('\n') || id.contains('\n') {
+            return Err(Error::InvalidInput(
+                "The ceremony id and id string must fit on one line".to_string(),
+            ));
+        }
+        let mut proof_bytes = vec![];
+        proof.serialize(&mut proof_bytes, true)?;
+        Ok(Receipt {
+            ceremony: ceremony.to_string(),
+            index,
+            id: id.to_string(),
+            input_digest: params_digest(old),
+            output_digest: params_digest(new),
+            g2_alpha: new.g2_alpha_1_to_n[0],
+            proof_mode: proof.mode(),
+            proof: proof_bytes,
+        })
+    }
+
+    /// Decodes the proof the receipt carries.
+    pub fn decode_proof(&self) -> Result<ContributionProof> {
+        ContributionProof::deserialize(&mut &self.proof[..], self.proof_mode, true)
+            .map_err(|e| Error::Format(format!("Bad proof in receipt: {}", e)))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        if !text.starts_with(RECEIPT_HEADER) && text.contains("-----BEGIN ") {
+            let armored = Armored::parse_kind(text, ArmorKind::Receipt)?;
+            let text = String::from_utf8(armored.data).map_err(|_| bad("not text"))?;
+            return Receipt::parse(&text);
+        }
+        let mut lines = text.lines().map(|l| l.trim_end_matches('\r'));
+        if lines.next() != Some(RECEIPT_HEADER) {
+            return Err(bad("not a receipt, or an unsupported version"));
+        }
+        let mut field = |name: &str| -> Result<String> {
+            match lines.next().and_then(|l| l.strip_prefix(name)) {
+                Some(value) if value.starts_with(": ") => Ok(value[2..].to_string()),
+                _ => Err(bad(&format!("expected the {} field", name))),
+            }
+        };
+        let ceremony = field("ceremony")?;
+        let index = field("index")?
+            .parse::<usize>()
+            .map_err(|_| bad("the index is not a number"))?;
+        let id = field("id")?;
+        let input_digest = digest_from_hex(&field("input")?)?;
+        let output_digest = digest_from_hex(&field("output")?)?;
+        let g2_alpha = point_from_hex::<G2Affine>(&field("g2_alpha")?)?;
+        let proof_mode = match field("proof_mode")?.as_str() {
+            "schnorr" => ProofMode::Schnorr,
+            "pairing" => ProofMode::Pairing,
+            _ => return Err(bad("unknown proof mode")),
+        };
+        let delta = match proof_mode {
+            ProofMode::Schnorr => field("g1_delta")?,
+            ProofMode::Pairing => field("g2_delta")?,
+        };
+        let proof = from_hex(&field("proof")?).ok_or_else(|| bad("the proof is not hex"))?;
+        let receipt = Receipt {
+            ceremony,
+            index,
+            id,
+            input_digest,
+            output_digest,
+            g2_alpha,
+            proof_mode,
+            proof,
+        };
+        // the commitment is there for people reading the receipt; it must
+        // agree with the proof that is actually checked
+        if delta_line(&receipt.decode_proof()?).1 != delta {
+            return Err(bad("the commitment does not match the proof"));
+        }
+        Ok(receipt)
+    }
+
+    /// The receipt in armored form.
+    pub fn to_armored(&self) -> Armored {
+        Armored::new(ArmorKind::Receipt, self.to_string().into_bytes())
+            .with_header("Ceremony", &self.ceremony)
+            .with_header("Index", &self.index.to_string())
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Receipt::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Checks that the receipt describes rerandomizing `old` into `new` with `proof`.
+    pub fn check_files(
+        &self,
+        old: &PointproofsParams,
+        new: &PointproofsParams,
+        proof: &ContributionProof,
+    ) -> std::result::Result<(), Rejection> {
+        let mut proof_bytes = vec![];
+        proof
+            .serialize(&mut proof_bytes, true)
+            .map_err(|e| Rejection::Malformed(e.to_string()))?;
+        if params_digest(old) != self.input_digest {
+            Err(mismatch("the old parameters"))
+        } else if params_digest(new) != self.output_digest {
+            Err(mismatch("the new parameters"))
+        } else if new.g2_alpha_1_to_n[0] != self.g2_alpha {
+            Err(mismatch("g2_alpha"))
+        } else if proof.mode() != self.proof_mode || proof_bytes != self.proof {
+            Err(mismatch("the proof"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for Receipt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", RECEIPT_HEADER)?;
+        writeln!(f, "ceremony: {}", self.ceremony)?;
+        writeln!(f, "index: {}", self.index)?;
+        writeln!(f, "id: {}", self.id)?;
+        writeln!(f, "input: {}", to_hex(&self.input_digest))?;
+        writeln!(f, "output: {}", to_hex(&self.output_digest))?;
+        writeln!(f, "g2_alpha: {}", point_hex(&self.g2_alpha))?;
+        writeln!(f, "proof_mode: {}", self.proof_mode)?;
+        // receipts are built from and parsed into proofs that decode
+        if let Ok(proof) = self.decode_proof() {
+            let (name, delta) = delta_line(&proof);
+            writeln!(f, "{}: {}", name, delta)?;
+        }
+        writeln!(f, "proof: {}", to_hex(&self.proof))
+    }
+}
+
+// The name and value of the line that shows the commitment to delta
+fn delta_line(proof: &ContributionProof) -> (&'static str, String) {
+    match proof {
+        ContributionProof::Schnorr(pok) => ("g1_delta", point_hex(&pok.g1x())),
+        ContributionProof::Pairing(proof) => ("g2_delta", point_hex(&proof.g2_delta())),
+    }
+}
+
+fn point_hex<G: CurveAffine>(p: &G) -> String {
+    to_hex(G::Compressed::from_affine(*p).as_ref())
+}
+
+fn point_from_hex<G: CurveAffine>(hex: &str) -> Result<G> {
+    let bytes = from_hex(hex).ok_or_else(|| bad("a point is not hex"))?;
+    read_point(&mut &bytes[..], true).map_err(|_| bad("a point is not a valid compressed point"))
+}
+
+fn digest_from_hex(hex: &str) -> Result<Digest> {
+    match from_hex(hex) {
+        Some(bytes) if bytes.len() == 64 => {
+            let mut digest = [0u8; 64];
+            digest.copy_from_slice(&bytes);
+            Ok(digest)
+        }
+        _ => Err(bad("a digest is not 64 bytes of hex")),
+    }
+}
+
+fn bad(what: &str) -> Error {
+    Error::Format(format!("Bad receipt: {}", what))
+}
+
+fn mismatch(what: &str) -> Rejection {
+    Rejection::ReceiptMismatch(format!("the receipt does not describe {}", what))
+}
@@ -1,6 +1,7 @@
 extern crate ff_zeroize as ff;
 extern crate zeroize;
 
+use crate::encoding::{read_point, read_scalar, write_point, write_scalar};
 use crate::hash_to_field_pointproofs::*;
 use crate::random_scalar;
 use ff::Field;
@@ -8,7 +9,7 @@ use pairing_plus::bls12_381::{Fr, G1Affine, G1};
 use pairing_plus::serdes::SerDes;
 use pairing_plus::{CurveAffine, CurveProjective, SubgroupCheck};
 use std::convert::TryInto;
-use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::io::{Read, Result, Write};
 use zeroize::Zeroize;
 
 pub struct PoK {
@@ -17,6 +18,13 @@ pub struct PoK {
     pub(crate) s: Fr,
 }
 
+impl PoK {
+    /// g_1^x, the commitment to the exponent this proves knowledge of.
+    pub fn g1x(&self) -> G1Affine {
+        self.g1x
+    }
+}
+
 // Make a schnorr proof-of-knowledge of a scalar x.
 // id is an arbitrary bytestring that gets hashed into the challenge
 // For the parameter generation protocol, each party must have a distinct id
@@ -93,27 +101,15 @@ pub fn verify_pok(pok: &PoK, id: &[u8]) -> bool {
 
 impl SerDes for PoK {
     fn deserialize<R: Read>(r: &mut R, compressed: bool) -> Result<Self> {
-        if !compressed {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "PoK can only be (de)serialized with compressed=true",
-            ));
-        }
-        let g1x = G1Affine::deserialize(r, true)?;
-        let a = G1Affine::deserialize(r, true)?;
-        let s: Fr = Fr::deserialize(r, true)?;
+        let g1x = read_point(r, compressed)?;
+        let a = read_point(r, compressed)?;
+        let s: Fr = read_scalar(r)?;
         Ok(PoK { g1x, a, s })
     }
     fn serialize<W: Write>(&self, w: &mut W, compressed: bool) -> Result<()> {
-        if !compressed {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "PoK can only be (de)serialized with compressed=true",
-            ));
-        }
-        self.g1x.serialize(w, true)?;
-        self.a.serialize(w, true)?;
-        self.s.serialize(w, true)?;
+        write_point(w, &self.g1x, compressed)?;
+        write_point(w, &self.a, compressed)?;
+        write_scalar(w, &self.s)?;
         Ok(())
     }
 }
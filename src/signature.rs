@@ -0,0 +1,216 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the Ed25519 signatures that contributors attach to their messages.
+//!
+//! usage.md has every participant register an id string that contains or
+//! points to their signing key. An id string whose last word is
+//! `ed25519:<hex public key>` embeds the key itself, so that the tool can
+//! sign and check messages without an external tool. The signature covers
+//! the SHA-512 digest of the contribution file up to the signature, and
+//! follows the proof as a trailer:
+//! ```text
+//! "PPSG" || 64-byte Ed25519 signature
+//! ```
+//! Files without the trailer read exactly as before, and messages from id
+//! strings without an embedded key do not need one.
+use crate::digest::{from_hex, to_hex, Digest};
+use crate::error::{Error, Result};
+use crate::Rejection;
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, SECRET_KEY_LENGTH};
+use rand::rngs::OsRng;
+use sha2::{Digest as _, Sha512};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use zeroize::Zeroize;
+
+/// Marks the start of a signature trailer.
+pub const SIGNATURE_MAGIC: [u8; 4] = *b"PPSG";
+
+/// Prefix of the last word of an id string that embeds a public key.
+pub const KEY_PREFIX: &str = "ed25519:";
+
+// Prepended to the digest before signing, so that a signature on a
+// contribution cannot be passed off as a signature on anything else
+const DOMAIN: &[u8] = b"pointproofs-paramgen contribution signature v1";
+
+/// A contributor's Ed25519 key pair.
+pub struct SigningKey {
+    keypair: Keypair,
+}
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        SigningKey {
+            keypair: Keypair::generate(&mut OsRng),
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.keypair.public
+    }
+
+    /// The id string to register: `name` followed by the embedded public key.
+    pub fn id_string(&self, name: &str) -> Result<String> {
+        if name.is_empty() || name.contains(['\t', '\n', '\r']) {
+            return Err(Error::InvalidInput(
+                "The name must be non-empty and contain no tabs or line breaks".to_string(),
+            ));
+        }
+        Ok(format!(
+            "{} {}{}",
+            name,
+            KEY_PREFIX,
+            to_hex(self.keypair.public.as_bytes())
+        ))
+    }
+
+    /// Writes the secret key as a line of hex.
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        let mut line = to_hex(self.keypair.secret.as_bytes());
+        line.push('\n');
+        let result = w.write_all(line.as_bytes());
+        line.zeroize();
+        Ok(result?)
+    }
+
+    /// Reads a key written by `write`.
+    pub fn read<R: Read>(r: &mut R) -> Result<Self> {
+        let mut text = String::new();
+        let read = r.read_to_string(&mut text);
+        let mut bytes = from_hex(text.trim()).unwrap_or_default();
+        text.zeroize();
+        read?;
+        let secret = if bytes.len() == SECRET_KEY_LENGTH {
+            SecretKey::from_bytes(&bytes).ok()
+        } else {
+            None
+        };
+        bytes.zeroize();
+        match secret {
+            Some(secret) => {
+                let public = PublicKey::from(&secret);
+                Ok(SigningKey {
+                    keypair: Keypair { secret, public },
+                })
+            }
+            None => Err(Error::Format("Malformed signing key".to_string())),
+        }
+    }
+
+    /// Signs the digest of a contribution file.
+    pub fn sign(&self, digest: &Digest) -> [u8; 64] {
+        self.keypair.sign(&signed_message(digest)).to_bytes()
+    }
+}
+
+// What is actually signed for a contribution with this digest
+fn signed_message(digest: &Digest) -> Vec<u8> {
+    let mut message = DOMAIN.to_vec();
+    message.extend_from_slice(digest);
+    message
+}
+
+/// The public key embedded in an id string, if it has one.
+/// Fails if the id string ends in something that looks like a key but is not.
+pub fn embedded_key(id: &[u8]) -> Result<Option<PublicKey>> {
+    let id = match std::str::from_utf8(id) {
+        Ok(id) => id,
+        Err(_) => return Ok(None),
+    };
+    let hex = match id
+        .rsplit(' ')
+        .next()
+        .and_then(|w| w.strip_prefix(KEY_PREFIX))
+    {
+        Some(hex) => hex,
+        None => return Ok(None),
+    };
+    match from_hex(hex).and_then(|bytes| PublicKey::from_bytes(&bytes).ok()) {
+        Some(key) => Ok(Some(key)),
+        None => Err(Error::Format(
+            "The id string embeds a malformed Ed25519 key".to_string(),
+        )),
+    }
+}
+
+/// The signature trailer of a contribution file, along with the digest of
+/// the bytes before it.
+pub struct ContributionSignature {
+    pub digest: Digest,
+    pub signature: [u8; 64],
+}
+
+impl ContributionSignature {
+    pub fn verify(&self, key: &PublicKey) -> bool {
+        match Signature::try_from(&self.signature[..]) {
+            Ok(signature) => key
+                .verify_strict(&signed_message(&self.digest), &signature)
+                .is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Checks the signature on a message from `id`. A message from an id string
+/// that embeds a key must be signed with that key; other messages are not
+/// checked here, since their signatures are checked by an external tool.
+pub fn check_signature(
+    id: &[u8],
+    signature: Option<&ContributionSignature>,
+) -> std::result::Result<(), Rejection> {
+    match (embedded_key(id), signature) {
+        (Ok(None), _) => Ok(()),
+        (Ok(Some(key)), Some(signature)) if signature.verify(&key) => Ok(()),
+        _ => Err(Rejection::BadSignature),
+    }
+}
+
+pub(crate) fn write_trailer<W: Write>(w: &mut W, signature: &[u8; 64]) -> Result<()> {
+    w.write_all(&SIGNATURE_MAGIC)?;
+    w.write_all(signature)?;
+    Ok(())
+}
+
+/// Reads a signature trailer, or returns `None` at the end of the file.
+pub(crate) fn read_trailer<R: Read>(r: &mut R) -> Result<Option<[u8; 64]>> {
+    let mut magic = [0u8; 4];
+    if r.read(&mut magic[..1])? == 0 {
+        return Ok(None);
+    }
+    r.read_exact(&mut magic[1..])?;
+    if magic != SIGNATURE_MAGIC {
+        return Err(Error::Format("Unexpected data after the proof".to_string()));
+    }
+    let mut signature = [0u8; 64];
+    r.read_exact(&mut signature)?;
+    Ok(Some(signature))
+}
+
+/// Passes reads through, hashing everything read.
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: Sha512,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha512::new(),
+        }
+    }
+
+    /// The digest of everything read so far.
+    pub(crate) fn finish(self) -> Digest {
+        let mut out = [0u8; 64];
+        out.copy_from_slice(&self.hasher.result());
+        out
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.input(&buf[..read]);
+        Ok(read)
+    }
+}
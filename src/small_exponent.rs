@@ -0,0 +1,131 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines a lookup table of g^{±k} for small k, used to spot exponents
+//! that anyone could guess, and a baby-step/giant-step search built on it.
+use pairing_plus::bls12_381::{G1Affine, G2Affine};
+use pairing_plus::{CurveAffine, CurveProjective, EncodedPoint};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Exponents up to this bound (and their negatives) are considered degenerate
+/// when mixed in by a contributor.
+pub const DEGENERATE_EXPONENT_BOUND: u64 = 1 << 16;
+
+// The tables of degenerate exponents, built the first time they are needed
+static DEGENERATE_G1: Mutex<Option<Arc<SmallExponentTable<G1Affine>>>> = Mutex::new(None);
+static DEGENERATE_G2: Mutex<Option<Arc<SmallExponentTable<G2Affine>>>> = Mutex::new(None);
+
+fn shared_table<G: CurveAffine>(
+    cell: &Mutex<Option<Arc<SmallExponentTable<G>>>>,
+) -> Arc<SmallExponentTable<G>> {
+    // a panic while building leaves no table behind, so the poison can be ignored
+    let mut table = cell.lock().unwrap_or_else(|e| e.into_inner());
+    table
+        .get_or_insert_with(|| Arc::new(SmallExponentTable::new(DEGENERATE_EXPONENT_BOUND)))
+        .clone()
+}
+
+/// Returns k if p = g1^{±k} for some k <= DEGENERATE_EXPONENT_BOUND.
+pub fn degenerate_exponent_g1(p: &G1Affine) -> Option<u64> {
+    shared_table(&DEGENERATE_G1).lookup(p)
+}
+
+/// Returns k if p = g2^{±k} for some k <= DEGENERATE_EXPONENT_BOUND.
+pub fn degenerate_exponent_g2(p: &G2Affine) -> Option<u64> {
+    shared_table(&DEGENERATE_G2).lookup(p)
+}
+
+/// A table of b^1, ..., b^bound for a base b of `G` (by default the generator).
+/// Points are keyed by their compressed encoding with the sign bit cleared,
+/// so P and -P share a key and one table covers both k and -k.
+pub struct SmallExponentTable<G: CurveAffine> {
+    // maps a key to k and to the sign bit of b^k
+    table: HashMap<Vec<u8>, (u64, bool)>,
+    bound: u64,
+    // b^bound, the distance between two giant steps
+    giant_step: G::Projective,
+}
+
+// Compressed encoding of p with the sign-of-y flag cleared, and the flag itself
+fn unsigned_key<G: CurveAffine>(p: &G) -> (Vec<u8>, bool) {
+    let mut key = G::Compressed::from_affine(*p).as_ref().to_vec();
+    let sign = key[0] & 0x20 != 0;
+    key[0] &= !0x20;
+    (key, sign)
+}
+
+impl<G: CurveAffine> SmallExponentTable<G> {
+    /// Builds the table of g^k for 1 <= k <= bound, where g is the generator.
+    pub fn new(bound: u64) -> Self {
+        SmallExponentTable::with_base(G::one(), bound)
+    }
+
+    /// Builds the table of base^k for 1 <= k <= bound.
+    pub fn with_base(base: G, bound: u64) -> Self {
+        let mut pts: Vec<G::Projective> = Vec::with_capacity(bound as usize);
+        let mut acc = G::Projective::zero();
+        for _ in 0..bound {
+            acc.add_assign_mixed(&base);
+            pts.push(acc);
+        }
+        G::Projective::batch_normalization(&mut pts);
+
+        let mut table = HashMap::with_capacity(bound as usize);
+        for (i, pt) in pts.iter().enumerate() {
+            let (key, sign) = unsigned_key(&pt.into_affine());
+            table.insert(key, (i as u64 + 1, sign));
+        }
+        SmallExponentTable {
+            table,
+            bound,
+            giant_step: acc,
+        }
+    }
+
+    /// The largest exponent in the table.
+    pub fn bound(&self) -> u64 {
+        self.bound
+    }
+
+    /// Returns k if p = b^k or p = b^{-k} for some 0 <= k <= bound.
+    pub fn lookup(&self, p: &G) -> Option<u64> {
+        self.lookup_signed(p).map(|k| k.unsigned_abs() as u64)
+    }
+
+    /// Returns k if p = b^k for some -bound <= k <= bound.
+    pub fn lookup_signed(&self, p: &G) -> Option<i128> {
+        if p.is_zero() {
+            return Some(0);
+        }
+        let (key, sign) = unsigned_key(p);
+        self.table.get(&key).map(|&(k, table_sign)| {
+            if sign == table_sign {
+                k as i128
+            } else {
+                -(k as i128)
+            }
+        })
+    }
+
+    /// Baby-step/giant-step search for k with p = b^k and
+    /// |k| <= bound * (giant_steps + 1).
+    /// This costs up to 2 * (giant_steps + 1) lookups.
+    pub fn find_exponent(&self, p: &G, giant_steps: u64) -> Option<i128> {
+        let mut neg_step = self.giant_step;
+        neg_step.negate();
+        // each lookup covers k in [-bound, bound], so walking p down in steps of
+        // b^bound covers nonnegative exponents, and walking -p covers negative ones
+        for &sign in &[1i128, -1i128] {
+            let mut q = p.into_projective();
+            if sign < 0 {
+                q.negate();
+            }
+            for i in 0..=giant_steps {
+                if let Some(k) = self.lookup_signed(&q.into_affine()) {
+                    return Some(sign * (i as i128 * self.bound as i128 + k));
+                }
+                q.add_assign(&neg_step);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,135 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines spot checks of a rerandomization against its input.
+//!
+//! A hardware fault during `rerandomize` could corrupt an output point while
+//! leaving the parameters consistent-looking, for instance if it hits the
+//! running power of alpha. Before a contribution is published, a few
+//! randomly chosen output points, the first and the last one, and
+//! e(g1,g2)^{alpha^{N+1}} are therefore recomputed from the input and the
+//! secret through separate code: powers of alpha by square-and-multiply
+//! rather than by a running product, and points by a plain double-and-add
+//! rather than the library's scalar multiplication.
+use crate::error::{Error, Result};
+use crate::PointproofsParams;
+use ff::{Field, PrimeField};
+use pairing_plus::bls12_381::{Fr, G1, G2};
+use pairing_plus::CurveProjective;
+use rand::RngCore;
+use zeroize::Zeroize;
+
+/// Number of randomly chosen powers recomputed by `spot_check`, on top of
+/// the first and the last one.
+pub const SPOT_CHECKS: usize = 16;
+
+/// Number of powers `spot_check` recomputes in parameters of size `n` with
+/// `count` random ones, counting repeats. When n = 1 the last power is the
+/// missing N+1, which is skipped.
+pub fn checked_powers(n: usize, count: usize) -> usize {
+    if n == 1 {
+        count + 1
+    } else {
+        count + 2
+    }
+}
+
+/// Checks that `new` is `old` rerandomized with `alpha` at `count` randomly
+/// chosen powers, the first and last powers, and gt_alpha_nplus1.
+/// Fails with `Error::Fault` on any mismatch.
+pub fn spot_check<R: RngCore>(
+    old: &PointproofsParams,
+    new: &PointproofsParams,
+    alpha: Fr,
+    count: usize,
+    rng: &mut R,
+) -> Result<()> {
+    let n = old.n;
+    if n == 0
+        || new.n != n
+        || new.g1_alpha_1_to_n.len() != n
+        || new.g2_alpha_1_to_n.len() != n
+        || new.g1_alpha_nplus2_to_2n.len() != n - 1
+        || new.g2_alpha_nplus2_to_2n.len() != n - 1
+    {
+        return Err(Error::Fault(
+            "the output does not have the shape of the input".to_string(),
+        ));
+    }
+
+    let mut indices = vec![1, 2 * n];
+    indices.extend((0..count).map(|_| {
+        // a power in 1..=2N other than N+1
+        let i = 1 + (rng.next_u64() % (2 * n as u64 - 1)) as usize;
+        if i <= n {
+            i
+        } else {
+            i + 1
+        }
+    }));
+    for &i in indices.iter() {
+        if i == n + 1 {
+            // only when n = 1, where the last power is 2N = N+1
+            continue;
+        }
+        let mut scalar = power(alpha, i as u64);
+        let (old_g1, old_g2, new_g1, new_g2) = if i <= n {
+            (
+                old.g1_alpha_1_to_n[i - 1],
+                old.g2_alpha_1_to_n[i - 1],
+                new.g1_alpha_1_to_n[i - 1],
+                new.g2_alpha_1_to_n[i - 1],
+            )
+        } else {
+            (
+                old.g1_alpha_nplus2_to_2n[i - n - 2],
+                old.g2_alpha_nplus2_to_2n[i - n - 2],
+                new.g1_alpha_nplus2_to_2n[i - n - 2],
+                new.g2_alpha_nplus2_to_2n[i - n - 2],
+            )
+        };
+        let g1_ok = double_and_add::<G1>(&old_g1, &scalar).into_affine() == new_g1;
+        let g2_ok = double_and_add::<G2>(&old_g2, &scalar).into_affine() == new_g2;
+        scalar.zeroize();
+        if !g1_ok || !g2_ok {
+            return Err(Error::Fault(format!(
+                "power {} of the output does not match its recomputation",
+                i
+            )));
+        }
+    }
+
+    let mut scalar = power(alpha, n as u64 + 1);
+    let gt_ok = old.gt_alpha_nplus1.pow(scalar.into_repr()) == new.gt_alpha_nplus1;
+    scalar.zeroize();
+    if !gt_ok {
+        return Err(Error::Fault(
+            "gt_alpha_nplus1 of the output does not match its recomputation".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// alpha^e by left-to-right square-and-multiply
+fn power(alpha: Fr, e: u64) -> Fr {
+    let mut res = Fr::one();
+    for bit in (0..64).rev() {
+        res.square();
+        if (e >> bit) & 1 == 1 {
+            res.mul_assign(&alpha);
+        }
+    }
+    res
+}
+
+// p^s by left-to-right double-and-add over the bits of s
+fn double_and_add<G: CurveProjective<Scalar = Fr>>(p: &G::Affine, s: &Fr) -> G {
+    let repr = s.into_repr();
+    let limbs: &[u64] = repr.as_ref();
+    let mut res = G::zero();
+    for bit in (0..64 * limbs.len()).rev() {
+        res.double();
+        if (limbs[bit / 64] >> (bit % 64)) & 1 == 1 {
+            res.add_assign_mixed(p);
+        }
+    }
+    res
+}
@@ -0,0 +1,189 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines faster subgroup checks that use the endomorphisms of BLS12-381.
+//!
+//! pairing-plus checks that a point P is in the prime-order subgroup by
+//! computing [r]P, a multiplication by the 255-bit group order. Each group
+//! instead has an efficiently computable endomorphism that acts on the
+//! subgroup as multiplication by a known scalar, and a point on the curve is
+//! in the subgroup exactly when the endomorphism maps it to that multiple.
+//! With z = -0xd201000000010000 the parameter of the curve:
+//! * in G1, sigma(x, y) = (beta * x, y) for a cube root of unity beta, and
+//!   P is in G1 iff sigma(P) = [-z^2]P (Bowe, "Faster subgroup checks for
+//!   BLS12-381", ePrint 2019/814);
+//! * in G2, psi is the untwist-Frobenius-twist map, and P is in G2 iff
+//!   psi(P) = [z]P (Scott, "A note on group membership tests for G1, G2 and
+//!   GT on BLS pairing-friendly curves", ePrint 2021/1130).
+//!
+//! Since z has only six bits set, this replaces the multiplication by r with
+//! one by a sparse 128-bit scalar in G1 and a sparse 64-bit one in G2. Both
+//! tests are exact, unlike a check of random combinations of the points.
+use ff::{Field, PrimeField};
+use pairing_plus::bls12_381::{Fq, Fq2, FqRepr, FrRepr, G1Affine, G2Affine};
+use pairing_plus::{CurveAffine, CurveProjective};
+
+/// How points are subgroup-checked when parameters are loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubgroupChecks {
+    /// Check every point on its own as it is decoded.
+    PerPoint,
+    /// Decode every point, checking only that it is on the curve, then check
+    /// the points of each group with the endomorphism tests, on all available threads.
+    Batched,
+}
+
+// |z|, where z = -0xd201000000010000 is the parameter of BLS12-381
+const Z_ABS: u64 = 0xd201000000010000;
+
+// A primitive cube root of unity in Fq, the one for which sigma(P) = [-z^2]P on G1
+const BETA: [u64; 6] = [
+    0x2e01fffffffefffe,
+    0xde17d813620a0002,
+    0xddb3a93be6f89688,
+    0xba69c6076a0f77ea,
+    0x5f19672fdf76ce51,
+    0x0000000000000000,
+];
+
+// The coefficients of psi(x, y) = (conj(x) * PSI_X, conj(y) * PSI_Y), namely
+// 1 / (u + 1)^((q - 1) / 3) and 1 / (u + 1)^((q - 1) / 2)
+const PSI_X: ([u64; 6], [u64; 6]) = (
+    [0, 0, 0, 0, 0, 0],
+    [
+        0x8bfd00000000aaad,
+        0x409427eb4f49fffd,
+        0x897d29650fb85f9b,
+        0xaa0d857d89759ad4,
+        0xec02408663d4de85,
+        0x1a0111ea397fe699,
+    ],
+);
+const PSI_Y: ([u64; 6], [u64; 6]) = (
+    [
+        0xf1ee7b04121bdea2,
+        0x304466cf3e67fa0a,
+        0xef396489f61eb45e,
+        0x1c3dedd930b1cf60,
+        0xe2e9c448d77a2cd9,
+        0x135203e60180a68e,
+    ],
+    [
+        0xc81084fbede3cc09,
+        0xee67992f72ec05f4,
+        0x77f76e17009241c5,
+        0x48395dabc2d3435e,
+        0x6831e36d6bd17ffe,
+        0x06af0e0437ff400b,
+    ],
+);
+
+fn fq(limbs: [u64; 6]) -> Fq {
+    Fq::from_repr(FqRepr(limbs)).expect("constant is reduced")
+}
+
+fn fq2(limbs: ([u64; 6], [u64; 6])) -> Fq2 {
+    Fq2 {
+        c0: fq(limbs.0),
+        c1: fq(limbs.1),
+    }
+}
+
+/// The checks a point of G1 or G2 needs after it was decoded without any.
+pub trait PointChecks: CurveAffine {
+    /// Returns true if the point satisfies the curve equation.
+    fn is_on_curve(&self) -> bool;
+
+    /// Returns true if the point, which must be on the curve, is in the
+    /// prime-order subgroup. Gives the same answer as the check pairing-plus
+    /// does on decoding, faster.
+    fn in_subgroup_by_endomorphism(&self) -> bool;
+}
+
+impl PointChecks for G1Affine {
+    fn is_on_curve(&self) -> bool {
+        // y^2 = x^3 + 4
+        on_curve(self, fq([4, 0, 0, 0, 0, 0]))
+    }
+
+    fn in_subgroup_by_endomorphism(&self) -> bool {
+        let mut sigma = *self;
+        // as_tuple_mut is unsafe since it can leave the curve; sigma maps the curve to itself
+        unsafe {
+            sigma.as_tuple_mut().0.mul_assign(&fq(BETA));
+        }
+        // -z^2 = -(|z|^2)
+        let mut expected = self
+            .mul(FrRepr([Z_ABS, 0, 0, 0]))
+            .into_affine()
+            .mul(FrRepr([Z_ABS, 0, 0, 0]));
+        expected.negate();
+        sigma.into_projective() == expected
+    }
+}
+
+impl PointChecks for G2Affine {
+    fn is_on_curve(&self) -> bool {
+        // y^2 = x^3 + 4(u + 1)
+        let four = fq([4, 0, 0, 0, 0, 0]);
+        on_curve(self, Fq2 { c0: four, c1: four })
+    }
+
+    fn in_subgroup_by_endomorphism(&self) -> bool {
+        let mut psi = *self;
+        // as_tuple_mut is unsafe since it can leave the curve; psi maps the curve to itself
+        unsafe {
+            let (x, y) = psi.as_tuple_mut();
+            x.frobenius_map(1);
+            x.mul_assign(&fq2(PSI_X));
+            y.frobenius_map(1);
+            y.mul_assign(&fq2(PSI_Y));
+        }
+        // z = -|z|
+        let mut expected = self.mul(FrRepr([Z_ABS, 0, 0, 0]));
+        expected.negate();
+        psi.into_projective() == expected
+    }
+}
+
+fn on_curve<G: CurveAffine>(p: &G, b: G::Base) -> bool {
+    if p.is_zero() {
+        return true;
+    }
+    let (x, y) = p.as_tuple();
+    let mut lhs = *y;
+    lhs.square();
+    let mut rhs = *x;
+    rhs.square();
+    rhs.mul_assign(x);
+    rhs.add_assign(&b);
+    lhs == rhs
+}
+
+/// Returns the index of the first point that is not in the subgroup, if any.
+/// The points must already be known to be on the curve. The points are
+/// split between all available threads.
+pub fn first_not_in_subgroup<G: PointChecks + Sync>(points: &[G]) -> Option<usize> {
+    if points.is_empty() {
+        return None;
+    }
+    let threads = std::thread::available_parallelism()
+        .map(|t| t.get())
+        .unwrap_or(1);
+    let chunk = (points.len() + threads - 1) / threads;
+    std::thread::scope(|s| {
+        let handles: Vec<_> = points
+            .chunks(chunk)
+            .enumerate()
+            .map(|(k, part)| {
+                s.spawn(move || {
+                    part.iter()
+                        .position(|p| !p.in_subgroup_by_endomorphism())
+                        .map(|i| k * chunk + i)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|h| h.join().expect("subgroup check thread panicked"))
+            .next()
+    })
+}
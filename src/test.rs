@@ -65,3 +65,1424 @@ fn test_schnorr_basic() {
     assert!(!ok, "pok verified with bad id string");
     // TODO: more tests
 }
+
+#[test]
+fn test_pairing_update_proof() {
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let init_param = crate::generate(alpha, 16);
+
+    let mut r: [u8; 64] = [0; 64];
+    OsRng {}.fill_bytes(&mut r[..]);
+    let (update_param, proof) = crate::rerandomize_with_mode(
+        &init_param,
+        &r[..],
+        b"hardcoded id string",
+        crate::format::ProofMode::Pairing,
+    )
+    .unwrap();
+    assert!(
+        crate::check_contribution(
+            &update_param,
+            init_param.g2_alpha_1_to_n[0],
+            &proof,
+            b"hardcoded id string"
+        ),
+        "pairing update proof failed"
+    );
+
+    // the proof must not verify against parameters it was not made from
+    let other_param = crate::generate(Fr::from_repr(FrRepr([7, 0, 0, 0])).unwrap(), 16);
+    assert!(!crate::check_contribution(
+        &update_param,
+        other_param.g2_alpha_1_to_n[0],
+        &proof,
+        b"hardcoded id string"
+    ));
+}
+
+#[test]
+fn test_header_roundtrip() {
+    use crate::format::{read_params, write_params, FileHeader, ProofMode};
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+
+    // legacy settings write no header at all
+    let mut legacy = vec![];
+    write_params(&mut legacy, &FileHeader::default(), &t).unwrap();
+    let mut plain = vec![];
+    t.serialize(&mut plain, true).unwrap();
+    assert_eq!(legacy, plain);
+
+    let header = FileHeader {
+        proof_mode: ProofMode::Pairing,
+        include_gt: true,
+        compressed: true,
+    };
+    let mut buf = vec![];
+    write_params(&mut buf, &header, &t).unwrap();
+    let (header2, t2) = read_params(&mut &buf[..]).unwrap();
+    assert_eq!(header, header2);
+    assert_eq!(t, t2);
+
+    // leaving out the GT element saves its 576 bytes, and it is recomputed on load
+    let header = FileHeader {
+        proof_mode: ProofMode::Schnorr,
+        include_gt: false,
+        compressed: true,
+    };
+    let mut compact = vec![];
+    write_params(&mut compact, &header, &t).unwrap();
+    assert_eq!(compact.len() + 576, buf.len());
+    let (header2, t2) = read_params(&mut &compact[..]).unwrap();
+    assert_eq!(header, header2);
+    assert_eq!(t, t2);
+
+    let (header3, t3) = read_params(&mut &legacy[..]).unwrap();
+    assert_eq!(header3, FileHeader::default());
+    assert_eq!(t, t3);
+}
+
+#[test]
+fn test_degenerate_contributions() {
+    use crate::{check_contribution_with_reason, ContributionProof, Rejection};
+    use ff::Field;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let init_param = crate::generate(alpha, 8);
+    let g2alpha = init_param.g2_alpha_1_to_n[0];
+
+    // delta = 1 leaves the parameters unchanged
+    let one = Fr::one();
+    let same = crate::rerandomize_with_scalar(&init_param, one, &Default::default()).unwrap();
+    let proof = ContributionProof::Schnorr(make_pok(one, b"id"));
+    assert_eq!(
+        check_contribution_with_reason(&same, g2alpha, &proof, b"id", &[]),
+        Err(Rejection::DegenerateExponent(1))
+    );
+    if let ContributionProof::Schnorr(pok) = &proof {
+        assert!(!crate::check_rerandomization(&same, g2alpha, pok, b"id"));
+    }
+
+    // delta = -3 is just as easy to guess
+    let mut minus_three = Fr::from_repr(FrRepr([3, 0, 0, 0])).unwrap();
+    minus_three.negate();
+    let params =
+        crate::rerandomize_with_scalar(&init_param, minus_three, &Default::default()).unwrap();
+    let proof = ContributionProof::Pairing(crate::update_proof::make_update_proof(
+        init_param.g1_alpha_1_to_n[0],
+        minus_three,
+    ));
+    assert_eq!(
+        check_contribution_with_reason(&params, g2alpha, &proof, b"id", &[]),
+        Err(Rejection::DegenerateExponent(3))
+    );
+
+    // resubmitting earlier parameters is caught even with a fresh proof
+    let mut r: [u8; 64] = [0; 64];
+    OsRng {}.fill_bytes(&mut r[..]);
+    let (params, proof) = crate::rerandomize(&init_param, &r[..], b"id");
+    let proof = ContributionProof::Schnorr(proof);
+    let earlier = [
+        crate::digest::params_digest(&init_param),
+        crate::digest::params_digest(&params),
+    ];
+    assert_eq!(
+        check_contribution_with_reason(&params, g2alpha, &proof, b"id", &earlier),
+        Err(Rejection::Replayed(1))
+    );
+    assert_eq!(
+        check_contribution_with_reason(&params, g2alpha, &proof, b"id", &earlier[..1]),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_transcript_parse() {
+    use crate::transcript::Transcript;
+    use std::path::Path;
+    let text = "# ceremony transcript\n/tmp/params.initial\n\nparams.1\talice\nBAD\tbob smith\n";
+    let t = Transcript::parse(text, Path::new("/ceremony")).unwrap();
+    assert_eq!(t.initial, Path::new("/tmp/params.initial"));
+    assert_eq!(t.entries.len(), 2);
+    assert_eq!(t.entries[0].id, "alice");
+    assert_eq!(
+        t.entries[0].path.as_deref(),
+        Some(Path::new("/ceremony/params.1"))
+    );
+    assert_eq!(t.entries[1].id, "bob smith");
+    assert!(t.entries[1].path.is_none());
+
+    assert!(Transcript::parse("params.initial\nparams.1\n", Path::new("")).is_err());
+}
+
+#[test]
+fn test_audit_alpha() {
+    use crate::audit::{audit_alpha, AuditOptions, WeakAlpha};
+    use ff::Field;
+    let options = AuditOptions {
+        baby_steps: 256,
+        giant_steps: 256,
+    };
+    let g2 = |alpha: Fr| G2Affine::one().mul(alpha).into_affine();
+
+    let five = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    assert_eq!(
+        audit_alpha(&g2(five), &options),
+        vec![WeakAlpha::SmallExponent(5)]
+    );
+
+    // found by the giant steps, on the negative side
+    let mut big = Fr::from_repr(FrRepr([40000, 0, 0, 0])).unwrap();
+    big.negate();
+    assert_eq!(
+        audit_alpha(&g2(big), &options),
+        vec![WeakAlpha::SmallExponent(-40000)]
+    );
+
+    // the initial parameters are both well known and 1 * init_alpha
+    let found = audit_alpha(&g2(crate::init_alpha()), &options);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[1], WeakAlpha::SmallMultipleOfInit(1));
+
+    assert!(audit_alpha(&g2(random_scalar()), &options).is_empty());
+}
+
+#[test]
+fn test_malformed_gt() {
+    use ff::Field;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let good = crate::generate(alpha, 4);
+    assert!(crate::gt_in_subgroup(&good.gt_alpha_nplus1));
+
+    // a random element of Fq12 is almost surely not in the cyclotomic subgroup
+    let random = Fq12::random(&mut OsRng);
+
+    // raising to (p^6 - 1)(p^2 + 1) lands in the cyclotomic subgroup,
+    // but almost surely not in the subgroup of order r
+    let cyclotomic = {
+        let mut inv = random.inverse().unwrap();
+        let mut f = random;
+        f.frobenius_map(6);
+        f.mul_assign(&inv);
+        inv = f;
+        f.frobenius_map(2);
+        f.mul_assign(&inv);
+        f
+    };
+
+    for bad in [Fq12::zero(), Fq12::one(), random, cyclotomic].iter() {
+        assert!(!crate::gt_in_subgroup(bad));
+
+        let mut params = crate::generate(alpha, 4);
+        params.gt_alpha_nplus1 = *bad;
+        let mut buf = vec![];
+        params.serialize(&mut buf, true).unwrap();
+        let res = crate::PointproofsParams::deserialize(&mut &buf[..], true);
+        assert!(res.is_err(), "accepted a malformed GT element");
+    }
+}
+
+#[test]
+fn test_uncompressed_roundtrip() {
+    use crate::format::{read_contribution, write_contribution, FileHeader, ProofMode};
+    use crate::schnorr::PoK;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+
+    let mut compressed = vec![];
+    t.serialize(&mut compressed, true).unwrap();
+    let mut uncompressed = vec![];
+    t.serialize(&mut uncompressed, false).unwrap();
+    // 4 * 8 - 2 points, each twice as large
+    assert_eq!(uncompressed.len(), compressed.len() + 15 * 48 + 15 * 96);
+    let t2 = crate::PointproofsParams::deserialize(&mut &uncompressed[..], false).unwrap();
+    assert_eq!(t, t2);
+
+    let pok = make_pok(random_scalar(), b"id");
+    let mut buf = vec![];
+    pok.serialize(&mut buf, false).unwrap();
+    assert_eq!(buf.len(), 96 + 96 + 32);
+    let pok2 = PoK::deserialize(&mut &buf[..], false).unwrap();
+    assert!(verify_pok(&pok2, b"id"));
+
+    for &mode in [ProofMode::Schnorr, ProofMode::Pairing].iter() {
+        let header = FileHeader {
+            proof_mode: mode,
+            include_gt: false,
+            compressed: false,
+        };
+        let (params, proof) = crate::rerandomize_with_mode(&t, b"entropy", b"id", mode).unwrap();
+        let mut buf = vec![];
+        write_contribution(&mut buf, &header, &params, &proof).unwrap();
+        let (header2, params2, proof2) = read_contribution(&mut &buf[..]).unwrap();
+        assert_eq!(header, header2);
+        assert_eq!(params, params2);
+        assert!(crate::check_contribution(
+            &params2,
+            t.g2_alpha_1_to_n[0],
+            &proof2,
+            b"id"
+        ));
+    }
+}
+
+#[test]
+fn test_params_view() {
+    use crate::digest::digest_bytes;
+    use crate::format::{write_params, FileHeader};
+    use crate::view::ParamsView;
+    use pairing_plus::EncodedPoint;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let n = 8;
+    let t = crate::generate(alpha, n);
+
+    for &(include_gt, compressed) in [(true, true), (false, false)].iter() {
+        let header = FileHeader {
+            include_gt,
+            compressed,
+            ..FileHeader::default()
+        };
+        let mut buf = vec![];
+        write_params(&mut buf, &header, &t).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "pointproofs-view-{}-{}.param",
+            std::process::id(),
+            compressed
+        ));
+        std::fs::write(&path, &buf).unwrap();
+
+        let views = [
+            ParamsView::open(&path).unwrap(),
+            ParamsView::open_pinned(&path, &digest_bytes(&buf)).unwrap(),
+        ];
+        for view in views.iter() {
+            assert_eq!(view.n(), n);
+            for i in 1..=n {
+                assert_eq!(view.g1_power(i).unwrap(), t.g1_alpha_1_to_n[i - 1]);
+                assert_eq!(view.g2_power(i).unwrap(), t.g2_alpha_1_to_n[i - 1]);
+            }
+            for i in n + 2..=2 * n {
+                assert_eq!(
+                    view.g1_power(i).unwrap(),
+                    t.g1_alpha_nplus2_to_2n[i - n - 2]
+                );
+                assert_eq!(
+                    view.g2_power(i).unwrap(),
+                    t.g2_alpha_nplus2_to_2n[i - n - 2]
+                );
+            }
+            assert!(view.g1_power(0).is_err());
+            assert!(view.g1_power(n + 1).is_err());
+            assert!(view.g2_power(2 * n + 1).is_err());
+            assert_eq!(view.gt_alpha_nplus1().unwrap(), t.gt_alpha_nplus1);
+            // later accesses are served from the cache
+            assert_eq!(view.g1_power(n).unwrap(), t.g1_alpha_1_to_n[n - 1]);
+            assert_eq!(view.gt_alpha_nplus1().unwrap(), t.gt_alpha_nplus1);
+        }
+
+        if !compressed {
+            // an uncompressed point off the curve is rejected when it is accessed
+            let enc = G1Uncompressed::from_affine(t.g1_alpha_1_to_n[2]);
+            let start = buf
+                .windows(enc.as_ref().len())
+                .position(|w| w == enc.as_ref())
+                .unwrap();
+            let mut off_curve = buf.clone();
+            off_curve[start + enc.as_ref().len() - 1] ^= 1;
+            std::fs::write(&path, &off_curve).unwrap();
+            let view = ParamsView::open(&path).unwrap();
+            assert!(view.g1_power(3).is_err());
+            assert_eq!(view.g1_power(4).unwrap(), t.g1_alpha_1_to_n[3]);
+        }
+
+        assert!(ParamsView::open_pinned(&path, &[0u8; 64]).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn test_batched_subgroup_checks() {
+    use crate::error::Error;
+    use crate::format::{read_params_with_checks, write_params, FileHeader};
+    use crate::subgroup::{first_not_in_subgroup, PointChecks, SubgroupChecks};
+    use pairing_plus::bls12_381::{G1Uncompressed, G2Compressed, G2Uncompressed};
+    use pairing_plus::{EncodedPoint, SubgroupCheck};
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+    assert_eq!(first_not_in_subgroup(&t.g1_alpha_1_to_n), None);
+    assert_eq!(first_not_in_subgroup(&t.g2_alpha_nplus2_to_2n), None);
+    for _ in 0..8 {
+        assert!(G1::random(&mut OsRng)
+            .into_affine()
+            .in_subgroup_by_endomorphism());
+        assert!(G2::random(&mut OsRng)
+            .into_affine()
+            .in_subgroup_by_endomorphism());
+    }
+
+    for &compressed in [true, false].iter() {
+        let header = FileHeader {
+            compressed,
+            ..FileHeader::default()
+        };
+        let mut buf = vec![];
+        write_params(&mut buf, &header, &t).unwrap();
+        let (_, t2) = read_params_with_checks(&mut &buf[..], SubgroupChecks::Batched).unwrap();
+        assert_eq!(t, t2);
+    }
+
+    // points on the curve with small x coordinates; the endomorphism tests
+    // must agree with multiplying by r on each of them
+    let mut bad_g1 = None;
+    let mut bad_g2 = None;
+    for x in 1..64u8 {
+        let mut enc = G1Compressed::empty();
+        enc.as_mut()[0] = 0x80;
+        enc.as_mut()[47] = x;
+        if let Ok(p) = enc.into_affine_unchecked() {
+            assert!(p.is_on_curve());
+            assert_eq!(p.in_subgroup_by_endomorphism(), p.in_subgroup());
+            if !p.in_subgroup() {
+                bad_g1 = Some(p);
+            }
+        }
+        let mut enc = G2Compressed::empty();
+        enc.as_mut()[0] = 0x80;
+        enc.as_mut()[95] = x;
+        if let Ok(p) = enc.into_affine_unchecked() {
+            assert!(p.is_on_curve());
+            assert_eq!(p.in_subgroup_by_endomorphism(), p.in_subgroup());
+            if !p.in_subgroup() {
+                bad_g2 = Some(p);
+            }
+        }
+    }
+
+    let mut t_bad = crate::generate(alpha, 8);
+    t_bad.g1_alpha_1_to_n[3] = bad_g1.unwrap();
+    assert_eq!(first_not_in_subgroup(&t_bad.g1_alpha_1_to_n), Some(3));
+    let mut buf = vec![];
+    write_params(&mut buf, &FileHeader::default(), &t_bad).unwrap();
+    assert!(read_params_with_checks(&mut &buf[..], SubgroupChecks::PerPoint).is_err());
+    match read_params_with_checks(&mut &buf[..], SubgroupChecks::Batched) {
+        Err(Error::NotInSubgroup(msg)) => assert!(msg.contains("alpha^4")),
+        _ => panic!("a G1 point outside the subgroup was accepted"),
+    }
+
+    let mut t_bad = crate::generate(alpha, 8);
+    t_bad.g2_alpha_nplus2_to_2n[2] = bad_g2.unwrap();
+    let mut buf = vec![];
+    write_params(&mut buf, &FileHeader::default(), &t_bad).unwrap();
+    assert!(read_params_with_checks(&mut &buf[..], SubgroupChecks::PerPoint).is_err());
+    match read_params_with_checks(&mut &buf[..], SubgroupChecks::Batched) {
+        Err(Error::NotInSubgroup(msg)) => assert!(msg.contains("G2 point for alpha^12")),
+        _ => panic!("a G2 point outside the subgroup was accepted"),
+    }
+
+    // an uncompressed point can be off the curve altogether
+    let header = FileHeader {
+        compressed: false,
+        ..FileHeader::default()
+    };
+    let mut buf = vec![];
+    write_params(&mut buf, &header, &t).unwrap();
+    let g1 = G1Uncompressed::from_affine(t.g1_alpha_1_to_n[2]);
+    let g2 = G2Uncompressed::from_affine(t.g2_alpha_1_to_n[5]);
+    for enc in [g1.as_ref(), g2.as_ref()].iter() {
+        let start = buf.windows(enc.len()).position(|w| w == &enc[..]).unwrap();
+        let mut off_curve = buf.clone();
+        off_curve[start + enc.len() - 1] ^= 1;
+        for &checks in [SubgroupChecks::PerPoint, SubgroupChecks::Batched].iter() {
+            match read_params_with_checks(&mut &off_curve[..], checks) {
+                Err(Error::Format(msg)) => assert!(msg.contains("curve")),
+                _ => panic!("a point off the curve was accepted"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_consistency_checker() {
+    use crate::consistency::ConsistencyChecker;
+    use ff::Field;
+    let checker = ConsistencyChecker::new();
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    for &n in [2, 8, 33].iter() {
+        let t = crate::generate(alpha, n);
+        assert!(checker.check(&t));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.g1_alpha_1_to_n.swap(0, 1);
+        assert!(!checker.check(&bad));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.g2_alpha_1_to_n[n - 1] = bad.g2_alpha_nplus2_to_2n[0];
+        assert!(!checker.check(&bad));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.g1_alpha_nplus2_to_2n[n - 2] = bad.g1_alpha_1_to_n[0];
+        assert!(!checker.check(&bad));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.g2_alpha_nplus2_to_2n[0] = bad.g2_alpha_1_to_n[0];
+        assert!(!checker.check(&bad));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.gt_alpha_nplus1.square();
+        assert!(!checker.check(&bad));
+    }
+}
+
+#[test]
+fn test_consistency_security_levels() {
+    use crate::consistency::{ConsistencyChecker, SecurityLevel};
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+    let mut bad = crate::generate(alpha, 8);
+    bad.g1_alpha_nplus2_to_2n.swap(2, 3);
+
+    for &level in [
+        SecurityLevel::Bits64,
+        SecurityLevel::Bits128,
+        SecurityLevel::Full,
+    ]
+    .iter()
+    {
+        let checker = ConsistencyChecker::new()
+            .with_security_level(level)
+            .with_rounds(2);
+        assert_eq!(checker.soundness_bits(), 2 * level.bits());
+        assert!(checker.check(&t));
+        assert!(!checker.check(&bad));
+        assert_eq!(level.to_string().parse::<SecurityLevel>().unwrap(), level);
+    }
+    assert_eq!(ConsistencyChecker::new().with_rounds(0).rounds(), 1);
+}
+
+#[test]
+fn test_seeded_consistency_check() {
+    use crate::consistency::{ChallengeRng, ConsistencyChecker};
+    use crate::digest::params_digest;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+    let digest = params_digest(&t);
+
+    let mut a = ChallengeRng::new(&digest, b"seed");
+    let mut b = ChallengeRng::new(&digest, b"seed");
+    let mut c = ChallengeRng::new(&digest, b"other seed");
+    let (mut x, mut y, mut z) = ([0u8; 200], [0u8; 200], [0u8; 200]);
+    a.fill_bytes(&mut x);
+    b.fill_bytes(&mut y[..100]);
+    b.fill_bytes(&mut y[100..]);
+    c.fill_bytes(&mut z);
+    assert_eq!(x[..], y[..]);
+    assert_ne!(x[..], z[..]);
+
+    let checker = ConsistencyChecker::new().with_seed(b"seed");
+    assert!(checker.check(&t));
+    assert!(crate::consistent_with_rng(
+        &t,
+        &mut ChallengeRng::new(&digest, b"seed")
+    ));
+    let mut bad = crate::generate(alpha, 8);
+    bad.g2_alpha_1_to_n.swap(3, 4);
+    assert!(!checker.check(&bad));
+}
+
+#[test]
+fn test_paranoid_consistency_check() {
+    use crate::consistency::ConsistencyChecker;
+    use crate::paranoid::paranoid_consistent;
+    use ff::Field;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    for &n in [1, 2, 3, 8].iter() {
+        let t = crate::generate(alpha, n);
+        assert!(paranoid_consistent(&t));
+        assert!(ConsistencyChecker::new().paranoid().check(&t));
+
+        let mut bad = crate::generate(alpha, n);
+        bad.gt_alpha_nplus1.square();
+        assert!(!paranoid_consistent(&bad));
+
+        if n >= 2 {
+            // every kind of tampering is caught by both checks
+            let mut tampered = vec![];
+            let mut bad = crate::generate(alpha, n);
+            bad.g1_alpha_1_to_n.swap(0, 1);
+            tampered.push(bad);
+            let mut bad = crate::generate(alpha, n);
+            bad.g2_alpha_1_to_n[n - 1] = bad.g2_alpha_1_to_n[0];
+            tampered.push(bad);
+            let mut bad = crate::generate(alpha, n);
+            bad.g1_alpha_nplus2_to_2n[0] = bad.g1_alpha_1_to_n[n - 1];
+            tampered.push(bad);
+            let mut bad = crate::generate(alpha, n);
+            bad.g2_alpha_nplus2_to_2n[n - 2] = bad.g2_alpha_1_to_n[0];
+            tampered.push(bad);
+            for bad in tampered.iter() {
+                assert!(!paranoid_consistent(bad));
+                assert!(!crate::consistent(bad));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_error_kinds() {
+    use crate::error::Error;
+    use crate::format::{read_params, write_params, FileHeader};
+    use ff::Field;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 8);
+    let mut buf = vec![];
+    write_params(&mut buf, &FileHeader::default(), &t).unwrap();
+
+    match read_params(&mut &buf[..buf.len() - 1]) {
+        Err(Error::Format(_)) => {}
+        r => panic!("expected a format error, got {:?}", r.map(|_| ())),
+    }
+
+    let mut zero_n = buf.clone();
+    zero_n[..4].copy_from_slice(&0u32.to_le_bytes());
+    match read_params(&mut &zero_n[..]) {
+        Err(Error::SizeLimit(0)) => {}
+        r => panic!("expected a size error, got {:?}", r.map(|_| ())),
+    }
+
+    let mut bad_gt = buf.clone();
+    let len = bad_gt.len();
+    Fq12::one()
+        .serialize(&mut &mut bad_gt[len - 576..], true)
+        .unwrap();
+    match read_params(&mut &bad_gt[..]) {
+        Err(Error::NotInSubgroup(_)) => {}
+        r => panic!("expected a subgroup error, got {:?}", r.map(|_| ())),
+    }
+
+    // SerDes still reports io errors
+    assert!(crate::PointproofsParams::deserialize(&mut &zero_n[..], true).is_err());
+}
+
+#[test]
+fn test_progress_and_cancellation() {
+    use crate::consistency::ConsistencyChecker;
+    use crate::error::Error;
+    use crate::format::ProofMode;
+    use crate::progress::{CancelToken, Hooks, Phase, Progress};
+    use std::sync::Mutex;
+
+    struct Recorder(Mutex<Vec<(Phase, usize, usize)>>);
+    impl Progress for Recorder {
+        fn update(&self, phase: Phase, done: usize, total: usize) {
+            self.0.lock().unwrap().push((phase, done, total));
+        }
+    }
+
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let n = 4;
+    let recorder = Recorder(Mutex::new(vec![]));
+    let hooks = Hooks {
+        progress: Some(&recorder),
+        cancel: None,
+    };
+    let t = crate::generate_with_hooks(alpha, n, &hooks).unwrap();
+    let (t2, _) =
+        crate::rerandomize_with_hooks(&t, b"entropy", b"id", ProofMode::Schnorr, &hooks).unwrap();
+    assert!(ConsistencyChecker::new()
+        .check_with_hooks(&t2, &hooks)
+        .unwrap());
+    assert!(ConsistencyChecker::new()
+        .paranoid()
+        .check_with_hooks(&t2, &hooks)
+        .unwrap());
+    let updates = recorder.0.into_inner().unwrap();
+    for &phase in [Phase::Generate, Phase::Rerandomize, Phase::Check].iter() {
+        let of_phase: Vec<_> = updates.iter().filter(|u| u.0 == phase).collect();
+        assert!(!of_phase.is_empty());
+        assert!(of_phase.iter().all(|u| u.1 <= u.2));
+        assert!(of_phase.iter().any(|u| u.1 == u.2));
+    }
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let hooks = Hooks {
+        progress: None,
+        cancel: Some(&cancel),
+    };
+    assert!(matches!(
+        crate::generate_with_hooks(alpha, n, &hooks),
+        Err(Error::Cancelled)
+    ));
+    assert!(matches!(
+        crate::rerandomize_with_hooks(&t, b"entropy", b"id", ProofMode::Schnorr, &hooks),
+        Err(Error::Cancelled)
+    ));
+    assert!(matches!(
+        ConsistencyChecker::new().check_with_hooks(&t, &hooks),
+        Err(Error::Cancelled)
+    ));
+    assert!(matches!(
+        ConsistencyChecker::new()
+            .paranoid()
+            .check_with_hooks(&t, &hooks),
+        Err(Error::Cancelled)
+    ));
+}
+
+#[test]
+fn test_checkpoints() {
+    use crate::checkpoint::{CheckpointKey, EvolveCheckpoint, VerifyCheckpoint};
+    use crate::consistency::{CheckProgress, ConsistencyChecker};
+    use crate::error::Error;
+    use crate::format::ProofMode;
+    use crate::progress::{CancelToken, Hooks};
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let n = 5;
+    let t = crate::generate(alpha, n);
+    let (expected, _) =
+        crate::rerandomize_with_mode(&t, b"entropy", b"id", ProofMode::Schnorr).unwrap();
+
+    // computing the powers a few at a time, through checkpoints, gives the same params
+    let key = CheckpointKey::new(b"passphrase").unwrap();
+    let mut checkpoint = EvolveCheckpoint::new(&t, b"entropy", b"id", ProofMode::Schnorr);
+    while !checkpoint.is_complete(&t) {
+        checkpoint.advance(&t, 3, &Hooks::default()).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        checkpoint.write(&mut buf, &key).unwrap();
+        assert!(EvolveCheckpoint::read(&mut &buf[..], b"wrong passphrase").is_err());
+        let (resumed, _) = EvolveCheckpoint::read(&mut &buf[..], b"passphrase").unwrap();
+        assert!(resumed.matches(&t));
+        assert_eq!(resumed.partial, checkpoint.partial);
+        checkpoint = resumed;
+    }
+    let (params, proof) = checkpoint.finish(&t).unwrap();
+    assert_eq!(params, expected);
+    assert!(crate::check_contribution(
+        &params,
+        t.g2_alpha_1_to_n[0],
+        &proof,
+        b"id"
+    ));
+
+    // a cancelled rerandomization keeps the powers computed so far
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let hooks = Hooks {
+        progress: None,
+        cancel: Some(&cancel),
+    };
+    let mut checkpoint = EvolveCheckpoint::new(&t, b"entropy", b"id", ProofMode::Schnorr);
+    checkpoint.advance(&t, 2, &Hooks::default()).unwrap();
+    assert!(checkpoint.advance(&t, 2, &hooks).is_err());
+    assert_eq!(checkpoint.partial.done(), 2);
+
+    // a check interrupted within a round resumes from the sums done so far,
+    // whether its challenges are seeded or not
+    for checker in [
+        ConsistencyChecker::new().with_rounds(3).with_seed(b"seed"),
+        ConsistencyChecker::new().with_rounds(3),
+    ]
+    .iter()
+    {
+        let mut saved = CheckProgress::default();
+        let interrupted = checker.check_resumable(
+            &expected,
+            CheckProgress::default(),
+            &Hooks::default(),
+            &mut |progress| {
+                saved = progress.clone();
+                if progress.rounds_passed == 1 && progress.sums_done() == 2 {
+                    return Err(Error::Cancelled);
+                }
+                Ok(())
+            },
+        );
+        assert!(matches!(interrupted, Err(Error::Cancelled)));
+        assert_eq!(saved.rounds_passed, 1);
+        assert_eq!(saved.sums_done(), 2);
+        assert!(saved.round_seed.is_some());
+
+        let mut sums = vec![];
+        assert!(checker
+            .check_resumable(
+                &expected,
+                saved.clone(),
+                &Hooks::default(),
+                &mut |progress| {
+                    sums.push((progress.rounds_passed, progress.sums_done()));
+                    Ok(())
+                }
+            )
+            .unwrap());
+        // the three sums left in the second round, the round itself, then the third round
+        assert_eq!(sums[..4], [(1, 3), (1, 4), (1, 5), (2, 0)]);
+        assert_eq!(sums.len(), 4 + 6);
+
+        // a resumed round trusts its recorded sums, so a wrong one fails the check
+        let mut wrong = saved.clone();
+        wrong.g1_sums[1] = G1Affine::one();
+        assert!(!checker
+            .check_resumable(&expected, wrong, &Hooks::default(), &mut |_| Ok(()))
+            .unwrap());
+    }
+
+    // a pass always rests on a round run by the resumed check
+    let checker = ConsistencyChecker::new().with_rounds(3).with_seed(b"seed");
+    let done = CheckProgress {
+        rounds_passed: 3,
+        ..CheckProgress::default()
+    };
+    assert_eq!(checker.rounds_resumed(&done), 2);
+    let mut rounds = vec![];
+    assert!(checker
+        .check_resumable(
+            &expected,
+            done.clone(),
+            &Hooks::default(),
+            &mut |progress| {
+                rounds.push(progress.rounds_passed);
+                Ok(())
+            }
+        )
+        .unwrap());
+    assert_eq!(rounds.last(), Some(&3));
+    assert_eq!(rounds.len(), 6);
+    let mut wrong = crate::generate(alpha, n);
+    wrong.g1_alpha_1_to_n[1] = G1Affine::one();
+    assert!(!checker
+        .check_resumable(&wrong, done, &Hooks::default(), &mut |_| Ok(()))
+        .unwrap());
+    let beyond = CheckProgress {
+        rounds_passed: 4,
+        ..CheckProgress::default()
+    };
+    assert!(checker
+        .check_resumable(&expected, beyond, &Hooks::default(), &mut |_| Ok(()))
+        .is_err());
+
+    let mut checkpoint = VerifyCheckpoint::new(&t, &expected, &checker);
+    checkpoint.progress = CheckProgress {
+        rounds_passed: 2,
+        round_seed: Some([7; 32]),
+        g1_sums: vec![t.g1_alpha_1_to_n[0], t.g1_alpha_1_to_n[1], G1Affine::one()],
+        g2_sums: vec![t.g2_alpha_1_to_n[0]],
+    };
+    let mut buf: Vec<u8> = vec![];
+    checkpoint.write(&mut buf).unwrap();
+    let resumed = VerifyCheckpoint::read(&mut &buf[..]).unwrap();
+    assert_eq!(resumed, checkpoint);
+    assert!(resumed.same_check(&VerifyCheckpoint::new(&t, &expected, &checker)));
+    assert!(!resumed.same_check(&VerifyCheckpoint::new(
+        &t,
+        &expected,
+        &ConsistencyChecker::new()
+    )));
+}
+
+#[test]
+fn test_spot_check() {
+    use crate::error::Error;
+    use crate::spot_check::spot_check;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let delta = Fr::from_repr(FrRepr([7, 0, 0, 0])).unwrap();
+    for &n in [1, 2, 8].iter() {
+        let old = crate::generate(alpha, n);
+        let new = crate::rerandomize_with_scalar(&old, delta, &Default::default()).unwrap();
+        assert!(spot_check(&old, &new, delta, 2 * n, &mut OsRng).is_ok());
+        assert!(spot_check(&old, &new, alpha, 2 * n, &mut OsRng).is_err());
+
+        // the first and last powers and gt are always checked
+        let mut bad = crate::rerandomize_with_scalar(&old, delta, &Default::default()).unwrap();
+        bad.g1_alpha_1_to_n[0] = old.g1_alpha_1_to_n[0];
+        assert!(matches!(
+            spot_check(&old, &bad, delta, 0, &mut OsRng),
+            Err(Error::Fault(_))
+        ));
+        let mut bad = crate::rerandomize_with_scalar(&old, delta, &Default::default()).unwrap();
+        bad.gt_alpha_nplus1 = old.gt_alpha_nplus1;
+        assert!(spot_check(&old, &bad, delta, 0, &mut OsRng).is_err());
+    }
+}
+
+#[test]
+fn test_manifest() {
+    use crate::error::Error;
+    use crate::format::FileHeader;
+    use crate::manifest::{Manifest, Slot};
+    use crate::openpgp::parse_certificate;
+    use crate::transcript::{verify_transcript_with_options, Transcript, TranscriptOptions};
+    use std::path::Path;
+    // plain ids, so the certificates are the keys
+    let cert = |name: &str| {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(format!("testdata/openpgp/{}.cert.asc", name));
+        serde_json::to_string(&std::fs::read_to_string(path).unwrap()).unwrap()
+    };
+    let text = format!(
+        r#"{{
+            "version": 1,
+            "n": 16,
+            "init_seed": "{}",
+            "participants": [
+                {{"id": "alice", "public_key": {}, "slot": {{"start": 100, "end": 200}}}},
+                {{"id": "bob", "public_key": {}, "slot": {{"start": 200, "end": 300}}}},
+                {{"id": "carol", "public_key": {}, "slot": {{"start": 300, "end": 400}}}}
+            ],
+            "beacon": {{"source": "some chain", "rule": "first block after 500", "not_before": 500}}
+        }}"#,
+        crate::INIT_ALPHA_SEED,
+        cert("alice"),
+        cert("mallory"),
+        cert("carol")
+    );
+    let manifest = Manifest::parse(&text).unwrap();
+    assert!(manifest.validate().is_ok());
+    assert!(manifest.participants[1].slot.contains(200));
+    assert!(!manifest.participants[1].slot.contains(300));
+
+    let mut bad = manifest.clone();
+    bad.participants.swap(0, 1);
+    bad.participants[2].public_key = bad.participants[1].public_key.clone();
+    bad.participants[2].slot = Slot {
+        start: 400,
+        end: 400,
+    };
+    bad.beacon.not_before = 350;
+    // unsorted ids, overlapping slots, a reused key, an empty slot, an early beacon
+    assert_eq!(bad.problems().len(), 5);
+    assert!(bad.validate().is_err());
+    assert!(Manifest::parse("{\"version\": 1}").is_err());
+
+    // every key must be one that messages are checked against
+    let key = crate::signature::SigningKey::generate();
+    let hex_key = crate::digest::to_hex(key.public_key().as_bytes());
+    let mut unused = manifest.clone();
+    unused.participants[0].public_key = hex_key.clone();
+    assert_eq!(unused.problems().len(), 1);
+    unused.participants[0].id = key.id_string("alice").unwrap();
+    assert!(unused.validate().is_ok());
+    unused.participants[0].public_key = "b41811a3f8db988d27264a96259ec82cdb8a05ec".to_string();
+    assert_eq!(unused.problems().len(), 1);
+    unused.participants[0].id =
+        "alice openpgp:b41811a3f8db988d27264a96259ec82cdb8a05ec".to_string();
+    assert!(unused.validate().is_ok());
+
+    let transcript = |lines: &str| {
+        Transcript::parse(&format!("params.initial\n{}", lines), Path::new("")).unwrap()
+    };
+    let prefix = transcript("p1\talice\t150\nBAD\tbob\n");
+    assert_eq!(prefix.entries[0].time, Some(150));
+    assert_eq!(prefix.entries[1].time, None);
+    assert!(manifest.check_transcript(&prefix).is_ok());
+    for lines in &[
+        "p1\tbob\t250\n",
+        "p1\talice\t150\np2\talice\t250\n",
+        "p1\talice\t150\np2\tmallory\t250\n",
+    ] {
+        assert!(manifest.check_transcript(&transcript(lines)).is_err());
+    }
+    assert!(Transcript::parse("params.initial\np1\talice\tnoon\n", Path::new("")).is_err());
+
+    // a manifest signed by the organizers; see testdata/openpgp/README
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/openpgp");
+    let organizers =
+        parse_certificate(include_str!("../testdata/openpgp/organizers.cert.asc")).unwrap();
+    let mallory = parse_certificate(include_str!("../testdata/openpgp/mallory.cert.asc")).unwrap();
+    let signed = Manifest::read_signed(dir.join("manifest.json"), &organizers).unwrap();
+    assert!(signed.validate().is_ok());
+    assert!(Manifest::read_signed(dir.join("manifest.json"), &mallory).is_err());
+    // a manifest whose signature is missing or covers another file
+    let tmp = std::env::temp_dir().join(format!("pointproofs-manifest-{}", std::process::id()));
+    std::fs::create_dir_all(&tmp).unwrap();
+    let copy = tmp.join("manifest.json");
+    std::fs::copy(dir.join("manifest.json"), &copy).unwrap();
+    assert!(Manifest::read_signed(&copy, &organizers).is_err());
+    std::fs::copy(dir.join("message.asc"), tmp.join("manifest.json.asc")).unwrap();
+    assert!(Manifest::read_signed(&copy, &organizers).is_err());
+
+    // the initial parameters must be the ones derived from init_seed, for n, and consistent
+    let initial = tmp.join("params.initial");
+    let write_initial = |params: &crate::PointproofsParams| {
+        let mut buf = vec![];
+        crate::format::write_params(&mut buf, &FileHeader::default(), params).unwrap();
+        std::fs::write(&initial, &buf).unwrap();
+    };
+    let empty = Transcript::parse("params.initial\n", &tmp).unwrap();
+    let options = TranscriptOptions {
+        manifest: Some(signed.clone()),
+        ..TranscriptOptions::default()
+    };
+    let mut params = crate::generate(crate::init_alpha(), 4);
+    write_initial(&params);
+    assert!(verify_transcript_with_options(&empty, &options).is_ok());
+    params.g1_alpha_1_to_n[2] = params.g1_alpha_1_to_n[1];
+    write_initial(&params);
+    assert!(matches!(
+        verify_transcript_with_options(&empty, &options),
+        Err(Error::Inconsistent)
+    ));
+    write_initial(&crate::generate(
+        Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap(),
+        4,
+    ));
+    assert!(matches!(
+        verify_transcript_with_options(&empty, &options),
+        Err(Error::Format(_))
+    ));
+    std::fs::remove_dir_all(&tmp).unwrap();
+}
+
+#[test]
+fn test_signatures() {
+    use crate::format::{
+        read_signed_contribution_with_checks, write_contribution, write_signed_contribution,
+        FileHeader, ProofMode,
+    };
+    use crate::signature::{check_signature, embedded_key, SigningKey};
+    use crate::subgroup::SubgroupChecks;
+    use crate::Rejection;
+    let key = SigningKey::generate();
+    let id = key.id_string("alice").unwrap();
+    assert_eq!(embedded_key(id.as_bytes()).unwrap(), Some(key.public_key()));
+    assert_eq!(embedded_key(b"alice").unwrap(), None);
+    assert!(embedded_key(b"alice ed25519:1234").is_err());
+    assert!(key.id_string("bad\tname").is_err());
+
+    let mut secret = vec![];
+    key.write(&mut secret).unwrap();
+    let key2 = SigningKey::read(&mut &secret[..]).unwrap();
+    assert_eq!(key2.public_key(), key.public_key());
+
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 4);
+    let header = FileHeader::default();
+    let (params, proof) =
+        crate::rerandomize_with_mode(&t, b"entropy", id.as_bytes(), ProofMode::Schnorr).unwrap();
+    let read = |buf: &[u8]| {
+        read_signed_contribution_with_checks(&mut &buf[..], SubgroupChecks::PerPoint).unwrap()
+    };
+
+    let mut signed = vec![];
+    write_signed_contribution(&mut signed, &header, &params, &proof, &key).unwrap();
+    let (_, params2, _, signature) = read(&signed);
+    assert_eq!(params, params2);
+    assert!(check_signature(id.as_bytes(), signature.as_ref()).is_ok());
+
+    // signed by someone else
+    let other = SigningKey::generate().id_string("alice").unwrap();
+    assert_eq!(
+        check_signature(other.as_bytes(), signature.as_ref()),
+        Err(Rejection::BadSignature)
+    );
+
+    // a corrupted signature
+    let last = signed.len() - 1;
+    signed[last] ^= 1;
+    let (_, _, _, signature) = read(&signed);
+    assert_eq!(
+        check_signature(id.as_bytes(), signature.as_ref()),
+        Err(Rejection::BadSignature)
+    );
+
+    // no signature: fine only for an id string without a key
+    let mut unsigned = vec![];
+    write_contribution(&mut unsigned, &header, &params, &proof).unwrap();
+    let (_, _, _, signature) = read(&unsigned);
+    assert!(signature.is_none());
+    assert_eq!(
+        check_signature(id.as_bytes(), None),
+        Err(Rejection::BadSignature)
+    );
+    assert!(check_signature(b"alice", None).is_ok());
+}
+
+#[test]
+fn test_openpgp_signatures() {
+    use crate::openpgp::{
+        check_detached_signature, fingerprint_hex, named_fingerprint, parse_certificate,
+        verify_detached, Keyring,
+    };
+    use crate::Rejection;
+    use std::path::Path;
+    // generated with gpg --quick-gen-key ... ed25519 sign; see testdata/openpgp/README
+    let alice = include_str!("../testdata/openpgp/alice.cert.asc");
+    let mallory = include_str!("../testdata/openpgp/mallory.cert.asc");
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/openpgp");
+    let message = std::fs::read(dir.join("message")).unwrap();
+    let by_alice = include_str!("../testdata/openpgp/message.asc");
+    let by_mallory = include_str!("../testdata/openpgp/message.mallory.asc");
+
+    let alice_fpr = "b41811a3f8db988d27264a96259ec82cdb8a05ec";
+    let id = format!("alice openpgp:{}", alice_fpr);
+    assert_eq!(
+        named_fingerprint(id.as_bytes()).unwrap(),
+        crate::digest::from_hex(alice_fpr)
+    );
+    assert!(named_fingerprint(b"alice openpgp:b418").is_err());
+    assert_eq!(named_fingerprint(b"alice").unwrap(), None);
+
+    let cert = parse_certificate(alice).unwrap();
+    assert_eq!(fingerprint_hex(&cert), alice_fpr);
+    assert!(verify_detached(&cert, &message, by_alice));
+    assert!(!verify_detached(&cert, &message, by_mallory));
+    assert!(!verify_detached(&cert, b"another message", by_alice));
+
+    let keyring = Keyring::parse(&format!("{}\n{}", alice, mallory)).unwrap();
+    assert_eq!(keyring.len(), 2);
+    let path = dir.join("message");
+    assert!(check_detached_signature(&path, id.as_bytes(), None, &keyring).is_ok());
+    // the certificate can also come from the manifest
+    assert!(check_detached_signature(&path, id.as_bytes(), Some(alice), &Keyring::new()).is_ok());
+    assert_eq!(
+        check_detached_signature(&path, id.as_bytes(), None, &Keyring::new()),
+        Err(Rejection::BadSignature)
+    );
+    // the manifest's certificate must be the one the id string names
+    assert_eq!(
+        check_detached_signature(&path, id.as_bytes(), Some(mallory), &keyring),
+        Err(Rejection::BadSignature)
+    );
+    // a signature by someone else
+    let mallory_id = format!(
+        "mallory openpgp:{}",
+        fingerprint_hex(&parse_certificate(mallory).unwrap())
+    );
+    assert_eq!(
+        check_detached_signature(&path, mallory_id.as_bytes(), None, &keyring),
+        Err(Rejection::BadSignature)
+    );
+    // participants who do not sign with OpenPGP are not checked
+    assert!(check_detached_signature(&path, b"bob", None, &keyring).is_ok());
+
+    // carol's primary key only certifies; her subkeys signed on 2025-01-10
+    // and were changed afterwards, see testdata/openpgp/README
+    let carol = parse_certificate(include_str!("../testdata/openpgp/carol.cert.asc")).unwrap();
+    assert_eq!(carol.public_subkeys.len(), 5);
+    let by_carol = |name: &str| {
+        let signature = std::fs::read_to_string(dir.join(format!("message.{}.asc", name)));
+        verify_detached(&carol, &message, &signature.unwrap())
+    };
+    assert!(by_carol("carol"));
+    // superseded after signing, which leaves earlier signatures standing
+    assert!(by_carol("superseded"));
+    // expired before signing
+    assert!(!by_carol("expired"));
+    // revoked as compromised, which reaches back
+    assert!(!by_carol("revoked"));
+    // no longer flagged for signing
+    assert!(!by_carol("unflagged"));
+    // alice's key is not carol's
+    assert!(!verify_detached(&carol, &message, by_alice));
+}
+
+#[test]
+fn test_receipts() {
+    use crate::format::ProofMode;
+    use crate::receipt::Receipt;
+    use crate::Rejection;
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 4);
+    for &mode in [ProofMode::Schnorr, ProofMode::Pairing].iter() {
+        let (params, proof) = crate::rerandomize_with_mode(&t, b"entropy", b"alice", mode).unwrap();
+        let receipt = Receipt::new("test ceremony", 1, "alice", &t, &params, &proof).unwrap();
+        let text = receipt.to_string();
+        assert!(text.len() < 1200);
+
+        let parsed = Receipt::parse(&text).unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.index, 1);
+        assert_eq!(parsed.id, "alice");
+        assert!(parsed.check_files(&t, &params, &proof).is_ok());
+        assert!(crate::check_contribution(
+            &params,
+            t.g2_alpha_1_to_n[0],
+            &parsed.decode_proof().unwrap(),
+            b"alice"
+        ));
+
+        // the receipt of another contribution to the same parameters
+        let (other, other_proof) =
+            crate::rerandomize_with_mode(&t, b"other", b"alice", mode).unwrap();
+        match parsed.check_files(&t, &other, &other_proof) {
+            Err(Rejection::ReceiptMismatch(_)) => (),
+            _ => panic!("receipt matched the wrong contribution"),
+        }
+        assert!(parsed.check_files(&params, &params, &proof).is_err());
+
+        // the commitment line must agree with the proof
+        let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        let delta = lines.iter().position(|l| l.contains("_delta: ")).unwrap();
+        lines[delta] = lines[delta].replace("_delta: ", "_delta: 00");
+        assert!(Receipt::parse(&lines.join("\n")).is_err());
+        assert!(Receipt::parse(&text.replace("index: 1", "index: one")).is_err());
+    }
+}
+
+#[test]
+fn test_armor() {
+    use crate::armor::{armor_digest, armor_proof, parse_digest, ArmorKind, Armored};
+    use crate::digest::{params_digest, to_hex};
+    use crate::format::{
+        read_contribution_with_checks, read_signed_contribution_with_checks, write_params,
+        FileHeader, ProofMode,
+    };
+    use crate::receipt::Receipt;
+    use crate::signature::write_trailer;
+    use crate::subgroup::SubgroupChecks;
+    let mut data = vec![0u8; 200];
+    OsRng.fill_bytes(&mut data);
+    let armored = Armored::new(ArmorKind::Proof, data).with_header("Mode", "schnorr");
+    let text = armored.to_string();
+    assert!(text.lines().all(|l| l.len() <= 64));
+    assert_eq!(Armored::parse(&text).unwrap(), armored);
+
+    // what mail transport and replying do to the text
+    let mangled: String = text.lines().map(|l| format!("> {}  \r\n", l)).collect();
+    let email = format!("Hi all,\n\nhere is my proof:\n\n{}\nThanks\n", mangled);
+    assert_eq!(Armored::parse(&email).unwrap(), armored);
+    assert!(Armored::parse_kind(&text, ArmorKind::Digest).is_err());
+
+    // a damaged character is caught by the checksum
+    let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+    let body = lines[3].clone();
+    let c = if body.starts_with('A') { "B" } else { "A" };
+    lines[3] = format!("{}{}", c, &body[1..]);
+    assert!(Armored::parse(&lines.join("\n")).is_err());
+    assert!(Armored::parse(&text.replace("-----END", "-----FIN")).is_err());
+
+    let alpha = Fr::from_repr(FrRepr([5, 0, 0, 0])).unwrap();
+    let t = crate::generate(alpha, 4);
+    let digest = params_digest(&t);
+    assert_eq!(parse_digest(&to_hex(&digest)).unwrap()[..], digest[..]);
+    assert_eq!(
+        parse_digest(&armor_digest(&digest).to_string()).unwrap()[..],
+        digest[..]
+    );
+    assert!(parse_digest("1234").is_err());
+
+    for &mode in [ProofMode::Schnorr, ProofMode::Pairing].iter() {
+        let header = FileHeader {
+            proof_mode: mode,
+            ..FileHeader::default()
+        };
+        let (params, proof) = crate::rerandomize_with_mode(&t, b"entropy", b"alice", mode).unwrap();
+        let mut proof_bytes = vec![];
+        proof.serialize(&mut proof_bytes, true).unwrap();
+
+        // a contribution whose proof is armored reads like the binary one
+        let mut file = vec![];
+        write_params(&mut file, &header, &params).unwrap();
+        file.extend_from_slice(armor_proof(&proof).unwrap().to_string().as_bytes());
+        let (_, params2, proof2) =
+            read_contribution_with_checks(&mut &file[..], SubgroupChecks::PerPoint).unwrap();
+        assert_eq!(params2, params);
+        let mut proof2_bytes = vec![];
+        proof2.serialize(&mut proof2_bytes, true).unwrap();
+        assert_eq!(proof2_bytes, proof_bytes);
+
+        // and a signature trailer may still follow it
+        write_trailer(&mut file, &[7u8; 64]).unwrap();
+        let (_, _, _, signature) =
+            read_signed_contribution_with_checks(&mut &file[..], SubgroupChecks::PerPoint).unwrap();
+        assert_eq!(signature.unwrap().signature[..], [7u8; 64][..]);
+
+        let receipt = Receipt::new("test ceremony", 2, "alice", &t, &params, &proof).unwrap();
+        let armored = receipt.to_armored();
+        assert_eq!(armored.header("Index"), Some("2"));
+        let parsed = Receipt::parse(&armored.to_string()).unwrap();
+        assert_eq!(parsed.to_string(), receipt.to_string());
+    }
+}
+
+#[test]
+fn test_mailbox() {
+    use crate::format::{write_contribution, write_signed_contribution, FileHeader, ProofMode};
+    use crate::mailbox::{ingest_mbox, split_mbox, IngestOptions, MailOutcome, TRANSCRIPT_FILE};
+    use crate::manifest::Manifest;
+    use crate::openpgp::Keyring;
+    use crate::receipt::Receipt;
+    use crate::signature::SigningKey;
+    use crate::subgroup::SubgroupChecks;
+    use crate::transcript::{verify_transcript_with_options, Transcript, TranscriptOptions};
+    let key = SigningKey::generate();
+    let alice = key.id_string("alice").unwrap();
+    let key_b = SigningKey::generate();
+    let bob_id = key_b.id_string("bob").unwrap();
+    let manifest = Manifest::parse(&format!(
+        r#"{{
+            "version": 1,
+            "n": 4,
+            "init_seed": "{}",
+            "participants": [
+                {{"id": "{}", "public_key": "{}", "slot": {{"start": 100, "end": 200}}}},
+                {{"id": "{}", "public_key": "{}", "slot": {{"start": 200, "end": 300}}}}
+            ],
+            "beacon": {{"source": "some chain", "rule": "first block after 500", "not_before": 500}}
+        }}"#,
+        crate::INIT_ALPHA_SEED,
+        alice,
+        crate::digest::to_hex(key.public_key().as_bytes()),
+        bob_id,
+        crate::digest::to_hex(key_b.public_key().as_bytes())
+    ))
+    .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("pointproofs-mailbox-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let initial = dir.join("params.init");
+    let t = crate::generate(crate::init_alpha(), 4);
+    let header = FileHeader::default();
+    let mut buf = vec![];
+    crate::format::write_params(&mut buf, &header, &t).unwrap();
+    std::fs::write(&initial, &buf).unwrap();
+
+    let (p1, proof1) =
+        crate::rerandomize_with_mode(&t, b"a", alice.as_bytes(), ProofMode::Schnorr).unwrap();
+    let mut signed = vec![];
+    write_signed_contribution(&mut signed, &header, &p1, &proof1, &key).unwrap();
+    let mut unsigned = vec![];
+    write_contribution(&mut unsigned, &header, &p1, &proof1).unwrap();
+    // signed, but proven for another id
+    let (p0, proof0) =
+        crate::rerandomize_with_mode(&t, b"x", b"alice", ProofMode::Schnorr).unwrap();
+    let mut broken = vec![];
+    write_signed_contribution(&mut broken, &header, &p0, &proof0, &key).unwrap();
+    let receipt = Receipt::new("test", 1, &alice, &t, &p1, &proof1).unwrap();
+    let (p2, proof2) =
+        crate::rerandomize_with_mode(&p1, b"b", bob_id.as_bytes(), ProofMode::Schnorr).unwrap();
+    let mut bob = vec![];
+    write_signed_contribution(&mut bob, &header, &p2, &proof2, &key_b).unwrap();
+
+    let message = |from: &str, time: u64, body: &str, attachment: Option<&[u8]>| {
+        let mut text = format!(
+            "From {} Thu Jan  1 00:00:00 1970\nFrom: {}\nDate: Thu, 1 Jan 1970 00:{:02}:{:02} +0000\n\
+             MIME-Version: 1.0\nContent-Type: multipart/mixed; boundary=\"XX\"\n\n\
+             --XX\nContent-Type: text/plain\n\n{}\n",
+            from,
+            from,
+            time / 60,
+            time % 60,
+            body
+        );
+        if let Some(data) = attachment {
+            let encoded = base64::encode(data);
+            text.push_str(
+                "--XX\nContent-Type: application/octet-stream\n\
+                 Content-Disposition: attachment; filename=\"params\"\n\
+                 Content-Transfer-Encoding: base64\n\n",
+            );
+            for chunk in encoded.as_bytes().chunks(76) {
+                text.push_str(std::str::from_utf8(chunk).unwrap());
+                text.push('\n');
+            }
+        }
+        text.push_str("--XX--\n\n");
+        text
+    };
+    let armored = receipt.to_armored().to_string();
+    let mbox = [
+        message(
+            "organizer",
+            110,
+            "Alice, you're up.\n>From the organizers",
+            None,
+        ),
+        // an impostor posts first: in alice's slot, but not signed with her key
+        message("mallory", 120, "", Some(&unsigned)),
+        // a broken first attempt does not lock out the good one
+        message("alice", 130, "", Some(&broken)),
+        message("alice", 150, &armored, Some(&signed)),
+        message("alice", 160, &armored, Some(&signed)),
+        message("bob", 350, "late", Some(&bob)),
+        message("bob", 250, "", Some(&bob)),
+    ]
+    .concat();
+    assert_eq!(split_mbox(mbox.as_bytes()).len(), 7);
+    assert!(String::from_utf8(split_mbox(mbox.as_bytes())[0].clone())
+        .unwrap()
+        .contains("\nFrom the organizers"));
+
+    let options = IngestOptions {
+        checks: SubgroupChecks::PerPoint,
+        checker: crate::consistency::ConsistencyChecker::new(),
+        manifest: manifest.clone(),
+        keyring: Keyring::new(),
+    };
+    let report = ingest_mbox(mbox.as_bytes(), &initial, &dir, &options).unwrap();
+    let outcomes: Vec<String> = report
+        .messages
+        .iter()
+        .map(|m| match &m.outcome {
+            MailOutcome::Accepted(i) => format!("accepted {}", i),
+            MailOutcome::Rejected(i, reason) => format!("rejected {} {:?}", i, reason),
+            MailOutcome::Ignored(i) => format!("ignored {}", i),
+            MailOutcome::Unattributed(_) => "unattributed".to_string(),
+        })
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            "unattributed",
+            "rejected 1 BadSignature",
+            "rejected 1 BadProof",
+            "accepted 1",
+            "ignored 1",
+            "unattributed",
+            "accepted 2"
+        ]
+    );
+    assert_eq!(report.transcript.entries[0].time, Some(150));
+    assert_eq!(std::fs::read(dir.join("1.params")).unwrap(), signed);
+
+    let transcript = Transcript::read_from_file(dir.join(TRANSCRIPT_FILE)).unwrap();
+    let options = TranscriptOptions {
+        manifest: Some(manifest),
+        ..TranscriptOptions::default()
+    };
+    let summary = verify_transcript_with_options(&transcript, &options).unwrap();
+    assert_eq!(summary.latest_good, 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_fingerprint() {
+    use crate::digest::digest_bytes;
+    use crate::fingerprint::{randomart, word_string, words, WORD_BYTES, WORD_SECURITY_BITS};
+    use std::collections::HashSet;
+    let digest = digest_bytes(b"params.final");
+    assert_eq!(words(&digest).len(), WORD_BYTES);
+    assert_eq!(
+        word_string(&digest),
+        word_string(&digest_bytes(b"params.final"))
+    );
+
+    // every byte value gets its own word, and the two lists do not share any
+    let mut seen = HashSet::new();
+    for b in 0..=255u8 {
+        let mut d = [b; 64];
+        assert!(seen.insert(words(&d)[0]));
+        assert!(seen.insert(words(&d)[1]));
+        d[1] ^= 1;
+        assert_ne!(word_string(&d), word_string(&[b; 64]));
+    }
+    assert_eq!(seen.len(), 512);
+
+    let art = randomart(&digest);
+    let lines: Vec<&str> = art.lines().collect();
+    assert_eq!(lines.len(), 11);
+    assert!(lines.iter().all(|l| l.chars().count() == 19));
+    assert!(lines[0].contains("[SHA-512]"));
+    assert!(art.contains('E'));
+    assert_ne!(art, randomart(&digest_bytes(b"params.final2")));
+
+    // the words cover the first WORD_BYTES bytes, the picture all of them
+    assert_eq!(WORD_SECURITY_BITS, 128);
+    let mut last_word = digest;
+    last_word[WORD_BYTES - 1] ^= 1;
+    assert_ne!(word_string(&digest), word_string(&last_word));
+    let mut tail = digest;
+    tail[WORD_BYTES] ^= 1;
+    assert_eq!(word_string(&digest), word_string(&tail));
+    let mut last = digest;
+    last[63] ^= 1;
+    assert_ne!(art, randomart(&last));
+}
@@ -0,0 +1,290 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It runs the "latest good message" selection described in usage.md over a
+//! whole transcript of broadcast messages.
+//!
+//! A transcript is a text file. Blank lines and lines starting with `#` are
+//! ignored. The first remaining line is the path to the initial parameters;
+//! every following line is `path<TAB>id_string` for one participant, in
+//! order, where `path` is `BAD` if the participant sent no properly signed
+//! message during their timeslot. Relative paths are resolved against the
+//! directory containing the transcript. A line may carry a third column,
+//! `path<TAB>id_string<TAB>time`, giving the Unix time in seconds at which
+//! the message was broadcast.
+//!
+//! When verified against a ceremony manifest, the transcript must list the
+//! manifest's participants in order, the initial parameters must be the ones
+//! the manifest fixes, and a message broadcast outside its sender's timeslot
+//! (or without a time) is rejected.
+//!
+//! A message from an id string that embeds a signing key is rejected unless
+//! it carries a valid signature under that key (see `signature`), and one
+//! from a participant who signs with OpenPGP unless the detached signature
+//! next to it verifies (see `openpgp`).
+use crate::consistency::ConsistencyChecker;
+use crate::digest::{params_digest, Digest};
+use crate::error::{Error, Result};
+use crate::format::{read_params_with_checks, read_signed_contribution_with_checks};
+use crate::hash_to_field_pointproofs::hash_to_field_pointproofs;
+use crate::manifest::Manifest;
+use crate::openpgp::{check_detached_signature, Keyring};
+use crate::signature::check_signature;
+use crate::subgroup::SubgroupChecks;
+use crate::{check_contribution_with_checker, PointproofsParams, Rejection};
+use pairing_plus::bls12_381::{G1Affine, G2Affine};
+use pairing_plus::{CurveAffine, CurveProjective};
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// One participant's line in a transcript.
+pub struct TranscriptEntry {
+    pub id: String,
+    /// Path to the participant's message, or `None` if they sent no good message.
+    pub path: Option<PathBuf>,
+    /// When the message was broadcast, in Unix seconds, if the transcript says.
+    pub time: Option<u64>,
+}
+
+pub struct Transcript {
+    pub initial: PathBuf,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    /// Parses a transcript, resolving relative paths against `base`.
+    pub fn parse(text: &str, base: &Path) -> Result<Self> {
+        let mut lines = text
+            .lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .filter(|l| !l.trim().is_empty() && !l.starts_with('#'));
+
+        let initial = match lines.next() {
+            Some(l) => base.join(l.trim()),
+            None => {
+                return Err(Error::Format(
+                    "Transcript does not name the initial parameters".to_string(),
+                ))
+            }
+        };
+
+        let mut entries = vec![];
+        for line in lines {
+            let mut parts = line.splitn(3, '\t');
+            let path = parts.next().unwrap_or("").trim();
+            let id = match parts.next() {
+                Some(id) => id.to_string(),
+                None => {
+                    return Err(Error::Format(format!(
+                        "Transcript line has no id string: {}",
+                        line
+                    )))
+                }
+            };
+            let time = match parts.next() {
+                None => None,
+                Some(t) => match t.trim().parse::<u64>() {
+                    Ok(t) => Some(t),
+                    Err(_) => {
+                        return Err(Error::Format(format!(
+                            "Transcript line has a bad time: {}",
+                            line
+                        )))
+                    }
+                },
+            };
+            let path = if path == "BAD" {
+                None
+            } else {
+                Some(base.join(path))
+            };
+            entries.push(TranscriptEntry { id, path, time });
+        }
+        Ok(Transcript { initial, entries })
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        let base = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        Transcript::parse(&text, base)
+    }
+}
+
+/// Writes the transcript in the format `parse` reads, with the paths as they are.
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.initial.display())?;
+        for entry in self.entries.iter() {
+            match &entry.path {
+                None => write!(f, "BAD\t{}", entry.id)?,
+                Some(path) => write!(f, "{}\t{}", path.display(), entry.id)?,
+            }
+            if let Some(time) = entry.time {
+                write!(f, "\t{}", time)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The verdict on one participant's message.
+pub struct Outcome {
+    pub id: String,
+    pub result: std::result::Result<(), Rejection>,
+}
+
+pub struct TranscriptReport {
+    /// Index of the latest good message; 0 means the initial parameters.
+    pub latest_good: usize,
+    /// One outcome per transcript entry; entry j has message index j + 1.
+    pub outcomes: Vec<Outcome>,
+}
+
+/// How a transcript is verified.
+pub struct TranscriptOptions {
+    pub checks: SubgroupChecks,
+    pub checker: ConsistencyChecker,
+    /// The ceremony manifest to enforce, if any.
+    pub manifest: Option<Manifest>,
+    /// Certificates of the participants who sign with OpenPGP.
+    pub keyring: Keyring,
+}
+
+impl Default for TranscriptOptions {
+    fn default() -> Self {
+        TranscriptOptions {
+            checks: SubgroupChecks::PerPoint,
+            checker: ConsistencyChecker::new(),
+            manifest: None,
+            keyring: Keyring::new(),
+        }
+    }
+}
+
+/// Verifies every message against the latest good message before it.
+/// Problems with individual messages are reported in the outcomes; only a
+/// failure to load the initial parameters, or a transcript that does not
+/// follow the manifest, is returned as an error.
+pub fn verify_transcript(transcript: &Transcript) -> Result<TranscriptReport> {
+    verify_transcript_with_options(transcript, &TranscriptOptions::default())
+}
+
+/// Like `verify_transcript`, but with the given subgroup and consistency checks.
+pub fn verify_transcript_with_options(
+    transcript: &Transcript,
+    options: &TranscriptOptions,
+) -> Result<TranscriptReport> {
+    let checks = options.checks;
+    if let Some(manifest) = &options.manifest {
+        manifest.validate()?;
+        manifest.check_transcript(transcript)?;
+    }
+    let (header, initial) = read_params_with_checks(&mut File::open(&transcript.initial)?, checks)?;
+    if let Some(manifest) = &options.manifest {
+        check_initial(manifest, &initial, &options.checker)?;
+    }
+
+    // digests of every readable message so far, and the message index each came from
+    let mut digests: Vec<Digest> = vec![params_digest(&initial)];
+    let mut digest_index: Vec<usize> = vec![0];
+
+    let mut latest: PointproofsParams = initial;
+    let mut latest_good = 0;
+    let mut outcomes = vec![];
+
+    for (j, entry) in transcript.entries.iter().enumerate() {
+        let index = j + 1;
+        let in_slot = match &options.manifest {
+            None => true,
+            Some(m) => entry
+                .time
+                .map_or(false, |t| m.participants[j].slot.contains(t)),
+        };
+        let manifest_key = options
+            .manifest
+            .as_ref()
+            .map(|m| m.participants[j].public_key.as_str());
+        let result = match &entry.path {
+            None => Err(Rejection::Missing),
+            Some(_) if !in_slot => Err(Rejection::OutsideSlot),
+            Some(path) => {
+                let loaded = File::open(path)
+                    .map_err(Error::from)
+                    .and_then(|mut f| read_signed_contribution_with_checks(&mut f, checks));
+                match loaded {
+                    Err(e) => Err(Rejection::Malformed(e.to_string())),
+                    Ok((h, _, _, _)) if h != header => Err(Rejection::SettingsMismatch),
+                    Ok((_, _, _, signature))
+                        if check_signature(entry.id.as_bytes(), signature.as_ref()).is_err() =>
+                    {
+                        Err(Rejection::BadSignature)
+                    }
+                    Ok(_)
+                        if check_detached_signature(
+                            path,
+                            entry.id.as_bytes(),
+                            manifest_key,
+                            &options.keyring,
+                        )
+                        .is_err() =>
+                    {
+                        Err(Rejection::BadSignature)
+                    }
+                    Ok((_, params, proof, _)) => {
+                        let result = check_contribution_with_checker(
+                            &options.checker,
+                            &params,
+                            latest.g2_alpha_1_to_n[0],
+                            &proof,
+                            entry.id.as_bytes(),
+                            &digests,
+                        )
+                        .map_err(|r| match r {
+                            Rejection::Replayed(i) => Rejection::Replayed(digest_index[i]),
+                            r => r,
+                        });
+                        digests.push(params_digest(&params));
+                        digest_index.push(index);
+                        if result.is_ok() {
+                            latest = params;
+                            latest_good = index;
+                        }
+                        result
+                    }
+                }
+            }
+        };
+        outcomes.push(Outcome {
+            id: entry.id.clone(),
+            result,
+        });
+    }
+
+    Ok(TranscriptReport {
+        latest_good,
+        outcomes,
+    })
+}
+
+// the initial parameters must be g^{alpha^i} for the alpha the manifest's
+// seed gives and for the manifest's n
+fn check_initial(
+    manifest: &Manifest,
+    initial: &PointproofsParams,
+    checker: &ConsistencyChecker,
+) -> Result<()> {
+    let alpha = hash_to_field_pointproofs(&manifest.init_seed);
+    if initial.n != manifest.n
+        || initial.g1_alpha_1_to_n[0] != G1Affine::one().mul(alpha).into_affine()
+        || initial.g2_alpha_1_to_n[0] != G2Affine::one().mul(alpha).into_affine()
+    {
+        return Err(Error::Format(
+            "Initial parameters are not the ones the manifest fixes".to_string(),
+        ));
+    }
+    // the first powers are right; the check ties every other power to them
+    if !checker.check(initial) {
+        return Err(Error::Inconsistent);
+    }
+    Ok(())
+}
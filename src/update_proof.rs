@@ -0,0 +1,77 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines the pairing-based update proof modeled on the Ethereum KZG ceremony.
+//!
+//! Instead of a Fiat-Shamir proof of knowledge, a contributor who mixed delta into
+//! parameters with old exponent alpha publishes g1^alpha (taken from the input
+//! parameters) and g2^delta. Anyone can then check with pairings that the new
+//! g1^{alpha * delta} was obtained by mixing delta into exactly those inputs.
+use crate::encoding::{read_point, write_point};
+use ff::Field;
+use pairing_plus::bls12_381::{Bls12, Fq12, Fr, G1Affine, G2Affine};
+use pairing_plus::serdes::SerDes;
+use pairing_plus::Engine;
+use pairing_plus::{CurveAffine, CurveProjective};
+use std::io::{Read, Result, Write};
+
+#[derive(Debug, PartialEq)]
+pub struct UpdateProof {
+    pub(crate) g1_alpha_old: G1Affine, // g_1^alpha of the parameters we started from
+    pub(crate) g2_delta: G2Affine,     // g_2^delta, where delta is the exponent we mixed in
+}
+
+impl UpdateProof {
+    /// g_2^delta, the commitment to the exponent that was mixed in.
+    pub fn g2_delta(&self) -> G2Affine {
+        self.g2_delta
+    }
+}
+
+// Make an update proof for mixing delta into parameters whose first G1 power is g1_alpha_old.
+pub fn make_update_proof(g1_alpha_old: G1Affine, delta: Fr) -> UpdateProof {
+    UpdateProof {
+        g1_alpha_old,
+        g2_delta: G2Affine::one().mul(delta).into_affine(),
+    }
+}
+
+// Verify an update proof.
+// g2alpha_old is g_2^alpha of the parameters we started from,
+// and g1alpha_new is g_1^{alpha * delta} of the rerandomized parameters.
+// The points of the proof are in the subgroup, as deserialize checks that.
+pub fn verify_update_proof(
+    proof: &UpdateProof,
+    g2alpha_old: G2Affine,
+    g1alpha_new: G1Affine,
+) -> bool {
+    // check that delta is not 0
+    // check e(g1_alpha_old, g_2) == e(g_1, g2alpha_old)
+    // check e(g1_alpha_old, g2_delta) == e(g1alpha_new, g_2)
+    if proof.g2_delta.is_zero() {
+        return false;
+    }
+    let g2inv = {
+        let mut g = G2Affine::one();
+        g.negate();
+        g
+    };
+
+    Bls12::pairing_product(proof.g1_alpha_old, g2inv, G1Affine::one(), g2alpha_old) == Fq12::one()
+        && Bls12::pairing_product(proof.g1_alpha_old, proof.g2_delta, g1alpha_new, g2inv)
+            == Fq12::one()
+}
+
+impl SerDes for UpdateProof {
+    fn deserialize<R: Read>(r: &mut R, compressed: bool) -> Result<Self> {
+        let g1_alpha_old = read_point(r, compressed)?;
+        let g2_delta = read_point(r, compressed)?;
+        Ok(UpdateProof {
+            g1_alpha_old,
+            g2_delta,
+        })
+    }
+    fn serialize<W: Write>(&self, w: &mut W, compressed: bool) -> Result<()> {
+        write_point(w, &self.g1_alpha_old, compressed)?;
+        write_point(w, &self.g2_delta, compressed)?;
+        Ok(())
+    }
+}
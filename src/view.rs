@@ -0,0 +1,192 @@
+//! This file is part of the pointproofs-paramgen crate.
+//! It defines a read-only view of a parameter file backed by a memory map.
+//!
+//! Unlike `PointproofsParams::deserialize`, opening a view does not decode
+//! anything: each point is decoded from the mapped file the first time it is
+//! asked for, checked to be on the curve and in the subgroup, and kept for
+//! later accesses. Alternatively, a view can be opened against a pinned
+//! digest of the whole file (as printed by the `digest` command after a full
+//! check), in which case the file is hashed once and no subgroup checks are
+//! done at all.
+//!
+//! The view is meant for applications that use a few powers of the final
+//! parameters at every start. The commands of this crate do not use it:
+//! verifying or extending parameters involves every point, so they decode the
+//! whole file at once.
+use crate::digest::{digest_bytes, Digest};
+use crate::encoding::{point_size, read_point_unchecked};
+use crate::error::{Error, Result, MAX_N};
+use crate::format::{parse_header, FileHeader};
+use crate::gt_in_subgroup;
+use crate::subgroup::PointChecks;
+use memmap2::Mmap;
+use pairing_plus::bls12_381::{Fq12, G1Affine, G2Affine};
+use pairing_plus::serdes::SerDes;
+use pairing_plus::CurveAffine;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Size of an encoded GT element
+const GT_SIZE: usize = 576;
+
+pub struct ParamsView {
+    mmap: Mmap,
+    header: FileHeader,
+    n: usize,
+    // offsets of the G1 points, the G2 points and the GT element in the file
+    g1_offset: usize,
+    g2_offset: usize,
+    gt_offset: usize,
+    // whether the file matched a pinned digest, so that points need no subgroup check
+    trusted: bool,
+    // the points and GT element decoded and checked so far
+    g1_cache: Vec<Mutex<Option<G1Affine>>>,
+    g2_cache: Vec<Mutex<Option<G2Affine>>>,
+    gt_cache: Mutex<Option<Fq12>>,
+}
+
+impl ParamsView {
+    /// Maps a parameter file, checking each point the first time it is read.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // The map is read-only; like any reader of the file, we rely on it
+        // not being modified while we use it.
+        let mmap = unsafe { Mmap::map(&file)? };
+        ParamsView::from_mmap(mmap, false)
+    }
+
+    /// Maps a parameter file whose SHA-512 digest must equal `pinned`.
+    /// The file is trusted to be valid once the digest matches.
+    pub fn open_pinned<P: AsRef<Path>>(path: P, pinned: &Digest) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        if digest_bytes(&mmap[..])[..] != pinned[..] {
+            return Err(Error::Format(
+                "Parameter file does not match the pinned digest".to_string(),
+            ));
+        }
+        ParamsView::from_mmap(mmap, true)
+    }
+
+    fn from_mmap(mmap: Mmap, trusted: bool) -> Result<Self> {
+        let (header, header_len) = parse_header(&mmap[..])?;
+        if mmap.len() < header_len + 4 {
+            return Err(Error::Format("Parameter file is truncated".to_string()));
+        }
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&mmap[header_len..header_len + 4]);
+        let n = u32::from_le_bytes(buf) as usize;
+        if n > MAX_N || n == 0 {
+            return Err(Error::SizeLimit(n));
+        }
+
+        let g1_offset = header_len + 4;
+        let g2_offset = g1_offset + (2 * n - 1) * point_size::<G1Affine>(header.compressed);
+        let gt_offset = g2_offset + (2 * n - 1) * point_size::<G2Affine>(header.compressed);
+        let end = if header.include_gt {
+            gt_offset + GT_SIZE
+        } else {
+            gt_offset
+        };
+        if mmap.len() < end {
+            return Err(Error::Format("Parameter file is truncated".to_string()));
+        }
+
+        Ok(ParamsView {
+            mmap,
+            header,
+            n,
+            g1_offset,
+            g2_offset,
+            gt_offset,
+            trusted,
+            g1_cache: (0..2 * n - 1).map(|_| Mutex::new(None)).collect(),
+            g2_cache: (0..2 * n - 1).map(|_| Mutex::new(None)).collect(),
+            gt_cache: Mutex::new(None),
+        })
+    }
+
+    /// Parameter N.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The settings the file was written with.
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    // Position of g^{alpha^i} among the 2N - 1 stored points of one group
+    fn slot(&self, i: usize) -> Result<usize> {
+        if i == 0 || i == self.n + 1 || i > 2 * self.n {
+            return Err(Error::InvalidInput(format!(
+                "No power alpha^{} in the parameters",
+                i
+            )));
+        }
+        Ok(if i <= self.n { i - 1 } else { i - 2 })
+    }
+
+    fn point<G: PointChecks>(
+        &self,
+        i: usize,
+        offset: usize,
+        cache: &[Mutex<Option<G>>],
+    ) -> Result<G> {
+        let slot = self.slot(i)?;
+        // a panic while the lock was held cannot have left a bad point in the cache
+        let mut cached = cache[slot].lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(p) = *cached {
+            return Ok(p);
+        }
+        let size = point_size::<G>(self.header.compressed);
+        let start = offset + slot * size;
+        let p: G =
+            read_point_unchecked(&mut &self.mmap[start..start + size], self.header.compressed)?;
+        if !self.trusted && !p.in_subgroup_by_endomorphism() {
+            return Err(Error::NotInSubgroup(format!(
+                "Point for alpha^{} is not in the subgroup",
+                i
+            )));
+        }
+        *cached = Some(p);
+        Ok(p)
+    }
+
+    /// g1^{alpha^i}, for 1 <= i <= 2N and i != N + 1.
+    pub fn g1_power(&self, i: usize) -> Result<G1Affine> {
+        self.point(i, self.g1_offset, &self.g1_cache)
+    }
+
+    /// g2^{alpha^i}, for 1 <= i <= 2N and i != N + 1.
+    pub fn g2_power(&self, i: usize) -> Result<G2Affine> {
+        self.point(i, self.g2_offset, &self.g2_cache)
+    }
+
+    /// e(g1, g2)^{alpha^{N+1}}, read from the file or recomputed if the file leaves it out.
+    pub fn gt_alpha_nplus1(&self) -> Result<Fq12> {
+        let mut cached = self.gt_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(gt) = *cached {
+            return Ok(gt);
+        }
+        let gt = if self.header.include_gt {
+            let gt = Fq12::deserialize(
+                &mut &self.mmap[self.gt_offset..self.gt_offset + GT_SIZE],
+                true,
+            )?;
+            if !self.trusted && !gt_in_subgroup(&gt) {
+                return Err(Error::NotInSubgroup(
+                    "gt_alpha_nplus1 is not an element of order r in GT".to_string(),
+                ));
+            }
+            gt
+        } else {
+            let g1 = self.g1_power(self.n)?;
+            let g2 = self.g2_power(1)?;
+            g1.pairing_with(&g2)
+        };
+        *cached = Some(gt);
+        Ok(gt)
+    }
+}
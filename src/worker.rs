@@ -0,0 +1,643 @@
+//! This file is part of the pointproofs-paramgen binary.
+//! It runs the secret-handling part of evolve in an isolated worker process.
+//!
+//! The worker is forked before anything is parsed, so that a malicious input
+//! file can only ever compromise a process that cannot leak the secret. It
+//! cannot produce core dumps, and before touching its input it installs a
+//! seccomp filter under which it can read only the input file, write only the
+//! output file and the pipe to its parent, and otherwise only allocate
+//! memory, draw randomness, start threads (but not processes) and inspect
+//! files it already has open. Every other system call, including opening
+//! files and using the network, fails with EPERM.
+//!
+//! The worker reports back over the pipe with length-prefixed messages: the
+//! lines and fields of the command's report, progress updates, and finally
+//! either `Done` or the error it failed with. It exits as soon as the output
+//! is written. Its secrets are zeroized as soon as they are no longer needed,
+//! and the kernel clears the rest of its memory when it exits.
+use crate::Report;
+use pointproofs_paramgen::error::{Error, Result};
+use pointproofs_paramgen::progress::{Phase, Progress};
+use pointproofs_paramgen::Rejection;
+use serde_json::Value;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+
+/// Whether evolve can run in an isolated worker on this platform.
+pub const SUPPORTED: bool = cfg!(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+));
+
+// Longest message the parent accepts from the worker
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+// Held while a message is written to the pipe
+static SENDING: Mutex<()> = Mutex::new(());
+
+/// What the worker tells its parent.
+pub enum Message {
+    Progress(Phase, usize, usize),
+    Say(String),
+    Set(String, Value),
+    Time(String, u64),
+    Done,
+    Failed(Error),
+}
+
+const TAG_PROGRESS: u8 = 1;
+const TAG_SAY: u8 = 2;
+const TAG_SET: u8 = 3;
+const TAG_TIME: u8 = 4;
+const TAG_DONE: u8 = 5;
+const TAG_FAILED: u8 = 6;
+
+impl Message {
+    // Body of the message, without the length prefix
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = vec![];
+        match self {
+            Message::Progress(phase, done, total) => {
+                buf.push(TAG_PROGRESS);
+                buf.push(phase_to_byte(*phase));
+                put_u64(&mut buf, *done as u64);
+                put_u64(&mut buf, *total as u64);
+            }
+            Message::Say(line) => {
+                buf.push(TAG_SAY);
+                put_str(&mut buf, line);
+            }
+            Message::Set(key, value) => {
+                buf.push(TAG_SET);
+                put_str(&mut buf, key);
+                put_str(&mut buf, &value.to_string());
+            }
+            Message::Time(phase, ms) => {
+                buf.push(TAG_TIME);
+                put_str(&mut buf, phase);
+                put_u64(&mut buf, *ms);
+            }
+            Message::Done => buf.push(TAG_DONE),
+            Message::Failed(e) => {
+                buf.push(TAG_FAILED);
+                encode_error(&mut buf, e);
+            }
+        }
+        buf
+    }
+
+    fn decode(mut r: &[u8]) -> Result<Self> {
+        let r = &mut r;
+        let msg = match get_u8(r)? {
+            TAG_PROGRESS => {
+                let phase = phase_from_byte(get_u8(r)?)?;
+                Message::Progress(phase, get_u64(r)? as usize, get_u64(r)? as usize)
+            }
+            TAG_SAY => Message::Say(get_str(r)?),
+            TAG_SET => {
+                let key = get_str(r)?;
+                let value = serde_json::from_str(&get_str(r)?).map_err(protocol_error)?;
+                Message::Set(key, value)
+            }
+            TAG_TIME => Message::Time(get_str(r)?, get_u64(r)?),
+            TAG_DONE => Message::Done,
+            TAG_FAILED => Message::Failed(decode_error(r)?),
+            _ => return Err(protocol_error("unknown message")),
+        };
+        if !r.is_empty() {
+            return Err(protocol_error("trailing bytes"));
+        }
+        Ok(msg)
+    }
+
+    // The message with its length prefix
+    fn frame(&self) -> Vec<u8> {
+        let body = self.encode();
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        frame
+    }
+
+    /// Sends the message. Threads can send concurrently: a message can be
+    /// longer than PIPE_BUF, so that the pipe may split it across several
+    /// writes, but messages are sent one at a time and never interleave.
+    /// Errors are ignored: if the parent is gone, nobody is listening.
+    pub fn send(&self, mut pipe: &File) {
+        let frame = self.frame();
+        // a thread that panicked while sending has sent nothing worth protecting
+        let _lock = SENDING.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = pipe.write_all(&frame);
+    }
+
+    // Reads the next message, or None once the worker has closed the pipe
+    fn receive<R: Read>(r: &mut R) -> Result<Option<Self>> {
+        let mut len = [0u8; 4];
+        match r.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(Error::Io(e)),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_MESSAGE_LEN {
+            return Err(protocol_error("message too long"));
+        }
+        let mut body = vec![0u8; len];
+        r.read_exact(&mut body).map_err(Error::Io)?;
+        Message::decode(&body).map(Some)
+    }
+}
+
+fn protocol_error<E: ToString>(e: E) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::Other,
+        format!("bad message from the evolve worker: {}", e.to_string()),
+    ))
+}
+
+fn phase_to_byte(phase: Phase) -> u8 {
+    match phase {
+        Phase::Generate => 0,
+        Phase::Rerandomize => 1,
+        Phase::Check => 2,
+    }
+}
+
+fn phase_from_byte(b: u8) -> Result<Phase> {
+    match b {
+        0 => Ok(Phase::Generate),
+        1 => Ok(Phase::Rerandomize),
+        2 => Ok(Phase::Check),
+        _ => Err(protocol_error("unknown phase")),
+    }
+}
+
+// Errors are sent as a variant byte, then a number, then a string, so that
+// the parent exits with the same code as an unisolated evolve would
+fn encode_error(buf: &mut Vec<u8>, e: &Error) {
+    let (variant, number, text): (u8, u64, String) = match e {
+        Error::Io(e) => (0, 0, e.to_string()),
+        Error::Format(msg) => (1, 0, msg.clone()),
+        Error::SizeLimit(n) => (2, *n as u64, String::new()),
+        Error::NotInSubgroup(msg) => (3, 0, msg.clone()),
+        Error::Inconsistent => (4, 0, String::new()),
+        Error::WeakAlpha(msg) => (5, 0, msg.clone()),
+        Error::InvalidInput(msg) => (6, 0, msg.clone()),
+        Error::Cancelled => (7, 0, String::new()),
+        Error::Fault(msg) => (8, 0, msg.clone()),
+        Error::Rejected(r) => match r {
+            Rejection::Missing => (16, 0, String::new()),
+            Rejection::Malformed(msg) => (17, 0, msg.clone()),
+            Rejection::SettingsMismatch => (18, 0, String::new()),
+            Rejection::DegenerateExponent(k) => (19, *k, String::new()),
+            Rejection::Replayed(i) => (20, *i as u64, String::new()),
+            Rejection::BadProof => (21, 0, String::new()),
+            Rejection::Inconsistent => (22, 0, String::new()),
+            Rejection::OutsideSlot => (23, 0, String::new()),
+            Rejection::BadSignature => (24, 0, String::new()),
+            Rejection::ReceiptMismatch(msg) => (25, 0, msg.clone()),
+        },
+    };
+    buf.push(variant);
+    put_u64(buf, number);
+    put_str(buf, &text);
+}
+
+fn decode_error(r: &mut &[u8]) -> Result<Error> {
+    let variant = get_u8(r)?;
+    let number = get_u64(r)?;
+    let text = get_str(r)?;
+    Ok(match variant {
+        0 => Error::Io(io::Error::new(io::ErrorKind::Other, text)),
+        1 => Error::Format(text),
+        2 => Error::SizeLimit(number as usize),
+        3 => Error::NotInSubgroup(text),
+        4 => Error::Inconsistent,
+        5 => Error::WeakAlpha(text),
+        6 => Error::InvalidInput(text),
+        7 => Error::Cancelled,
+        8 => Error::Fault(text),
+        16 => Error::Rejected(Rejection::Missing),
+        17 => Error::Rejected(Rejection::Malformed(text)),
+        18 => Error::Rejected(Rejection::SettingsMismatch),
+        19 => Error::Rejected(Rejection::DegenerateExponent(number)),
+        20 => Error::Rejected(Rejection::Replayed(number as usize)),
+        21 => Error::Rejected(Rejection::BadProof),
+        22 => Error::Rejected(Rejection::Inconsistent),
+        23 => Error::Rejected(Rejection::OutsideSlot),
+        24 => Error::Rejected(Rejection::BadSignature),
+        25 => Error::Rejected(Rejection::ReceiptMismatch(text)),
+        _ => return Err(protocol_error("unknown error")),
+    })
+}
+
+fn put_u64(buf: &mut Vec<u8>, x: u64) {
+    buf.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn take<'a>(r: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if r.len() < len {
+        return Err(protocol_error("truncated message"));
+    }
+    let (head, rest) = r.split_at(len);
+    *r = rest;
+    Ok(head)
+}
+
+fn get_u8(r: &mut &[u8]) -> Result<u8> {
+    Ok(take(r, 1)?[0])
+}
+
+fn get_u64(r: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(take(r, 8)?.try_into().unwrap()))
+}
+
+fn get_str(r: &mut &[u8]) -> Result<String> {
+    let len = u32::from_le_bytes(take(r, 4)?.try_into().unwrap()) as usize;
+    String::from_utf8(take(r, len)?.to_vec()).map_err(protocol_error)
+}
+
+/// Runs `work` on the input and output files in an isolated worker, relaying
+/// its report and progress into `report`.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub(crate) fn run_isolated<F>(input: File, output: File, report: &mut Report, work: F) -> Result<()>
+where
+    F: FnOnce(File, File, &mut Report) -> Result<()>,
+{
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::sync::Arc;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let (mut from_worker, to_parent) =
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error().into()),
+        0 => {
+            drop(from_worker);
+            // a panic must not unwind into the parent's code
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+                let pipe = Arc::new(to_parent);
+                let readable = [input.as_raw_fd()];
+                let writable = [output.as_raw_fd(), pipe.as_raw_fd()];
+                let result = sandbox::enter(&readable, &writable).and_then(|()| {
+                    let mut report = Report::forwarding(pipe.clone());
+                    work(input, output, &mut report)
+                });
+                match result {
+                    Ok(()) => Message::Done.send(&pipe),
+                    Err(e) => Message::Failed(e).send(&pipe),
+                }
+            }));
+            unsafe { libc::_exit(0) }
+        }
+        pid => {
+            drop(to_parent);
+            drop(input);
+            drop(output);
+            let bar = report.progress_bar();
+            let mut outcome = None;
+            let relayed = loop {
+                match Message::receive(&mut from_worker) {
+                    Ok(None) => break Ok(()),
+                    Ok(Some(msg)) => match msg {
+                        Message::Progress(phase, done, total) => bar.update(phase, done, total),
+                        Message::Say(line) => report.say(&line),
+                        Message::Set(key, value) => report.set(&key, value),
+                        Message::Time(phase, ms) => report.set_time(&phase, ms),
+                        Message::Done => outcome = Some(Ok(())),
+                        Message::Failed(e) => outcome = Some(Err(e)),
+                    },
+                    Err(e) => break Err(e),
+                }
+            };
+            drop(bar);
+            let mut status = 0;
+            unsafe { libc::waitpid(pid, &mut status, 0) };
+            relayed?;
+            match outcome {
+                Some(outcome) => outcome,
+                None if libc::WIFSIGNALED(status) => Err(Error::Io(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "the evolve worker was killed by signal {}",
+                        libc::WTERMSIG(status)
+                    ),
+                ))),
+                None => Err(Error::Io(io::Error::new(
+                    io::ErrorKind::Other,
+                    "the evolve worker exited without reporting a result",
+                ))),
+            }
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub(crate) fn run_isolated<F>(
+    _input: File,
+    _output: File,
+    _report: &mut Report,
+    _work: F,
+) -> Result<()>
+where
+    F: FnOnce(File, File, &mut Report) -> Result<()>,
+{
+    Err(Error::InvalidInput(
+        "The isolated evolve worker is not supported on this platform".to_string(),
+    ))
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sandbox {
+    use pointproofs_paramgen::error::Result;
+    use std::io;
+    use std::os::unix::io::RawFd;
+
+    // Classic BPF opcodes, from linux/bpf_common.h
+    // BPF_LD | BPF_W | BPF_ABS
+    const BPF_LD_W_ABS: u16 = 0x20;
+    // BPF_JMP | BPF_JEQ | BPF_K
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    // BPF_RET | BPF_K
+    const BPF_RET_K: u16 = 0x06;
+    // BPF_ALU | BPF_AND | BPF_K
+    const BPF_ALU_AND_K: u16 = 0x54;
+
+    // From linux/seccomp.h
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    // Offsets into struct seccomp_data
+    const OFFSET_NR: u32 = 0;
+    const OFFSET_ARCH: u32 = 4;
+    // low half of argument i, on a little-endian machine
+    const fn offset_arg(i: u32) -> u32 {
+        16 + 8 * i
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    // Allowed with any arguments: memory, randomness, futexes, signals, and exiting
+    const ALLOWED: [libc::c_long; 23] = [
+        libc::SYS_exit,
+        libc::SYS_exit_group,
+        libc::SYS_brk,
+        libc::SYS_mmap,
+        libc::SYS_munmap,
+        libc::SYS_mremap,
+        libc::SYS_mprotect,
+        libc::SYS_madvise,
+        libc::SYS_futex,
+        libc::SYS_getrandom,
+        libc::SYS_rt_sigreturn,
+        libc::SYS_rt_sigaction,
+        libc::SYS_rt_sigprocmask,
+        libc::SYS_sigaltstack,
+        libc::SYS_sched_yield,
+        libc::SYS_sched_getaffinity,
+        libc::SYS_set_robust_list,
+        libc::SYS_rseq,
+        libc::SYS_gettid,
+        libc::SYS_close,
+        libc::SYS_fstat,
+        libc::SYS_lseek,
+        libc::SYS_clock_gettime,
+    ];
+
+    fn stmt(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code: BPF_JMP_JEQ_K,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    // Allows syscall nr only when its first argument is one of fds
+    fn allow_on_fds(filter: &mut Vec<libc::sock_filter>, nr: libc::c_long, fds: &[RawFd]) {
+        filter.push(stmt(BPF_LD_W_ABS, OFFSET_NR));
+        filter.push(jump(nr as u32, 0, (1 + 2 * fds.len()) as u8));
+        filter.push(stmt(BPF_LD_W_ABS, offset_arg(0)));
+        for &fd in fds {
+            filter.push(jump(fd as u32, 0, 1));
+            filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+        }
+    }
+
+    // Allows syscall nr only when argument arg has all the bits of flags set
+    fn allow_with_flags(
+        filter: &mut Vec<libc::sock_filter>,
+        nr: libc::c_long,
+        arg: u32,
+        flags: u32,
+    ) {
+        filter.push(stmt(BPF_LD_W_ABS, OFFSET_NR));
+        filter.push(jump(nr as u32, 0, 4));
+        filter.push(stmt(BPF_LD_W_ABS, offset_arg(arg)));
+        filter.push(stmt(BPF_ALU_AND_K, flags));
+        filter.push(jump(flags, 0, 1));
+        filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+    }
+
+    // Fails syscall nr with errno
+    fn fail_with(filter: &mut Vec<libc::sock_filter>, nr: libc::c_long, errno: libc::c_int) {
+        filter.push(stmt(BPF_LD_W_ABS, OFFSET_NR));
+        filter.push(jump(nr as u32, 0, 1));
+        filter.push(stmt(BPF_RET_K, SECCOMP_RET_ERRNO | errno as u32));
+    }
+
+    fn check(ret: libc::c_int) -> Result<()> {
+        if ret != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Disables core dumps and restricts the process to reading `readable`
+    /// and writing `writable`. This cannot be undone.
+    pub fn enter(readable: &[RawFd], writable: &[RawFd]) -> Result<()> {
+        let no_core = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        unsafe {
+            check(libc::setrlimit(libc::RLIMIT_CORE, &no_core))?;
+            check(libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0))?;
+        }
+
+        let mut filter = vec![
+            stmt(BPF_LD_W_ABS, OFFSET_ARCH),
+            jump(AUDIT_ARCH, 1, 0),
+            stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD_W_ABS, OFFSET_NR),
+        ];
+        for &nr in ALLOWED.iter() {
+            filter.push(jump(nr as u32, 0, 1));
+            filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+        }
+        allow_on_fds(&mut filter, libc::SYS_read, readable);
+        allow_on_fds(&mut filter, libc::SYS_write, writable);
+        // clone only for threads, which share the filter, never for a new process
+        let thread = libc::CLONE_VM | libc::CLONE_SIGHAND | libc::CLONE_THREAD;
+        allow_with_flags(&mut filter, libc::SYS_clone, 0, thread as u32);
+        // the flags of clone3 are out of the filter's reach, in memory;
+        // without it, the C library starts threads with clone
+        fail_with(&mut filter, libc::SYS_clone3, libc::ENOSYS);
+        // stat calls only on open files (fstat is one on newer C libraries),
+        // which still leaves the metadata, though not the contents, of
+        // absolute paths within reach
+        let empty_path = libc::AT_EMPTY_PATH as u32;
+        allow_with_flags(&mut filter, libc::SYS_newfstatat, 3, empty_path);
+        allow_with_flags(&mut filter, libc::SYS_statx, 2, empty_path);
+        filter.push(stmt(BPF_RET_K, SECCOMP_RET_ERRNO | libc::EPERM as u32));
+
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+        unsafe {
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+            check(libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &program as *const libc::sock_fprog,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(msg: Message) {
+        let frame = msg.frame();
+        let mut r = &frame[..];
+        let decoded = Message::receive(&mut r).unwrap().unwrap();
+        assert_eq!(decoded.encode(), msg.encode());
+        assert!(Message::receive(&mut r).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        round_trip(Message::Progress(Phase::Check, 3, 10));
+        round_trip(Message::Say("Checking the contribution".to_string()));
+        // longer than PIPE_BUF, like a receipt
+        round_trip(Message::Set(
+            "receipt".to_string(),
+            json!("r".repeat(10_000)),
+        ));
+        round_trip(Message::Set(
+            "resumed".to_string(),
+            json!({"rounds": 2, "sums": [1, 2]}),
+        ));
+        round_trip(Message::Time("check".to_string(), 1234));
+        round_trip(Message::Done);
+        round_trip(Message::Failed(Error::Rejected(Rejection::Replayed(4))));
+        round_trip(Message::Failed(Error::SizeLimit(1 << 30)));
+
+        let mut frame = Message::Say("x".to_string()).frame();
+        frame.push(0);
+        frame[0] += 1;
+        assert!(Message::receive(&mut &frame[..]).is_err());
+        let frame = Message::Done.frame();
+        assert!(Message::receive(&mut &frame[..frame.len() - 1]).is_err());
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    #[test]
+    fn test_run_isolated() {
+        let path = std::env::temp_dir().join(format!("worker-test-{}", std::process::id()));
+        std::fs::write(&path, b"input").unwrap();
+        let files = || {
+            let output = std::fs::OpenOptions::new().append(true).open(&path);
+            (File::open(&path).unwrap(), output.unwrap())
+        };
+
+        // the worker can read its input, use threads, and report back
+        let (input, output) = files();
+        let mut report = Report::new(true, "evolve");
+        run_isolated(input, output, &mut report, |mut input, _, report| {
+            let mut buf = vec![];
+            input.read_to_end(&mut buf)?;
+            let len = std::thread::spawn(move || buf.len()).join().unwrap();
+            report.set("read", len);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(report.fields["read"], json!(5));
+
+        // but cannot open files or start processes
+        let (input, output) = files();
+        let mut report = Report::new(true, "evolve");
+        let res = run_isolated(input, output, &mut report, |_, _, _| {
+            File::open("/etc/hostname")?;
+            Ok(())
+        });
+        assert!(matches!(res, Err(Error::Io(_))));
+        let (input, output) = files();
+        let res = run_isolated(input, output, &mut report, |_, _, _| {
+            std::process::Command::new("true").status()?;
+            Ok(())
+        });
+        assert!(matches!(res, Err(Error::Io(_))));
+
+        // its errors come back as they were
+        let (input, output) = files();
+        let res = run_isolated(input, output, &mut report, |_, _, _| {
+            Err(Error::Rejected(Rejection::BadProof))
+        });
+        assert!(matches!(res, Err(Error::Rejected(Rejection::BadProof))));
+
+        // and so does its death, which the signal handlers must not turn
+        // into an endless loop of faults
+        let (input, output) = files();
+        let res = run_isolated(input, output, &mut report, |_, _, _| {
+            unsafe { std::ptr::write_volatile(std::ptr::null_mut::<u8>(), 0) };
+            Ok(())
+        });
+        match res {
+            Err(e) => assert!(e.to_string().contains("killed by signal")),
+            Ok(()) => panic!("a crashed worker succeeded"),
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}